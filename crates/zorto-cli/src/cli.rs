@@ -151,6 +151,12 @@ enum Commands {
         cache: bool,
     },
 
+    /// Inspect and manage the code block execution cache
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+
     /// Install zorto skill files for AI agents
     #[command(hide = true)]
     Skill {
@@ -159,6 +165,34 @@ enum Commands {
     },
 }
 
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Show the number, size and languages of cached results
+    Info,
+
+    /// Remove cached results, images and diagrams the last build did not use
+    Prune {
+        /// Only report what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+
+    /// Write the cached results to a .tar.gz archive
+    Export {
+        /// Archive to write
+        path: PathBuf,
+    },
+
+    /// Add the cached results from a .tar.gz archive
+    Import {
+        /// Archive to read
+        path: PathBuf,
+    },
+
+    /// Remove every cached result
+    Clear,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, ValueEnum)]
 enum Preset {
     Deck,
@@ -336,6 +370,7 @@ where
                 println!("Cleared code block cache");
             }
         }
+        Commands::Cache { command } => handle_cache(&root, command)?,
         Commands::Init { name, template } => {
             // In a TTY we always run the interactive flow — positional args
             // become prompt defaults rather than skipping prompts entirely,
@@ -380,6 +415,89 @@ where
     Ok(())
 }
 
+fn handle_cache(root: &std::path::Path, command: CacheCommands) -> anyhow::Result<()> {
+    use zorto_core::cache;
+
+    match command {
+        CacheCommands::Info => {
+            let info = cache::info(root);
+            println!(
+                "{} cached {} ({})",
+                info.entries,
+                if info.entries == 1 {
+                    "result"
+                } else {
+                    "results"
+                },
+                format_bytes(info.bytes)
+            );
+            match info.referenced {
+                Some(referenced) => println!(
+                    "{referenced} used by the last build, {} unreferenced",
+                    info.entries - referenced
+                ),
+                None => println!("No build has recorded which results it used yet"),
+            }
+            for (language, count) in &info.languages {
+                println!("  {language}: {count}");
+            }
+            if info.legacy_files > 0 {
+                println!(
+                    "{} per-page cache files from an older zorto (removed by `zorto cache prune`)",
+                    info.legacy_files
+                );
+            }
+        }
+        CacheCommands::Prune { dry_run } => {
+            let summary = cache::prune(root, dry_run)?;
+            println!(
+                "{} {} {} ({})",
+                if dry_run { "Would remove" } else { "Removed" },
+                summary.removed,
+                if summary.removed == 1 {
+                    "file"
+                } else {
+                    "files"
+                },
+                format_bytes(summary.bytes)
+            );
+        }
+        CacheCommands::Export { path } => {
+            let file = std::fs::File::create(&path)
+                .with_context(|| format!("failed to create {}", path.display()))?;
+            let count = cache::export_archive(root, std::io::BufWriter::new(file))?;
+            println!("Exported {count} cached results to {}", path.display());
+        }
+        CacheCommands::Import { path } => {
+            let file = std::fs::File::open(&path)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            let summary = cache::import_archive(root, std::io::BufReader::new(file))?;
+            println!(
+                "Imported {} cached results ({} skipped)",
+                summary.imported, summary.skipped
+            );
+        }
+        CacheCommands::Clear => {
+            cache::clear_cache(root)?;
+            println!("Cleared code block cache");
+        }
+    }
+    Ok(())
+}
+
+/// Format a byte count for cache summaries.
+fn format_bytes(bytes: u64) -> String {
+    const KIB: f64 = 1024.0;
+    let b = bytes as f64;
+    if b < KIB {
+        format!("{bytes} B")
+    } else if b < KIB * KIB {
+        format!("{:.1} KiB", b / KIB)
+    } else {
+        format!("{:.1} MiB", b / (KIB * KIB))
+    }
+}
+
 /// Format a build duration for the `Built …` summary line.
 ///
 /// Sub-second durations render as `123ms` (integer); anything >= 1s as
@@ -733,6 +851,31 @@ mod tests {
         assert!(matches!(cli.command, Some(Commands::Skill { .. })));
     }
 
+    #[test]
+    fn parse_cache_subcommands() {
+        let cli = Cli::parse_from(["zorto", "cache", "prune", "--dry-run"]);
+        assert!(matches!(
+            cli.command,
+            Some(Commands::Cache {
+                command: CacheCommands::Prune { dry_run: true }
+            })
+        ));
+        let cli = Cli::parse_from(["zorto", "cache", "export", "cache.tar.gz"]);
+        match cli.command {
+            Some(Commands::Cache {
+                command: CacheCommands::Export { path },
+            }) => assert_eq!(path, PathBuf::from("cache.tar.gz")),
+            _ => panic!("expected cache export"),
+        }
+    }
+
+    #[test]
+    fn format_bytes_units() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(2048), "2.0 KiB");
+        assert_eq!(format_bytes(3 * 1024 * 1024), "3.0 MiB");
+    }
+
     #[test]
     fn preview_defaults_to_drafts_on() {
         // No --no-drafts → drafts should be included. Marketing users previewing
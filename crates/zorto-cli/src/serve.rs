@@ -5,10 +5,11 @@ use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
 use axum::http::{Request, StatusCode, header};
 use axum::response::{IntoResponse, Response};
 use axum::routing::get;
-use notify_debouncer_mini::{DebouncedEventKind, new_debouncer};
+use notify_debouncer_mini::{DebouncedEventKind, Debouncer, new_debouncer};
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::path::{Path, PathBuf};
-use std::sync::LazyLock;
+use std::sync::{Arc, LazyLock, Mutex};
 use std::time::Duration;
 use tokio::sync::broadcast;
 use zorto_core::content::Section;
@@ -181,13 +182,15 @@ pub async fn serve(cfg: &ServeConfig<'_>) -> anyhow::Result<()> {
     let (notify_tx, notify_rx) = std::sync::mpsc::channel();
 
     let mut debouncer = new_debouncer(Duration::from_millis(DEBOUNCE_MS), notify_tx)?;
-    let watch_dirs = ["content", "templates", "sass", "static"];
+    let mut watched = HashSet::new();
+    let watch_dirs = ["content", "templates", "sass", "static", "data"];
     for dir in &watch_dirs {
         let path = cfg.root.join(dir);
         if path.exists() {
             debouncer
                 .watcher()
                 .watch(&path, notify::RecursiveMode::Recursive)?;
+            watched.insert(std::fs::canonicalize(&path).unwrap_or(path));
         }
     }
     // Also watch any external content_dirs declared in config.toml so edits
@@ -204,6 +207,8 @@ pub async fn serve(cfg: &ServeConfig<'_>) -> anyhow::Result<()> {
                     "Warning: cannot watch content_dir {}: {e}",
                     external.display()
                 );
+            } else {
+                watched.insert(std::fs::canonicalize(&external).unwrap_or(external));
             }
         }
     }
@@ -213,6 +218,11 @@ pub async fn serve(cfg: &ServeConfig<'_>) -> anyhow::Result<()> {
             .watcher()
             .watch(&config_path, notify::RecursiveMode::NonRecursive)?;
     }
+    // Files that code blocks declared with `deps=` or templates read with
+    // `load_data()` can live anywhere; more directories are watched as
+    // rebuilds discover them.
+    let debouncer = Arc::new(Mutex::new(debouncer));
+    watch_data_dirs(&debouncer, &site, &mut watched);
 
     // Blocking thread to bridge std::sync::mpsc -> tokio::sync::mpsc
     std::thread::spawn(move || {
@@ -223,17 +233,11 @@ pub async fn serve(cfg: &ServeConfig<'_>) -> anyhow::Result<()> {
         }
     });
 
-    // Spawn the async watcher
-    let rebuild_cfg = RebuildConfig {
-        root: cfg.root.to_path_buf(),
-        output: cfg.output_dir.to_path_buf(),
-        drafts: cfg.drafts,
-        no_exec: cfg.no_exec,
-        sandbox: cfg.sandbox.map(|p| p.to_path_buf()),
-        base_url,
-    };
+    // Spawn the async watcher. It keeps the built site around so later
+    // changes only re-render what depends on them.
+    let watcher = Arc::clone(&debouncer);
     let watcher_handle = tokio::spawn(async move {
-        watch_and_rebuild(rebuild_cfg, reload_tx, watch_rx).await;
+        watch_and_rebuild(site, reload_tx, watch_rx, watcher, watched).await;
     });
 
     // Start server — ctrl+c cancels everything
@@ -410,52 +414,69 @@ fn inject_livereload(html: &str) -> String {
     }
 }
 
-/// Owned configuration for the file watcher rebuild loop.
-struct RebuildConfig {
-    root: PathBuf,
-    output: PathBuf,
-    drafts: bool,
-    no_exec: bool,
-    sandbox: Option<PathBuf>,
-    base_url: String,
+/// Watch the directories of files that code blocks declared with `deps=` or
+/// templates read with `load_data()`, skipping those inside an already
+/// watched directory.
+fn watch_data_dirs(
+    debouncer: &Mutex<Debouncer<notify::RecommendedWatcher>>,
+    site: &zorto_core::site::Site,
+    watched: &mut HashSet<PathBuf>,
+) {
+    let mut debouncer = debouncer.lock().unwrap_or_else(|e| e.into_inner());
+    for dir in site.dependencies().data_dirs() {
+        if !dir.is_dir() || dir.ancestors().any(|a| watched.contains(a)) {
+            continue;
+        }
+        match debouncer
+            .watcher()
+            .watch(&dir, notify::RecursiveMode::Recursive)
+        {
+            Ok(()) => {
+                watched.insert(dir);
+            }
+            Err(e) => eprintln!("Warning: cannot watch {}: {e}", dir.display()),
+        }
+    }
 }
 
+/// Rebuild `site` incrementally whenever the watcher reports changed paths.
 async fn watch_and_rebuild(
-    cfg: RebuildConfig,
+    mut site: zorto_core::site::Site,
     reload_tx: broadcast::Sender<LivereloadMsg>,
     mut watch_rx: tokio::sync::mpsc::Receiver<
         Result<Vec<notify_debouncer_mini::DebouncedEvent>, notify::Error>,
     >,
+    debouncer: Arc<Mutex<Debouncer<notify::RecommendedWatcher>>>,
+    mut watched: HashSet<PathBuf>,
 ) {
     while let Some(event) = watch_rx.recv().await {
         if let Ok(events) = event {
-            let has_changes = events
-                .iter()
-                .any(|e| matches!(e.kind, DebouncedEventKind::Any));
+            let changed: Vec<PathBuf> = events
+                .into_iter()
+                .filter(|e| matches!(e.kind, DebouncedEventKind::Any))
+                .map(|e| e.path)
+                .collect();
 
-            if has_changes {
+            if !changed.is_empty() {
                 let rebuild_start = std::time::Instant::now();
                 println!("Change detected, rebuilding...");
-                match zorto_core::site::Site::load(&cfg.root, &cfg.output, cfg.drafts) {
-                    Ok(mut site) => {
-                        site.no_exec = cfg.no_exec;
-                        site.sandbox = cfg.sandbox.clone();
-                        site.set_base_url(cfg.base_url.clone());
-                        match site.build() {
-                            Ok(()) => {
-                                let ms = rebuild_start.elapsed().as_millis();
-                                println!("Rebuilt in {ms}ms.");
-                                let _ = reload_tx.send(LivereloadMsg::Clear);
-                                let _ = reload_tx.send(LivereloadMsg::Reload);
-                            }
-                            Err(e) => {
-                                eprintln!("Build error: {e}");
-                                let _ = reload_tx.send(LivereloadMsg::Error(format!("{e:#}")));
-                            }
+                match site.rebuild(&changed) {
+                    Ok(summary) => {
+                        watch_data_dirs(&debouncer, &site, &mut watched);
+                        let ms = rebuild_start.elapsed().as_millis();
+                        if summary.full {
+                            println!("Rebuilt in {ms}ms.");
+                        } else {
+                            println!(
+                                "Rebuilt in {ms}ms ({} rendered, {} written).",
+                                summary.rendered, summary.written
+                            );
                         }
+                        let _ = reload_tx.send(LivereloadMsg::Clear);
+                        let _ = reload_tx.send(LivereloadMsg::Reload);
                     }
                     Err(e) => {
-                        eprintln!("Load error: {e}");
+                        eprintln!("Build error: {e}");
                         let _ = reload_tx.send(LivereloadMsg::Error(format!("{e:#}")));
                     }
                 }
@@ -558,6 +579,10 @@ mod tests {
                 render_pages: true,
                 extra: Default::default(),
                 relative_path: "_index.md".into(),
+                lang: "en".into(),
+                translations: vec![],
+                exec_data: Default::default(),
+                toc: vec![],
             },
             Section {
                 title: "Deck".into(),
@@ -573,6 +598,10 @@ mod tests {
                 render_pages: false,
                 extra: Default::default(),
                 relative_path: "intro/_index.md".into(),
+                lang: "en".into(),
+                translations: vec![],
+                exec_data: Default::default(),
+                toc: vec![],
             },
         ];
         let url = preview_open_url("http://127.0.0.1:1111", sections.iter());
@@ -596,6 +625,10 @@ mod tests {
                 render_pages: false,
                 extra: Default::default(),
                 relative_path: "intro/_index.md".into(),
+                lang: "en".into(),
+                translations: vec![],
+                exec_data: Default::default(),
+                toc: vec![],
             },
             Section {
                 title: "Deck Two".into(),
@@ -611,6 +644,10 @@ mod tests {
                 render_pages: false,
                 extra: Default::default(),
                 relative_path: "deep-dive/_index.md".into(),
+                lang: "en".into(),
+                translations: vec![],
+                exec_data: Default::default(),
+                toc: vec![],
             },
         ];
         let url = preview_open_url("http://127.0.0.1:1111", sections.iter());
@@ -1,16 +1,47 @@
+//! Content-addressed store for executed code block outputs.
+//!
+//! Each result is stored once under its [`block_cache_key`] at
+//! `.zorto/cache/objects/{key[..2]}/{key}.json`, independent of the page it
+//! came from, so moved pages, reordered blocks and pages repeating the same
+//! code all hit. Declared input files and environment variables
+//! (`[cache] inputs` / `[cache] env`) are folded into every key with
+//! [`with_inputs`]. Each build records the keys it used in a manifest, along
+//! with the processed images and diagrams it kept in `images/` and
+//! `diagrams/`, which [`prune`] uses to garbage-collect unreferenced entries;
+//! [`export_archive`] and [`import_archive`] move the store between machines
+//! as a tarball.
+
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 
+use crate::config::CacheConfig;
+use crate::execute::{ExecutableBlock, VizOutput};
+
 /// Directory name for the cache inside the site root.
 const CACHE_DIR: &str = ".zorto/cache";
 
+/// Directory of the object store inside the cache directory.
+const OBJECTS_DIR: &str = "objects";
+
+/// File recording the keys referenced by the last build.
+const MANIFEST_FILE: &str = "manifest.json";
+
+/// Directories of generated files inside the cache directory, listed in the
+/// manifest by path: processed images and rendered diagrams.
+const GENERATED_DIRS: [&str; 2] = ["images", "diagrams"];
+
+/// Version of the manifest format.
+const MANIFEST_VERSION: u32 = 1;
+
 /// A single cached block result.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CachedBlock {
-    /// SHA-256 hex digest of the block source code.
-    pub hash: String,
+    /// Language of the block that produced the result.
+    #[serde(default)]
+    pub language: String,
     /// Captured stdout (may be empty).
     pub output: Option<String>,
     /// Captured stderr / error message (may be empty).
@@ -18,15 +49,94 @@ pub struct CachedBlock {
     /// Captured visualizations as (kind, data) tuples.
     #[serde(default)]
     pub viz: Vec<(String, String)>,
+    /// Values the block passed to templates (`page.exec_data`).
+    #[serde(default)]
+    pub data: serde_json::Map<String, serde_json::Value>,
+}
+
+impl CachedBlock {
+    /// Fill in a block's results from this entry.
+    pub(crate) fn apply_to(self, block: &mut ExecutableBlock) {
+        block.output = self.output;
+        block.error = self.error;
+        block.viz = self
+            .viz
+            .into_iter()
+            .map(|(kind, data)| VizOutput { kind, data })
+            .collect();
+        block.data = self.data;
+    }
 }
 
-/// In-memory representation of a page's cached blocks.
+impl From<&ExecutableBlock> for CachedBlock {
+    fn from(block: &ExecutableBlock) -> Self {
+        Self {
+            language: block.language.clone(),
+            output: block.output.clone(),
+            error: block.error.clone(),
+            viz: block
+                .viz
+                .iter()
+                .map(|v| (v.kind.clone(), v.data.clone()))
+                .collect(),
+            data: block.data.clone(),
+        }
+    }
+}
+
+/// The cache keys each page and section used in the last build, and the
+/// generated files it used.
 ///
-/// Stored on disk as `.zorto/cache/{page_key_hash}.json`.
+/// Stored on disk as `.zorto/cache/manifest.json`.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
-pub struct PageCache {
-    /// Cached blocks indexed by their position (0-based) within the page.
-    pub blocks: HashMap<String, CachedBlock>,
+pub struct Manifest {
+    pub version: u32,
+    /// Cache keys by page or section key, in block order.
+    pub pages: BTreeMap<String, Vec<String>>,
+    /// Processed images and diagrams, by path relative to the cache
+    /// directory (`images/photo-3f2a9c0e1b7d4a65.webp`).
+    #[serde(default)]
+    pub files: BTreeSet<String>,
+}
+
+impl Manifest {
+    /// Every key referenced by some page.
+    pub fn keys(&self) -> BTreeSet<&str> {
+        self.pages.values().flatten().map(String::as_str).collect()
+    }
+}
+
+/// Summary of the store returned by [`info`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheInfo {
+    /// Number of stored results.
+    pub entries: usize,
+    /// Total size of the stored results in bytes.
+    pub bytes: u64,
+    /// Stored results referenced by the last build, if a manifest exists.
+    pub referenced: Option<usize>,
+    /// Stored results by block language.
+    pub languages: BTreeMap<String, usize>,
+    /// Per-page cache files left by older versions of Zorto.
+    pub legacy_files: usize,
+}
+
+/// What [`prune`] removed (or would remove, for a dry run).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneSummary {
+    /// Number of files removed.
+    pub removed: usize,
+    /// Bytes freed.
+    pub bytes: u64,
+}
+
+/// What [`import_archive`] did with the entries of an archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    /// Results added to the store.
+    pub imported: usize,
+    /// Entries already present or not cache results.
+    pub skipped: usize,
 }
 
 /// Compute the SHA-256 hex digest of a string.
@@ -38,9 +148,8 @@ pub fn hash_source(source: &str) -> String {
 
 /// Compute a cache key for an executable block.
 ///
-/// For inline blocks (no `file_ref`) this matches the legacy
-/// `hash_source("{language}:{source}")` format so existing on-disk caches
-/// remain valid.
+/// For inline blocks (no `file_ref`) this is
+/// `hash_source("{language}:{source}")`.
 ///
 /// For `file_ref` blocks, the key includes the language, the inline source,
 /// the referenced path, and the file's contents — so editing the referenced
@@ -75,38 +184,360 @@ pub fn block_cache_key(
     format!("{:x}", hasher.finalize())
 }
 
+/// Fold a fingerprint from [`inputs_fingerprint`] or [`deps_fingerprint`]
+/// into a block's cache key.
+pub fn with_inputs(key: String, fingerprint: Option<&str>) -> String {
+    match fingerprint {
+        Some(fingerprint) => hash_source(&format!("{key}\0inputs={fingerprint}")),
+        None => key,
+    }
+}
+
+/// Hash the files matching `[cache] inputs` and the values of `[cache] env`,
+/// looked up with `env` (`|name| std::env::var(name).ok()` in a build).
+///
+/// Input patterns are globs relative to the site root. Returns `None` when
+/// nothing is declared, so keys stay as [`block_cache_key`] computes them.
+///
+/// # Errors
+///
+/// Returns an error if a pattern is not a valid glob or a matching file
+/// cannot be read.
+pub fn inputs_fingerprint(
+    site_root: &Path,
+    config: &CacheConfig,
+    env: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<Option<String>> {
+    if config.inputs.is_empty() && config.env.is_empty() {
+        return Ok(None);
+    }
+    let files = glob_files(site_root, &config.inputs)?;
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"inputs\x00");
+    hash_files(&mut hasher, &files);
+    hasher.update(b"env\x00");
+    let names: BTreeSet<&String> = config.env.iter().collect();
+    for name in names {
+        hasher.update(name.as_bytes());
+        hasher.update(b"=");
+        match env(name) {
+            Some(value) => hasher.update(value.as_bytes()),
+            None => hasher.update(b"<unset>"),
+        }
+        hasher.update(b"\x00");
+    }
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+/// Hash the files matching a block's `deps=` patterns, which are globs
+/// relative to the site root like `[cache] inputs`. Returns `None` for a
+/// block without dependencies. Warns about a pattern that matches no files,
+/// since it cannot invalidate anything.
+///
+/// # Errors
+///
+/// Returns an error if a pattern is not a valid glob or a matching file
+/// cannot be read.
+pub fn deps_fingerprint(site_root: &Path, deps: &[String]) -> anyhow::Result<Option<String>> {
+    if deps.is_empty() {
+        return Ok(None);
+    }
+    let mut files = BTreeMap::new();
+    for pattern in deps {
+        let matched = glob_files(site_root, std::slice::from_ref(pattern))?;
+        if matched.is_empty() {
+            eprintln!("warning: deps pattern '{pattern}' matches no files under the site root");
+        }
+        files.extend(matched);
+    }
+    let mut hasher = Sha256::new();
+    hasher.update(b"deps\x00");
+    hash_files(&mut hasher, &files);
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+fn hash_files(hasher: &mut Sha256, files: &BTreeMap<String, String>) {
+    for (path, hash) in files {
+        hasher.update(path.as_bytes());
+        hasher.update(b"\x00");
+        hasher.update(hash.as_bytes());
+        hasher.update(b"\x00");
+    }
+}
+
+/// Compile the part of a glob returned by [`split_glob`]. `*` and `?` do
+/// not match `/`; `**` matches any number of directories.
+pub(crate) fn compile_glob(pattern: &str) -> Result<globset::GlobMatcher, globset::Error> {
+    globset::GlobBuilder::new(pattern)
+        .literal_separator(true)
+        .build()
+        .map(|glob| glob.compile_matcher())
+}
+
+/// The SHA-256 digest of every file under `base` matching one of
+/// `patterns`, keyed by its path as written relative to `base`.
+fn glob_files(base: &Path, patterns: &[String]) -> anyhow::Result<BTreeMap<String, String>> {
+    let mut files = BTreeMap::new();
+    for pattern in patterns {
+        let (dir, glob) = split_glob(pattern);
+        let matcher =
+            compile_glob(&glob).map_err(|e| anyhow::anyhow!("invalid glob '{pattern}': {e}"))?;
+        let dir_path = base.join(&dir);
+        for entry in walkdir::WalkDir::new(&dir_path)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file())
+        {
+            let Ok(rel) = entry.path().strip_prefix(&dir_path) else {
+                continue;
+            };
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            if !matcher.is_match(&rel) {
+                continue;
+            }
+            let name = if dir.is_empty() {
+                rel
+            } else {
+                format!("{dir}/{rel}")
+            };
+            if files.contains_key(&name) {
+                continue;
+            }
+            let bytes = std::fs::read(entry.path())
+                .map_err(|e| anyhow::anyhow!("cannot read {name}: {e}"))?;
+            files.insert(name, format!("{:x}", Sha256::digest(&bytes)));
+        }
+    }
+    Ok(files)
+}
+
+/// Split a glob into its leading directory components without glob syntax
+/// (where a walk for its matches can start) and the rest of the pattern.
+pub(crate) fn split_glob(pattern: &str) -> (String, String) {
+    let components: Vec<&str> = pattern.split('/').collect();
+    // The last component is a file name (or pattern), never a directory to
+    // walk from.
+    let literal = components[..components.len() - 1]
+        .iter()
+        .take_while(|part| !part.contains(['*', '?', '[', '{']))
+        .count();
+    (
+        components[..literal].join("/"),
+        components[literal..].join("/"),
+    )
+}
+
 /// Return the cache directory path for a site root.
 pub fn cache_dir(site_root: &Path) -> PathBuf {
     site_root.join(CACHE_DIR)
 }
 
-/// Derive a filesystem-safe name for a page key.
-fn page_cache_file(site_root: &Path, page_key: &str) -> PathBuf {
-    // Hash the page key to avoid filesystem issues with slashes/special chars.
-    let mut hasher = Sha256::new();
-    hasher.update(page_key.as_bytes());
-    let name = format!("{:x}", hasher.finalize());
-    cache_dir(site_root).join(format!("{name}.json"))
+fn objects_dir(site_root: &Path) -> PathBuf {
+    cache_dir(site_root).join(OBJECTS_DIR)
+}
+
+/// Whether `key` looks like a cache key (a SHA-256 hex digest).
+fn is_key(key: &str) -> bool {
+    key.len() == 64 && key.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+/// Path of the stored result for `key`, relative to the cache directory.
+fn object_rel_path(key: &str) -> String {
+    format!("{OBJECTS_DIR}/{}/{key}.json", &key[..2])
 }
 
-/// Load the cached blocks for a page, if the cache file exists.
-pub fn load_page_cache(site_root: &Path, page_key: &str) -> Option<PageCache> {
-    let path = page_cache_file(site_root, page_key);
-    let data = std::fs::read_to_string(&path).ok()?;
+fn object_path(site_root: &Path, key: &str) -> PathBuf {
+    cache_dir(site_root).join(object_rel_path(key))
+}
+
+/// Load the stored result for a cache key, if there is one.
+pub fn load_block(site_root: &Path, key: &str) -> Option<CachedBlock> {
+    if !is_key(key) {
+        return None;
+    }
+    let data = std::fs::read_to_string(object_path(site_root, key)).ok()?;
     serde_json::from_str(&data).ok()
 }
 
-/// Save the cached blocks for a page.
-pub fn save_page_cache(site_root: &Path, page_key: &str, cache: &PageCache) -> anyhow::Result<()> {
-    let path = page_cache_file(site_root, page_key);
-    if let Some(parent) = path.parent() {
-        std::fs::create_dir_all(parent)?;
+/// Store the result for a cache key.
+///
+/// The file is written atomically, so concurrent builds sharing a cache never
+/// read a partial entry.
+pub fn save_block(site_root: &Path, key: &str, block: &CachedBlock) -> anyhow::Result<()> {
+    if !is_key(key) {
+        anyhow::bail!("invalid cache key '{key}'");
     }
-    let json = serde_json::to_string_pretty(cache)?;
-    std::fs::write(&path, json)?;
+    let json = serde_json::to_string_pretty(block)?;
+    write_atomic(&object_path(site_root, key), json.as_bytes())
+}
+
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("invalid cache path {}", path.display()))?;
+    std::fs::create_dir_all(parent)?;
+    let mut file = tempfile::NamedTempFile::new_in(parent)?;
+    file.write_all(contents)?;
+    file.persist(path).map_err(|e| e.error)?;
     Ok(())
 }
 
+/// Load the manifest written by the last build, if any.
+pub fn load_manifest(site_root: &Path) -> Option<Manifest> {
+    let data = std::fs::read_to_string(cache_dir(site_root).join(MANIFEST_FILE)).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+/// Record the cache keys each page and section used, and the generated
+/// files (paths relative to the cache directory) the build used.
+pub fn save_manifest(
+    site_root: &Path,
+    pages: BTreeMap<String, Vec<String>>,
+    files: BTreeSet<String>,
+) -> anyhow::Result<()> {
+    let manifest = Manifest {
+        version: MANIFEST_VERSION,
+        pages,
+        files,
+    };
+    let json = serde_json::to_string_pretty(&manifest)?;
+    write_atomic(&cache_dir(site_root).join(MANIFEST_FILE), json.as_bytes())
+}
+
+/// Every stored result as `(key, path)`, sorted by key.
+fn stored_objects(site_root: &Path) -> Vec<(String, PathBuf)> {
+    let mut objects: Vec<(String, PathBuf)> = walkdir::WalkDir::new(objects_dir(site_root))
+        .min_depth(2)
+        .max_depth(2)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let key = e.file_name().to_str()?.strip_suffix(".json")?.to_string();
+            is_key(&key).then(|| (key, e.into_path()))
+        })
+        .collect();
+    objects.sort();
+    objects
+}
+
+/// Per-page cache files (`.zorto/cache/{page_hash}.json`) from before the
+/// store was content-addressed.
+fn legacy_files(site_root: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(cache_dir(site_root)) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| {
+            p.is_file()
+                && p.extension().is_some_and(|ext| ext == "json")
+                && p.file_stem().and_then(|s| s.to_str()).is_some_and(is_key)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+/// Files in the generated directories as `(path relative to the cache
+/// directory, path)`, sorted.
+fn generated_files(site_root: &Path) -> Vec<(String, PathBuf)> {
+    let dir = cache_dir(site_root);
+    let mut files: Vec<(String, PathBuf)> = GENERATED_DIRS
+        .iter()
+        .flat_map(|name| walkdir::WalkDir::new(dir.join(name)))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .filter_map(|e| {
+            let rel = e.path().strip_prefix(&dir).ok()?;
+            let rel = rel.to_string_lossy().replace('\\', "/");
+            Some((rel, e.into_path()))
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn file_size(path: &Path) -> u64 {
+    std::fs::metadata(path).map(|m| m.len()).unwrap_or(0)
+}
+
+/// Summarize the store.
+pub fn info(site_root: &Path) -> CacheInfo {
+    let manifest = load_manifest(site_root);
+    let referenced_keys = manifest.as_ref().map(Manifest::keys);
+    let mut info = CacheInfo {
+        referenced: referenced_keys.as_ref().map(|_| 0),
+        legacy_files: legacy_files(site_root).len(),
+        ..CacheInfo::default()
+    };
+    for (key, path) in stored_objects(site_root) {
+        info.entries += 1;
+        info.bytes += file_size(&path);
+        if let (Some(count), Some(keys)) = (info.referenced.as_mut(), &referenced_keys) {
+            if keys.contains(key.as_str()) {
+                *count += 1;
+            }
+        }
+        let language = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|data| serde_json::from_str::<CachedBlock>(&data).ok())
+            .map(|block| block.language)
+            .filter(|language| !language.is_empty())
+            .unwrap_or_else(|| "unknown".to_string());
+        *info.languages.entry(language).or_default() += 1;
+    }
+    info
+}
+
+/// Remove stored results, processed images and diagrams that the last
+/// build did not use, along with per-page cache files from older versions.
+/// With `dry_run`, only report what would be removed.
+///
+/// # Errors
+///
+/// Returns an error if no build has recorded a manifest yet, or a file
+/// cannot be removed.
+pub fn prune(site_root: &Path, dry_run: bool) -> anyhow::Result<PruneSummary> {
+    let manifest = load_manifest(site_root).ok_or_else(|| {
+        anyhow::anyhow!(
+            "no cache manifest found; run `zorto build` with [cache] enable = true first"
+        )
+    })?;
+    let referenced = manifest.keys();
+
+    let mut stale: Vec<PathBuf> = stored_objects(site_root)
+        .into_iter()
+        .filter(|(key, _)| !referenced.contains(key.as_str()))
+        .map(|(_, path)| path)
+        .collect();
+    stale.extend(
+        generated_files(site_root)
+            .into_iter()
+            .filter(|(rel, _)| !manifest.files.contains(rel))
+            .map(|(_, path)| path),
+    );
+    stale.extend(legacy_files(site_root));
+
+    let mut summary = PruneSummary::default();
+    for path in &stale {
+        summary.removed += 1;
+        summary.bytes += file_size(path);
+        if !dry_run {
+            std::fs::remove_file(path)
+                .map_err(|e| anyhow::anyhow!("cannot remove {}: {e}", path.display()))?;
+            // Drop the shard directory once it is empty; failure just means
+            // it still holds other entries.
+            if let Some(parent) = path.parent() {
+                let _ = std::fs::remove_dir(parent);
+            }
+        }
+    }
+    Ok(summary)
+}
+
 /// Remove the entire cache directory.
 pub fn clear_cache(site_root: &Path) -> anyhow::Result<()> {
     let dir = cache_dir(site_root);
@@ -116,11 +547,107 @@ pub fn clear_cache(site_root: &Path) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Largest archive entry [`import_archive`] reads. Stored results are JSON
+/// with embedded images, far below this; bigger entries are skipped unread.
+const MAX_ARCHIVE_ENTRY_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Write every stored result to `writer` as a gzipped tarball with entries
+/// at `objects/{key[..2]}/{key}.json`. Entries are sorted and carry no
+/// timestamps, so the same store always produces the same archive.
+/// Returns the number of entries written.
+///
+/// # Errors
+///
+/// Returns an error if a stored result cannot be read or `writer` fails.
+pub fn export_archive(site_root: &Path, writer: impl Write) -> anyhow::Result<usize> {
+    let gz = flate2::write::GzEncoder::new(writer, flate2::Compression::default());
+    let mut archive = tar::Builder::new(gz);
+    let objects = stored_objects(site_root);
+    for (key, path) in &objects {
+        let read_error = |e: std::io::Error| anyhow::anyhow!("cannot read {}: {e}", path.display());
+        let file = std::fs::File::open(path).map_err(read_error)?;
+        let mut header = tar::Header::new_ustar();
+        header.set_size(file.metadata().map_err(read_error)?.len());
+        header.set_mode(0o644);
+        header.set_mtime(0);
+        archive.append_data(&mut header, object_rel_path(key), file)?;
+    }
+    archive.into_inner()?.finish()?.flush()?;
+    Ok(objects.len())
+}
+
+/// Add the results in a tarball written by [`export_archive`] to the store.
+/// Results already present are kept; entries that are not valid cache
+/// results are skipped.
+///
+/// The archive is read one entry at a time, so its size is not limited by
+/// memory.
+///
+/// # Errors
+///
+/// Returns an error if the archive is not a gzipped tarball or a result
+/// cannot be written.
+pub fn import_archive(site_root: &Path, reader: impl Read) -> anyhow::Result<ImportSummary> {
+    let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(reader));
+    let entries = archive
+        .entries()
+        .map_err(|e| anyhow::anyhow!("cannot read cache archive: {e}"))?;
+
+    let mut summary = ImportSummary::default();
+    for entry in entries {
+        let mut entry = entry.map_err(|e| anyhow::anyhow!("cannot read cache archive: {e}"))?;
+        let name = String::from_utf8_lossy(&entry.path_bytes()).into_owned();
+        let key = name
+            .strip_prefix("objects/")
+            .and_then(|rest| rest.split_once('/'))
+            .and_then(|(shard, file)| {
+                let key = file.strip_suffix(".json")?;
+                (is_key(key) && key.starts_with(shard)).then(|| key.to_string())
+            });
+        let wanted = entry.header().entry_type().is_file()
+            && entry.size() <= MAX_ARCHIVE_ENTRY_BYTES
+            && key
+                .as_deref()
+                .is_some_and(|key| !object_path(site_root, key).exists());
+        let block = if wanted {
+            let mut data = Vec::new();
+            entry
+                .read_to_end(&mut data)
+                .map_err(|e| anyhow::anyhow!("cannot read cache archive entry '{name}': {e}"))?;
+            serde_json::from_slice::<CachedBlock>(&data).ok()
+        } else {
+            None
+        };
+        match (key, block) {
+            (Some(key), Some(block)) => {
+                save_block(site_root, &key, &block)?;
+                summary.imported += 1;
+            }
+            _ => summary.skipped += 1,
+        }
+    }
+    Ok(summary)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use tempfile::TempDir;
 
+    fn result(language: &str, output: &str) -> CachedBlock {
+        CachedBlock {
+            language: language.to_string(),
+            output: Some(output.to_string()),
+            error: None,
+            viz: Vec::new(),
+            data: Default::default(),
+        }
+    }
+
+    fn key(source: &str) -> String {
+        block_cache_key("bash", source, None, Path::new("."))
+    }
+
     #[test]
     fn test_hash_source_deterministic() {
         let h1 = hash_source("print('hello')");
@@ -136,41 +663,40 @@ mod tests {
     }
 
     #[test]
-    fn test_save_and_load_page_cache() {
+    fn test_save_and_load_block() {
         let tmp = TempDir::new().unwrap();
-        let page_key = "blog/post.md";
-
-        let mut cache = PageCache::default();
-        cache.blocks.insert(
-            "0".to_string(),
-            CachedBlock {
-                hash: hash_source("echo hello"),
-                output: Some("hello\n".to_string()),
-                error: None,
-                viz: Vec::new(),
-            },
+        let key = key("echo hello");
+        save_block(tmp.path(), &key, &result("bash", "hello\n")).unwrap();
+
+        assert!(
+            cache_dir(tmp.path())
+                .join(format!("objects/{}/{key}.json", &key[..2]))
+                .is_file()
         );
+        let loaded = load_block(tmp.path(), &key).unwrap();
+        assert_eq!(loaded.language, "bash");
+        assert_eq!(loaded.output.as_deref(), Some("hello\n"));
+        assert!(loaded.error.is_none());
+        assert!(loaded.viz.is_empty());
+    }
 
-        save_page_cache(tmp.path(), page_key, &cache).unwrap();
-        let loaded = load_page_cache(tmp.path(), page_key).unwrap();
-        assert_eq!(loaded.blocks.len(), 1);
-        let block = &loaded.blocks["0"];
-        assert_eq!(block.output.as_deref(), Some("hello\n"));
-        assert!(block.error.is_none());
-        assert!(block.viz.is_empty());
+    #[test]
+    fn test_load_missing_block_returns_none() {
+        let tmp = TempDir::new().unwrap();
+        assert!(load_block(tmp.path(), &key("echo nothing")).is_none());
+        assert!(load_block(tmp.path(), "../../etc/passwd").is_none());
     }
 
     #[test]
-    fn test_load_missing_cache_returns_none() {
+    fn test_save_block_rejects_invalid_key() {
         let tmp = TempDir::new().unwrap();
-        assert!(load_page_cache(tmp.path(), "nonexistent.md").is_none());
+        assert!(save_block(tmp.path(), "../escape", &CachedBlock::default()).is_err());
     }
 
     #[test]
     fn test_clear_cache() {
         let tmp = TempDir::new().unwrap();
-        let cache = PageCache::default();
-        save_page_cache(tmp.path(), "test.md", &cache).unwrap();
+        save_block(tmp.path(), &key("echo a"), &CachedBlock::default()).unwrap();
         assert!(cache_dir(tmp.path()).exists());
 
         clear_cache(tmp.path()).unwrap();
@@ -187,45 +713,11 @@ mod tests {
     #[test]
     fn test_corrupted_json_returns_none() {
         let tmp = TempDir::new().unwrap();
-        let page_key = "test.md";
-        // Write a valid cache first to get the file path, then corrupt it
-        let cache = PageCache::default();
-        save_page_cache(tmp.path(), page_key, &cache).unwrap();
-        let cache_file = page_cache_file(tmp.path(), page_key);
-        std::fs::write(&cache_file, "not valid json {{{").unwrap();
+        let key = key("echo corrupt");
+        save_block(tmp.path(), &key, &CachedBlock::default()).unwrap();
+        std::fs::write(object_path(tmp.path(), &key), "not valid json {{{").unwrap();
         // Should gracefully return None instead of panicking
-        assert!(load_page_cache(tmp.path(), page_key).is_none());
-    }
-
-    #[test]
-    fn test_cache_hit_and_miss() {
-        let tmp = TempDir::new().unwrap();
-        let page_key = "test.md";
-        let source = "echo cached";
-        let source_hash = hash_source(source);
-
-        // Save a cached result
-        let mut cache = PageCache::default();
-        cache.blocks.insert(
-            "0".to_string(),
-            CachedBlock {
-                hash: source_hash.clone(),
-                output: Some("cached\n".to_string()),
-                error: None,
-                viz: Vec::new(),
-            },
-        );
-        save_page_cache(tmp.path(), page_key, &cache).unwrap();
-
-        // Load and check hit
-        let loaded = load_page_cache(tmp.path(), page_key).unwrap();
-        let block = &loaded.blocks["0"];
-        assert_eq!(block.hash, source_hash);
-        assert_eq!(block.output.as_deref(), Some("cached\n"));
-
-        // Different source => miss
-        let new_hash = hash_source("echo different");
-        assert_ne!(block.hash, new_hash);
+        assert!(load_block(tmp.path(), &key).is_none());
     }
 
     #[test]
@@ -239,14 +731,10 @@ mod tests {
     }
 
     #[test]
-    fn test_block_cache_key_inline_matches_legacy() {
-        // Backward-compat: blocks without file_ref must hash to the same value
-        // as the previous `hash_source("{language}:{source}")` formulation so
-        // existing on-disk caches continue to hit.
+    fn test_block_cache_key_inline_format() {
         let tmp = TempDir::new().unwrap();
         let key = block_cache_key("python", "print('hi')", None, tmp.path());
-        let legacy = hash_source("python:print('hi')");
-        assert_eq!(key, legacy);
+        assert_eq!(key, hash_source("python:print('hi')"));
     }
 
     #[test]
@@ -312,4 +800,209 @@ mod tests {
         let from_file = block_cache_key("python", "", Some("x.py"), tmp.path());
         assert_ne!(inline, from_file);
     }
+
+    #[test]
+    fn test_inputs_fingerprint_tracks_files_and_env() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::create_dir_all(tmp.path().join("data/raw")).unwrap();
+        std::fs::write(tmp.path().join("data/raw/a.csv"), "x\n1\n").unwrap();
+        std::fs::write(tmp.path().join("data/notes.txt"), "ignored").unwrap();
+        let config = CacheConfig {
+            enable: true,
+            inputs: vec!["data/**/*.csv".into()],
+            env: vec!["API_VERSION".into()],
+        };
+        let unset = |_: &str| None;
+        let fingerprint = |env: &dyn Fn(&str) -> Option<String>| {
+            inputs_fingerprint(tmp.path(), &config, env)
+                .unwrap()
+                .unwrap()
+        };
+
+        assert!(
+            inputs_fingerprint(tmp.path(), &CacheConfig::default(), unset)
+                .unwrap()
+                .is_none()
+        );
+        let first = fingerprint(&unset);
+        assert_eq!(fingerprint(&unset), first);
+
+        // Files outside the patterns do not matter
+        std::fs::write(tmp.path().join("data/notes.txt"), "changed").unwrap();
+        assert_eq!(fingerprint(&unset), first);
+
+        std::fs::write(tmp.path().join("data/raw/a.csv"), "x\n2\n").unwrap();
+        let edited = fingerprint(&unset);
+        assert_ne!(edited, first);
+
+        let version = |v: &'static str| move |name: &str| (name == "API_VERSION").then(|| v.into());
+        let with_env = fingerprint(&version("1"));
+        assert_ne!(with_env, edited);
+        assert_eq!(fingerprint(&version("1")), with_env);
+        assert_ne!(fingerprint(&version("2")), with_env);
+        // Other variables are not part of it
+        assert_eq!(fingerprint(&|_: &str| Some("1".into())), with_env);
+
+        let key = key("cat data/raw/a.csv");
+        assert_eq!(with_inputs(key.clone(), None), key);
+        assert_ne!(with_inputs(key.clone(), Some(&first)), key);
+    }
+
+    #[test]
+    fn test_deps_fingerprint_tracks_matching_files() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        std::fs::create_dir_all(root.join("data/raw")).unwrap();
+        std::fs::write(root.join("data/a.csv"), "1").unwrap();
+        std::fs::write(root.join("data/raw/b.csv"), "2").unwrap();
+        let deps = vec!["data/*.csv".to_string()];
+
+        assert!(deps_fingerprint(root, &[]).unwrap().is_none());
+        let first = deps_fingerprint(root, &deps).unwrap().unwrap();
+
+        // `*` does not cross directories
+        std::fs::write(root.join("data/raw/b.csv"), "3").unwrap();
+        assert_eq!(deps_fingerprint(root, &deps).unwrap().unwrap(), first);
+
+        std::fs::write(root.join("data/c.csv"), "new").unwrap();
+        assert_ne!(deps_fingerprint(root, &deps).unwrap().unwrap(), first);
+
+        // A pattern matching nothing still yields a fingerprint
+        assert!(
+            deps_fingerprint(root, &["missing/*.csv".to_string()])
+                .unwrap()
+                .is_some()
+        );
+
+        let err = deps_fingerprint(root, &["data/[".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("data/["), "{err}");
+    }
+
+    #[test]
+    fn test_inputs_fingerprint_rejects_invalid_glob() {
+        let tmp = TempDir::new().unwrap();
+        let config = CacheConfig {
+            inputs: vec!["data/[".into()],
+            ..CacheConfig::default()
+        };
+        let err = inputs_fingerprint(tmp.path(), &config, |_| None).unwrap_err();
+        assert!(err.to_string().contains("data/["), "{err}");
+    }
+
+    #[test]
+    fn test_split_glob() {
+        let split = split_glob;
+        assert_eq!(split("data/**/*.csv"), ("data".into(), "**/*.csv".into()));
+        assert_eq!(split("data/raw/a.csv"), ("data/raw".into(), "a.csv".into()));
+        assert_eq!(split("*.toml"), ("".into(), "*.toml".into()));
+        assert_eq!(split("src/{a,b}/x"), ("src".into(), "{a,b}/x".into()));
+        assert_eq!(split("../data/*.csv"), ("../data".into(), "*.csv".into()));
+    }
+
+    #[test]
+    fn test_prune_removes_unreferenced_and_legacy_entries() {
+        let tmp = TempDir::new().unwrap();
+        let (used, unused) = (key("echo used"), key("echo unused"));
+        save_block(tmp.path(), &used, &result("bash", "used\n")).unwrap();
+        save_block(tmp.path(), &unused, &result("bash", "unused\n")).unwrap();
+        let legacy = cache_dir(tmp.path()).join(format!("{}.json", hash_source("page.md")));
+        std::fs::write(&legacy, "{}").unwrap();
+
+        let err = prune(tmp.path(), false).unwrap_err();
+        assert!(err.to_string().contains("zorto build"), "{err}");
+
+        let images = cache_dir(tmp.path()).join("images");
+        std::fs::create_dir_all(&images).unwrap();
+        std::fs::write(images.join("used.webp"), "used").unwrap();
+        std::fs::write(images.join("old.webp"), "old").unwrap();
+
+        save_manifest(
+            tmp.path(),
+            BTreeMap::from([("page.md".to_string(), vec![used.clone()])]),
+            BTreeSet::from(["images/used.webp".to_string()]),
+        )
+        .unwrap();
+        let before = info(tmp.path());
+        assert_eq!(before.entries, 2);
+        assert_eq!(before.referenced, Some(1));
+        assert_eq!(before.legacy_files, 1);
+        assert_eq!(before.languages["bash"], 2);
+
+        let dry = prune(tmp.path(), true).unwrap();
+        assert_eq!(dry.removed, 3);
+        assert!(load_block(tmp.path(), &unused).is_some());
+        assert!(images.join("old.webp").exists());
+
+        let summary = prune(tmp.path(), false).unwrap();
+        assert_eq!(summary, dry);
+        assert!(load_block(tmp.path(), &used).is_some());
+        assert!(load_block(tmp.path(), &unused).is_none());
+        assert!(!legacy.exists());
+        assert!(images.join("used.webp").exists());
+        assert!(!images.join("old.webp").exists());
+        assert_eq!(info(tmp.path()).entries, 1);
+    }
+
+    #[test]
+    fn test_export_import_roundtrip() {
+        let source = TempDir::new().unwrap();
+        let keys = [key("echo one"), key("echo two")];
+        for (i, key) in keys.iter().enumerate() {
+            save_block(source.path(), key, &result("bash", &format!("{i}\n"))).unwrap();
+        }
+
+        let mut archive = Vec::new();
+        assert_eq!(export_archive(source.path(), &mut archive).unwrap(), 2);
+        // Archives are reproducible
+        let mut again = Vec::new();
+        export_archive(source.path(), &mut again).unwrap();
+        assert_eq!(archive, again);
+
+        let dest = TempDir::new().unwrap();
+        save_block(dest.path(), &keys[0], &result("bash", "kept\n")).unwrap();
+        let summary = import_archive(dest.path(), archive.as_slice()).unwrap();
+        assert_eq!(
+            summary,
+            ImportSummary {
+                imported: 1,
+                skipped: 1
+            }
+        );
+        assert_eq!(
+            load_block(dest.path(), &keys[0]).unwrap().output.as_deref(),
+            Some("kept\n")
+        );
+        assert_eq!(
+            load_block(dest.path(), &keys[1]).unwrap().output.as_deref(),
+            Some("1\n")
+        );
+    }
+
+    #[test]
+    fn test_import_skips_foreign_entries() {
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            Vec::new(),
+            flate2::Compression::default(),
+        ));
+        for (name, data) in [
+            ("../escape.json", b"{}".as_slice()),
+            ("objects/zz/not-a-key.json", b"{}".as_slice()),
+        ] {
+            // Written into the raw header: the tar crate refuses `..` paths
+            let mut header = tar::Header::new_ustar();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_size(data.len() as u64);
+            header.set_cksum();
+            tar.append(&header, data).unwrap();
+        }
+        let archive = tar.into_inner().unwrap().finish().unwrap();
+
+        let tmp = TempDir::new().unwrap();
+        let summary = import_archive(tmp.path(), archive.as_slice()).unwrap();
+        assert_eq!(summary.imported, 0);
+        assert_eq!(summary.skipped, 2);
+        assert!(!tmp.path().join("escape.json").exists());
+
+        assert!(import_archive(tmp.path(), b"not gzip".as_slice()).is_err());
+    }
 }
@@ -1,5 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
+use std::sync::Arc;
+
+use crate::highlight::Highlighter;
 
 /// Top-level site configuration, loaded from `config.toml`.
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -15,6 +19,13 @@ pub struct Config {
     /// Default language code (default: `"en"`).
     #[serde(default = "default_en")]
     pub default_language: String,
+    /// Per-language settings from `[languages.<code>]` tables.
+    ///
+    /// Every code other than `default_language` enables translated content:
+    /// `about.fr.md` next to `about.md` renders at `/fr/about/`. An entry for
+    /// the default language may be present to hold its `translations` table.
+    #[serde(default)]
+    pub languages: BTreeMap<String, LanguageConfig>,
     /// Compile SCSS files from `sass/` directory (default: `true`).
     #[serde(default = "default_true", skip_serializing)]
     pub compile_sass: bool,
@@ -54,6 +65,18 @@ pub struct Config {
     /// External content directories to load as pages/sections.
     #[serde(default, skip_serializing)]
     pub content_dirs: Vec<ContentDirConfig>,
+    /// Data files to generate one page per record from.
+    #[serde(default, skip_serializing)]
+    pub generators: Vec<GeneratorConfig>,
+    /// Built-in full-text search configuration.
+    #[serde(default)]
+    pub search: SearchConfig,
+    /// Image processing and responsive image configuration.
+    #[serde(default, skip_serializing)]
+    pub images: ImagesConfig,
+    /// Build-time diagram rendering configuration.
+    #[serde(default, skip_serializing)]
+    pub diagrams: DiagramsConfig,
     /// Code block execution cache configuration.
     #[serde(default, skip_serializing)]
     pub cache: CacheConfig,
@@ -73,6 +96,17 @@ pub enum AnchorLinks {
     Right,
 }
 
+/// How `$…$` and `$$…$$` math in markdown is rendered.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MathMode {
+    /// No math parsing; dollar signs are plain text.
+    #[default]
+    None,
+    /// MathML, rendered natively by browsers.
+    Mathml,
+}
+
 /// How pages in a section are sorted.
 #[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "snake_case")]
@@ -95,9 +129,19 @@ pub struct MarkdownConfig {
     /// Insert anchor links on headings.
     #[serde(default)]
     pub insert_anchor_links: AnchorLinks,
-    /// Syntect theme name (default: `"base16-ocean.dark"`).
+    /// Syntect theme name, from the bundled themes or the site's
+    /// `highlight_themes/` (default: `"base16-ocean.dark"`). `"css"` marks
+    /// code up with classes instead, colored by the generated `syntax.css`.
     #[serde(default)]
     pub highlight_theme: Option<String>,
+    /// Theme for the light variant of `syntax.css` (default:
+    /// `"InspiredGitHub"`).
+    #[serde(default)]
+    pub highlight_theme_light: Option<String>,
+    /// Theme for the dark variant of `syntax.css` (default:
+    /// `"base16-ocean.dark"`).
+    #[serde(default)]
+    pub highlight_theme_dark: Option<String>,
     /// Open external links in a new tab.
     #[serde(default)]
     pub external_links_target_blank: bool,
@@ -110,6 +154,44 @@ pub struct MarkdownConfig {
     /// Enable smart punctuation (curly quotes, em dashes, etc.).
     #[serde(default)]
     pub smart_punctuation: bool,
+    /// Deepest heading level in `page.toc` and `section.toc` (default: 6,
+    /// every heading).
+    #[serde(default = "default_toc_depth")]
+    pub toc_depth: u32,
+    /// Render `$inline$` and `$$display$$` math (default: `"none"`, `$` is
+    /// plain text).
+    #[serde(default)]
+    pub math: MathMode,
+    /// Definition lists: a term line followed by `: definition` lines.
+    #[serde(default)]
+    pub definition_lists: bool,
+    /// Keep classes and attributes set on headings, as in
+    /// `## Title {.wide data-level=2}`. A trailing `{#id}` always sets the
+    /// id; without this option any other `{...}` stays heading text.
+    #[serde(default)]
+    pub heading_attributes: bool,
+    /// `{.class #id key=value}` on its own line after a block (or as the
+    /// last line of a paragraph) sets that block's attributes.
+    #[serde(default)]
+    pub block_attributes: bool,
+    /// `^text^` superscript.
+    #[serde(default)]
+    pub superscript: bool,
+    /// `~text~` subscript (`~~text~~` stays strikethrough).
+    #[serde(default)]
+    pub subscript: bool,
+    /// `[[Page]]`, `[[Page|label]]` and `[[Page#Heading]]` links, resolved
+    /// against page paths, file names and titles.
+    #[serde(default)]
+    pub wiki_links: bool,
+    /// `*[HTML]: Hyper Text Markup Language` definitions, which wrap the
+    /// abbreviation's uses in `<abbr title="...">`.
+    #[serde(default)]
+    pub abbreviations: bool,
+    /// The site's own grammars and themes, set by `Site::load`; `None`
+    /// highlights with the bundled ones.
+    #[serde(skip)]
+    pub(crate) highlighter: Option<Arc<Highlighter>>,
 }
 
 impl Default for MarkdownConfig {
@@ -118,20 +200,185 @@ impl Default for MarkdownConfig {
             highlight_code: true,
             insert_anchor_links: AnchorLinks::None,
             highlight_theme: None,
+            highlight_theme_light: None,
+            highlight_theme_dark: None,
             external_links_target_blank: false,
             external_links_no_follow: false,
             external_links_no_referrer: false,
             smart_punctuation: false,
+            toc_depth: default_toc_depth(),
+            math: MathMode::None,
+            definition_lists: false,
+            heading_attributes: false,
+            block_attributes: false,
+            superscript: false,
+            subscript: false,
+            wiki_links: false,
+            abbreviations: false,
+            highlighter: None,
+        }
+    }
+}
+
+fn default_toc_depth() -> u32 {
+    6
+}
+
+/// Default maximum characters in a search result snippet.
+const DEFAULT_SNIPPET_LENGTH: usize = 160;
+
+fn default_snippet_length() -> usize {
+    DEFAULT_SNIPPET_LENGTH
+}
+
+/// Configuration for the built-in search index from `[search]`.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SearchConfig {
+    /// Write `search_index.json` and the `zorto-search.js` client (default: `false`).
+    #[serde(default)]
+    pub enable: bool,
+    /// Index body text in addition to titles, headings and descriptions
+    /// (default: `true`). Disable to shrink the index on large sites.
+    #[serde(default = "default_true")]
+    pub include_content: bool,
+    /// Maximum characters in a result snippet (default: 160).
+    #[serde(default = "default_snippet_length")]
+    pub snippet_length: usize,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            include_content: true,
+            snippet_length: default_snippet_length(),
         }
     }
 }
 
+/// Configuration for image processing from `[images]`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ImagesConfig {
+    /// Give local images in content `srcset`, `sizes`, `width` and `height`
+    /// attributes, with resized variants (default: `false`).
+    #[serde(default)]
+    pub responsive: bool,
+    /// Widths, in pixels, of the resized variants (default: `[480, 960, 1440]`).
+    /// Widths at or above an image's own width are skipped.
+    #[serde(default = "default_image_widths")]
+    pub widths: Vec<u32>,
+    /// Extra formats to offer variants in through `<picture>`, e.g.
+    /// `["avif"]` (default: none). `webp` is rejected: WebP variants are
+    /// lossless, and usually larger than the JPEGs they would replace.
+    #[serde(default)]
+    pub formats: Vec<ImageFormat>,
+    /// JPEG and AVIF quality, 1-100 (default: 80).
+    #[serde(default = "default_image_quality")]
+    pub quality: u8,
+    /// The `sizes` attribute of responsive images (default: `"100vw"`).
+    #[serde(default = "default_image_sizes")]
+    pub sizes: String,
+}
+
+impl Default for ImagesConfig {
+    fn default() -> Self {
+        Self {
+            responsive: false,
+            widths: default_image_widths(),
+            formats: Vec::new(),
+            quality: default_image_quality(),
+            sizes: default_image_sizes(),
+        }
+    }
+}
+
+/// Output format of a processed image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageFormat {
+    /// JPEG (`.jpg`).
+    #[serde(alias = "jpg")]
+    Jpeg,
+    /// PNG (`.png`).
+    Png,
+    /// Lossless WebP (`.webp`). Only `resize_image()` of a non-JPEG
+    /// image writes it.
+    Webp,
+    /// AVIF (`.avif`).
+    Avif,
+}
+
+fn default_image_widths() -> Vec<u32> {
+    vec![480, 960, 1440]
+}
+
+fn default_image_quality() -> u8 {
+    80
+}
+
+fn default_image_sizes() -> String {
+    "100vw".to_string()
+}
+
+/// Configuration for build-time diagram rendering from `[diagrams]`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DiagramsConfig {
+    /// Render diagram code blocks (`mermaid`, `dot`, `d2`) and the `mermaid`
+    /// shortcode to inline SVG at build time (default: `false`).
+    #[serde(default)]
+    pub render: bool,
+    /// Commands by diagram language, e.g. `plantuml = ["plantuml", "-tsvg",
+    /// "-pipe"]`. Each reads the diagram source on stdin and writes SVG to
+    /// stdout. Entries replace the built-in presets for `mermaid`, `dot`
+    /// (or `graphviz`) and `d2`.
+    #[serde(default)]
+    pub tools: BTreeMap<String, Vec<String>>,
+}
+
+impl DiagramsConfig {
+    /// The command rendering `language` diagrams: the configured one, else
+    /// a preset.
+    pub fn tool(&self, language: &str) -> Option<Vec<String>> {
+        self.tools
+            .get(language)
+            .cloned()
+            .or_else(|| preset_diagram_tool(language))
+    }
+}
+
+/// Commands for diagram languages without one in `[diagrams.tools]`.
+fn preset_diagram_tool(language: &str) -> Option<Vec<String>> {
+    let command: &[&str] = match language {
+        "mermaid" => &[
+            "mmdc",
+            "--input",
+            "-",
+            "--output",
+            "-",
+            "--outputFormat",
+            "svg",
+        ],
+        "dot" | "graphviz" => &["dot", "-Tsvg"],
+        "d2" => &["d2", "-", "-"],
+        _ => return None,
+    };
+    Some(command.iter().map(|s| s.to_string()).collect())
+}
+
 /// Configuration for code block execution caching.
 #[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct CacheConfig {
     /// Enable caching of executable code block results (default: `false`).
     #[serde(default)]
     pub enable: bool,
+    /// Files, as globs relative to the site root, whose contents invalidate
+    /// every cached result when they change.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// Environment variables whose values invalidate every cached result
+    /// when they change.
+    #[serde(default)]
+    pub env: Vec<String>,
 }
 
 /// Default per-block execution timeout in seconds.
@@ -141,28 +388,459 @@ fn default_exec_timeout_seconds() -> u64 {
     DEFAULT_EXEC_TIMEOUT_SECONDS
 }
 
+/// How `{python}` code blocks are run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PythonMode {
+    /// In the embedded PyO3 interpreter, with a namespace per page.
+    #[default]
+    Embedded,
+    /// In a worker process per page, like a notebook kernel. Blocks on a
+    /// page share state; the worker is killed if a block times out.
+    Subprocess,
+}
+
+/// Where images from code blocks (plots, `_repr_png_`) are stored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImageOutput {
+    /// Inline in the page as base64 `data:` URIs.
+    #[default]
+    Inline,
+    /// As content-hashed files under `viz/` in the output directory, so
+    /// pages stay small and identical images are stored once.
+    Files,
+}
+
+/// What an executable block shows of its output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputMode {
+    /// Output, visualizations and all (`output=true`).
+    Show,
+    /// Nothing (`output=false`).
+    Hide,
+    /// stdout rendered as markdown into the page (`output="asis"`).
+    Asis,
+}
+
+impl OutputMode {
+    /// Parse a fence or config value: `true`, `false` or `asis`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "true" | "show" => Some(Self::Show),
+            "false" | "hide" => Some(Self::Hide),
+            "asis" => Some(Self::Asis),
+            _ => None,
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for OutputMode {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr {
+            Bool(bool),
+            Name(String),
+        }
+        match Repr::deserialize(deserializer)? {
+            Repr::Bool(true) => Ok(Self::Show),
+            Repr::Bool(false) => Ok(Self::Hide),
+            Repr::Name(name) => Self::parse(&name).ok_or_else(|| {
+                serde::de::Error::custom(format!(
+                    "unknown output mode '{name}', expected true, false or \"asis\""
+                ))
+            }),
+        }
+    }
+}
+
+/// How a failed executable block is displayed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorMode {
+    /// Render the error below the block.
+    Show,
+    /// Leave the error out of the page.
+    Hide,
+    /// Render it, and fail the build (shorthand for `on_error = "fail"`).
+    Fail,
+}
+
+impl ErrorMode {
+    /// Parse a fence value: `show`, `hide` or `fail`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "show" => Some(Self::Show),
+            "hide" => Some(Self::Hide),
+            "fail" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// How an executable block is displayed. Set per block in the fence
+/// (`{python echo=false output="asis"}`), with defaults for the site under
+/// `[execute]`; unset options fall back to those, then to the defaults
+/// documented on each method.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BlockOptions {
+    /// Show the block's source.
+    #[serde(default)]
+    pub echo: Option<bool>,
+    /// What to show of its output.
+    #[serde(default)]
+    pub output: Option<OutputMode>,
+    /// What to do when it fails.
+    #[serde(default)]
+    pub error: Option<ErrorMode>,
+    /// Fold the source into a `<details>` element.
+    #[serde(default)]
+    pub collapse: Option<bool>,
+    /// Whether failing fails the build.
+    #[serde(default)]
+    pub on_error: Option<OnError>,
+    /// Run alongside the page's other blocks instead of in order.
+    #[serde(default)]
+    pub parallel: Option<bool>,
+    /// Caption below the block. Per block only.
+    #[serde(skip)]
+    pub caption: Option<String>,
+}
+
+impl BlockOptions {
+    /// These options, with unset ones taken from `defaults`.
+    pub fn or(&self, defaults: &BlockOptions) -> BlockOptions {
+        BlockOptions {
+            echo: self.echo.or(defaults.echo),
+            output: self.output.or(defaults.output),
+            error: self.error.or(defaults.error),
+            collapse: self.collapse.or(defaults.collapse),
+            on_error: self.on_error.or(defaults.on_error),
+            parallel: self.parallel.or(defaults.parallel),
+            caption: self.caption.clone().or_else(|| defaults.caption.clone()),
+        }
+    }
+
+    /// Whether to show the source (default: yes).
+    pub fn echo(&self) -> bool {
+        self.echo.unwrap_or(true)
+    }
+
+    /// What to show of the output (default: all of it).
+    pub fn output(&self) -> OutputMode {
+        self.output.unwrap_or(OutputMode::Show)
+    }
+
+    /// What to do on failure (default: show the error).
+    pub fn error(&self) -> ErrorMode {
+        self.error.unwrap_or(ErrorMode::Show)
+    }
+
+    /// Whether to fold the source (default: no).
+    pub fn collapse(&self) -> bool {
+        self.collapse.unwrap_or(false)
+    }
+
+    /// Whether the block may run alongside the page's other blocks, rather
+    /// than after the ones before it (default: no). Python blocks share the
+    /// page's namespace, so they always run in order.
+    pub fn parallel(&self) -> bool {
+        self.parallel.unwrap_or(false)
+    }
+
+    /// Whether failing fails the build (default: no, it warns). With
+    /// `error = "fail"` it does, whatever `on_error` says.
+    pub fn fails_build(&self) -> bool {
+        self.error == Some(ErrorMode::Fail) || self.on_error == Some(OnError::Fail)
+    }
+}
+
+/// Whether a failed executable block fails the build.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OnError {
+    /// Print a warning and carry on.
+    Warn,
+    /// Fail the build, after running every block, with a summary of the
+    /// failures.
+    Fail,
+}
+
+impl OnError {
+    /// Parse a fence or frontmatter value: `warn` or `fail`.
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "warn" => Some(Self::Warn),
+            "fail" => Some(Self::Fail),
+            _ => None,
+        }
+    }
+}
+
+/// How a [`LanguageRunner`] is handed a block's code.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunnerInput {
+    /// As the last argument, e.g. `Rscript -e <code>`.
+    #[default]
+    Arg,
+    /// As the path of a file holding it, e.g. `rust-script <file>`.
+    File,
+    /// On standard input, e.g. `duckdb < code`.
+    Stdin,
+}
+
+/// A command that runs code blocks of one language
+/// (`[execute.languages.<name>]`).
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LanguageRunner {
+    /// Program and leading arguments, e.g. `["Rscript", "-e"]`.
+    pub command: Vec<String>,
+    /// How the code is passed to the command (default: `"arg"`).
+    #[serde(default)]
+    pub input: RunnerInput,
+    /// Extension of the file written for `input = "file"`, without the dot.
+    #[serde(default)]
+    pub extension: Option<String>,
+}
+
+impl LanguageRunner {
+    fn preset(command: &[&str], input: RunnerInput, extension: Option<&str>) -> Self {
+        Self {
+            command: command.iter().map(|s| s.to_string()).collect(),
+            input,
+            extension: extension.map(String::from),
+        }
+    }
+}
+
+/// Runners for languages without one in `[execute.languages]`.
+fn preset_runner(language: &str) -> Option<LanguageRunner> {
+    use RunnerInput::*;
+    Some(match language {
+        "r" => LanguageRunner::preset(&["Rscript", "-e"], Arg, None),
+        "julia" => LanguageRunner::preset(&["julia", "-e"], Arg, None),
+        "duckdb" => LanguageRunner::preset(&["duckdb"], Stdin, None),
+        "sqlite3" => LanguageRunner::preset(&["sqlite3"], Stdin, None),
+        "deno" => LanguageRunner::preset(
+            &["deno", "run", "--quiet", "--allow-read"],
+            File,
+            Some("ts"),
+        ),
+        "rust" => LanguageRunner::preset(&["rust-script"], File, Some("rs")),
+        _ => return None,
+    })
+}
+
+/// A downloadable format written for pages with executable code blocks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// A Jupyter notebook with the executed outputs.
+    Ipynb,
+    /// A plain Python script in the percent format (`# %%` cells).
+    Py,
+}
+
+impl ExportFormat {
+    /// File extension, also the key in `page.downloads`.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Ipynb => "ipynb",
+            Self::Py => "py",
+        }
+    }
+}
+
 /// Configuration for executable code blocks.
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ExecuteConfig {
-    /// Maximum seconds for any single bash/sh/node code block to run before
-    /// being killed. Default: 120. Set to 0 to disable the timeout.
+    /// Maximum seconds for any single code block to run before being killed.
+    /// Default: 120. Set to 0 to disable the timeout.
     ///
-    /// Python code blocks are NOT subject to this timeout — zorto uses an
-    /// embedded PyO3 interpreter that cannot safely be interrupted from
-    /// another thread. Wrap long-running Python in a subprocess
-    /// (`subprocess.run(..., timeout=...)`) if you need a deadline.
+    /// With the default `python_mode = "embedded"`, Python code blocks are NOT
+    /// subject to this timeout — the embedded PyO3 interpreter cannot safely
+    /// be interrupted from another thread. Use `python_mode = "subprocess"`
+    /// if you need a deadline.
     #[serde(default = "default_exec_timeout_seconds")]
     pub timeout_seconds: u64,
+    /// How `{python}` blocks run (default: `"embedded"`).
+    #[serde(default)]
+    pub python_mode: PythonMode,
+    /// Interpreter for `python_mode = "subprocess"` (default: the site's
+    /// `.venv` Python if there is one, else `python3`).
+    #[serde(default)]
+    pub python: Option<String>,
+    /// Execute the code cells of `.ipynb` pages instead of rendering the
+    /// outputs saved in the notebook (default: `false`). A notebook can
+    /// override this with `execute = true|false` under its `[extra]`.
+    #[serde(default)]
+    pub notebooks: bool,
+    /// Downloadable versions to write next to each page with executable
+    /// blocks (default: none), e.g. `["ipynb", "py"]`. Templates link to
+    /// them via `page.downloads`.
+    #[serde(default)]
+    pub export: Vec<ExportFormat>,
+    /// Where images from code blocks go (default: `"inline"`).
+    #[serde(default)]
+    pub images: ImageOutput,
+    /// Isolation for bash/sh/node and `languages` blocks (`[execute.sandbox]`).
+    #[serde(default)]
+    pub sandbox: SandboxConfig,
+    /// Maximum number of code blocks running at once across the site
+    /// (default: 0, the number of CPUs). Embedded Python blocks run one at a
+    /// time regardless and do not count against it.
+    #[serde(default)]
+    pub jobs: usize,
+    /// Commands for other languages' blocks, by the name used in the fence
+    /// (`{r}` runs `[execute.languages.r]`). Entries replace the built-in
+    /// presets for `r`, `julia`, `duckdb`, `sqlite3`, `deno` and `rust`.
+    #[serde(default)]
+    pub languages: BTreeMap<String, LanguageRunner>,
+    /// Site-wide options for blocks (`echo`, `output`, `error`, `collapse`
+    /// and `on_error` directly under `[execute]`); pages and fences
+    /// override them.
+    #[serde(flatten)]
+    pub block: BlockOptions,
+    /// Any other keys under `[execute]`, which `Config::load` rejects:
+    /// serde cannot deny unknown fields next to the flattened `block`.
+    #[serde(flatten, skip_serializing)]
+    pub(crate) unknown: BTreeMap<String, toml::Value>,
+}
+
+impl ExecuteConfig {
+    /// The effective `jobs` limit.
+    pub fn job_limit(&self) -> usize {
+        if self.jobs > 0 {
+            self.jobs
+        } else {
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        }
+    }
+
+    /// The runner for `{language}` blocks: the configured one, else a preset.
+    pub fn runner(&self, language: &str) -> Option<LanguageRunner> {
+        self.languages
+            .get(language)
+            .cloned()
+            .or_else(|| preset_runner(language))
+    }
 }
 
 impl Default for ExecuteConfig {
     fn default() -> Self {
         Self {
             timeout_seconds: default_exec_timeout_seconds(),
+            python_mode: PythonMode::default(),
+            python: None,
+            notebooks: false,
+            export: Vec::new(),
+            images: ImageOutput::default(),
+            sandbox: SandboxConfig::default(),
+            jobs: 0,
+            languages: BTreeMap::new(),
+            block: BlockOptions::default(),
+            unknown: BTreeMap::new(),
         }
     }
 }
 
+fn default_sandbox_env_allow() -> Vec<String> {
+    ["PATH", "LANG", "LC_ALL", "TERM", "TZ"]
+        .into_iter()
+        .map(String::from)
+        .collect()
+}
+
+fn default_sandbox_memory_mb() -> u64 {
+    1024
+}
+
+fn default_sandbox_cpu_seconds() -> u64 {
+    60
+}
+
+fn default_sandbox_max_output_bytes() -> u64 {
+    1024 * 1024
+}
+
+fn default_sandbox_max_file_bytes() -> u64 {
+    64 * 1024 * 1024
+}
+
+/// Isolation for subprocess code blocks from `[execute.sandbox]`.
+///
+/// Applies to bash/sh/node blocks, `[execute.languages]` runners and, with
+/// `python_mode = "subprocess"`, the Python worker. The embedded interpreter
+/// cannot be sandboxed, so with the sandbox on, `{python}` blocks in
+/// `python_mode = "embedded"` fail without running. Limits set to 0 are
+/// disabled.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SandboxConfig {
+    /// Run blocks in isolation (default: `false`).
+    ///
+    /// Each block gets a fresh temporary working directory (also its `HOME`
+    /// and `TMPDIR`) and a scrubbed environment.
+    #[serde(default)]
+    pub enable: bool,
+    /// Environment variables passed through from the builder (default:
+    /// `PATH`, `LANG`, `LC_ALL`, `TERM`, `TZ`).
+    #[serde(default = "default_sandbox_env_allow")]
+    pub env_allow: Vec<String>,
+    /// Memory limit in MiB, enforced with `RLIMIT_DATA` (default: 1024).
+    #[serde(default = "default_sandbox_memory_mb")]
+    pub memory_mb: u64,
+    /// CPU time limit in seconds, enforced with `RLIMIT_CPU` (default: 60).
+    #[serde(default = "default_sandbox_cpu_seconds")]
+    pub cpu_seconds: u64,
+    /// Maximum bytes of combined stdout/stderr (default: 1 MiB).
+    #[serde(default = "default_sandbox_max_output_bytes")]
+    pub max_output_bytes: u64,
+    /// Maximum size of any file the block writes, enforced with
+    /// `RLIMIT_FSIZE` (default: 64 MiB).
+    #[serde(default = "default_sandbox_max_file_bytes")]
+    pub max_file_bytes: u64,
+    /// Allow network access (default: `true`). When `false`, blocks run in a
+    /// new Linux network namespace with no interfaces; other platforms, or
+    /// kernels without unprivileged user namespaces, report an error.
+    #[serde(default = "default_true")]
+    pub allow_network: bool,
+}
+
+impl Default for SandboxConfig {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            env_allow: default_sandbox_env_allow(),
+            memory_mb: default_sandbox_memory_mb(),
+            cpu_seconds: default_sandbox_cpu_seconds(),
+            max_output_bytes: default_sandbox_max_output_bytes(),
+            max_file_bytes: default_sandbox_max_file_bytes(),
+            allow_network: true,
+        }
+    }
+}
+
+/// Settings for one language from `[languages.<code>]` in `config.toml`.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct LanguageConfig {
+    /// Site title in this language (falls back to the top-level `title`).
+    #[serde(default)]
+    pub title: Option<String>,
+    /// Site description in this language (falls back to the top-level `description`).
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Taxonomies rendered for this language (falls back to the top-level
+    /// `[[taxonomies]]`).
+    #[serde(default, skip_serializing)]
+    pub taxonomies: Option<Vec<TaxonomyConfig>>,
+    /// String table for the `trans()` template function.
+    #[serde(default)]
+    pub translations: HashMap<String, String>,
+}
+
 /// A taxonomy definition from `[[taxonomies]]` in `config.toml`.
 #[derive(Debug, Clone, Deserialize)]
 pub struct TaxonomyConfig {
@@ -195,6 +873,67 @@ pub struct ContentDirConfig {
     pub exclude: Vec<String>,
 }
 
+/// Pages generated from the records of a data file, from `[[generators]]` in
+/// `config.toml`.
+///
+/// Each record becomes a page whose `extra` holds all of its fields. Fields
+/// named like a taxonomy (an array, or a comma-separated string) set the
+/// page's terms.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GeneratorConfig {
+    /// Data file holding the records (relative to site root, e.g. `"data/products.csv"`).
+    pub source: String,
+    /// Format of `source`: `toml`, `json`, `yaml` or `csv` (default: from its extension).
+    #[serde(default)]
+    pub format: Option<String>,
+    /// Dotted key of the array of records inside `source` (default: the whole file).
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Section directory the pages belong to, relative to `content/`
+    /// (e.g. `"products"`; default: the root section).
+    #[serde(default)]
+    pub section: String,
+    /// Template for generated pages (default: `"page.html"`).
+    #[serde(default = "default_page_html")]
+    pub template: String,
+    /// Record field holding the page slug (default: `"slug"`; records without
+    /// it use their slugified title).
+    #[serde(default = "default_slug_field")]
+    pub slug: String,
+    /// Record field holding the page title (default: `"title"`).
+    #[serde(default = "default_title_field")]
+    pub title: String,
+    /// Record field holding the page date (default: `"date"`).
+    #[serde(default = "default_date_field")]
+    pub date: String,
+    /// Record field holding the page description (default: `"description"`).
+    #[serde(default = "default_description_field")]
+    pub description: String,
+    /// Record field holding markdown rendered as the page body (default: `"content"`).
+    #[serde(default = "default_content_field")]
+    pub content: String,
+}
+
+fn default_slug_field() -> String {
+    "slug".to_string()
+}
+
+fn default_title_field() -> String {
+    "title".to_string()
+}
+
+fn default_date_field() -> String {
+    "date".to_string()
+}
+
+fn default_description_field() -> String {
+    "description".to_string()
+}
+
+fn default_content_field() -> String {
+    "content".to_string()
+}
+
 fn default_page_html() -> String {
     "page.html".to_string()
 }
@@ -270,8 +1009,106 @@ impl Config {
             }
         }
 
+        // Validate language codes: they become URL prefixes and filename suffixes
+        for code in config.languages.keys() {
+            if code.is_empty()
+                || !code
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+            {
+                anyhow::bail!(
+                    "invalid language code '{code}' in [languages]: \
+                     use letters, digits, '-' or '_' (e.g. \"fr\", \"pt-br\")"
+                );
+            }
+        }
+
+        if !(1..=100).contains(&config.images.quality) {
+            anyhow::bail!(
+                "invalid [images] quality {}: use 1 to 100",
+                config.images.quality
+            );
+        }
+        if let Some(key) = config.execute.unknown.keys().next() {
+            anyhow::bail!(
+                "invalid [execute]: unknown key `{key}` (block defaults there are echo, output, \
+                 error, collapse, on_error and parallel)"
+            );
+        }
+        if config.images.widths.contains(&0) {
+            anyhow::bail!("invalid [images] widths: widths must be greater than 0");
+        }
+        if config.images.formats.contains(&ImageFormat::Webp) {
+            anyhow::bail!(
+                "invalid [images] formats: \"webp\" is not supported; Zorto can only write \
+                 lossless WebP, which is usually larger than the JPEG it would replace. \
+                 Use \"avif\" instead"
+            );
+        }
+        if let Some((language, _)) = config
+            .diagrams
+            .tools
+            .iter()
+            .find(|(_, command)| command.is_empty())
+        {
+            anyhow::bail!("invalid [diagrams.tools] {language}: the command is empty");
+        }
+
         Ok(config)
     }
+
+    /// All language codes the site is published in: the default language
+    /// first, then every other `[languages.<code>]` entry in sorted order.
+    pub fn language_codes(&self) -> Vec<&str> {
+        let mut codes = vec![self.default_language.as_str()];
+        codes.extend(
+            self.languages
+                .keys()
+                .map(String::as_str)
+                .filter(|code| *code != self.default_language),
+        );
+        codes
+    }
+
+    /// Whether any language besides the default is configured.
+    pub fn is_multilingual(&self) -> bool {
+        self.languages
+            .keys()
+            .any(|code| *code != self.default_language)
+    }
+
+    /// Site title for `lang`, falling back to the top-level `title`.
+    pub fn title_for(&self, lang: &str) -> &str {
+        self.languages
+            .get(lang)
+            .and_then(|l| l.title.as_deref())
+            .unwrap_or(&self.title)
+    }
+
+    /// Site description for `lang`, falling back to the top-level `description`.
+    pub fn description_for(&self, lang: &str) -> &str {
+        self.languages
+            .get(lang)
+            .and_then(|l| l.description.as_deref())
+            .unwrap_or(&self.description)
+    }
+
+    /// Taxonomies rendered for `lang`, falling back to the top-level `[[taxonomies]]`.
+    pub fn taxonomies_for(&self, lang: &str) -> &[TaxonomyConfig] {
+        self.languages
+            .get(lang)
+            .and_then(|l| l.taxonomies.as_deref())
+            .unwrap_or(&self.taxonomies)
+    }
+
+    /// URL path prefix for `lang`: empty for the default language, `"/fr"` otherwise.
+    pub fn language_prefix(&self, lang: &str) -> String {
+        if lang.is_empty() || lang == self.default_language {
+            String::new()
+        } else {
+            format!("/{lang}")
+        }
+    }
 }
 
 #[cfg(test)]
@@ -349,6 +1186,76 @@ feed = true
         assert_eq!(config.default_language, "en");
     }
 
+    #[test]
+    fn test_languages_table() {
+        let tmp = TempDir::new().unwrap();
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+title = "My Site"
+description = "A site"
+
+[languages.en.translations]
+read_more = "Read more"
+
+[languages.fr]
+title = "Mon site"
+taxonomies = [{ name = "etiquettes" }]
+
+[languages.fr.translations]
+read_more = "Lire la suite"
+"#,
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        assert!(config.is_multilingual());
+        assert_eq!(config.language_codes(), ["en", "fr"]);
+        assert_eq!(config.title_for("fr"), "Mon site");
+        assert_eq!(config.title_for("en"), "My Site");
+        assert_eq!(config.description_for("fr"), "A site");
+        assert_eq!(config.taxonomies_for("fr")[0].name, "etiquettes");
+        assert_eq!(config.taxonomies_for("en")[0].name, "tags");
+        assert_eq!(config.language_prefix("fr"), "/fr");
+        assert_eq!(config.language_prefix("en"), "");
+        assert_eq!(
+            config.languages["fr"].translations["read_more"],
+            "Lire la suite"
+        );
+    }
+
+    #[test]
+    fn test_languages_default_only_is_not_multilingual() {
+        let tmp = TempDir::new().unwrap();
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+
+[languages.en.translations]
+hello = "Hello"
+"#,
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        assert!(!config.is_multilingual());
+        assert_eq!(config.language_codes(), ["en"]);
+    }
+
+    #[test]
+    fn test_invalid_language_code() {
+        let tmp = TempDir::new().unwrap();
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+
+[languages."fr/ca"]
+title = "Bad"
+"#,
+        );
+        let err = Config::load(tmp.path()).unwrap_err().to_string();
+        assert!(err.contains("invalid language code"), "got: {err}");
+    }
+
     #[test]
     fn test_default_language_set() {
         let tmp = TempDir::new().unwrap();
@@ -477,6 +1384,9 @@ name = "categories"
         assert!(!config.markdown.external_links_no_follow);
         assert!(!config.markdown.external_links_no_referrer);
         assert!(!config.markdown.smart_punctuation);
+        assert_eq!(config.markdown.math, MathMode::None);
+        assert!(!config.markdown.definition_lists);
+        assert!(!config.markdown.wiki_links);
     }
 
     #[test]
@@ -491,10 +1401,20 @@ base_url = "https://example.com"
 highlight_code = true
 insert_anchor_links = "right"
 highlight_theme = "InspiredGitHub"
+highlight_theme_light = "Solarized (light)"
+highlight_theme_dark = "Solarized (dark)"
 external_links_target_blank = true
 external_links_no_follow = true
 external_links_no_referrer = true
 smart_punctuation = true
+math = "mathml"
+definition_lists = true
+heading_attributes = true
+block_attributes = true
+superscript = true
+subscript = true
+wiki_links = true
+abbreviations = true
 "#,
         );
         let config = Config::load(tmp.path()).unwrap();
@@ -504,10 +1424,23 @@ smart_punctuation = true
             config.markdown.highlight_theme.as_deref(),
             Some("InspiredGitHub")
         );
+        assert_eq!(
+            config.markdown.highlight_theme_light.as_deref(),
+            Some("Solarized (light)")
+        );
+        assert_eq!(
+            config.markdown.highlight_theme_dark.as_deref(),
+            Some("Solarized (dark)")
+        );
         assert!(config.markdown.external_links_target_blank);
         assert!(config.markdown.external_links_no_follow);
         assert!(config.markdown.external_links_no_referrer);
         assert!(config.markdown.smart_punctuation);
+        assert_eq!(config.markdown.math, MathMode::Mathml);
+        let markdown = &config.markdown;
+        assert!(markdown.definition_lists && markdown.heading_attributes);
+        assert!(markdown.block_attributes && markdown.superscript && markdown.subscript);
+        assert!(markdown.wiki_links && markdown.abbreviations);
     }
 
     #[test]
@@ -584,6 +1517,131 @@ url_prefix = "docs"
         assert!(dir.exclude.is_empty());
     }
 
+    #[test]
+    fn test_generators_config() {
+        let tmp = TempDir::new().unwrap();
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+
+[[generators]]
+source = "data/products.csv"
+section = "products"
+template = "product.html"
+slug = "sku"
+title = "name"
+
+[[generators]]
+source = "data/catalog.toml"
+key = "catalog.items"
+format = "toml"
+"#,
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        assert_eq!(config.generators.len(), 2);
+        let products = &config.generators[0];
+        assert_eq!(products.source, "data/products.csv");
+        assert_eq!(products.section, "products");
+        assert_eq!(products.template, "product.html");
+        assert_eq!(
+            (products.slug.as_str(), products.title.as_str()),
+            ("sku", "name")
+        );
+        assert_eq!(products.content, "content");
+        let catalog = &config.generators[1];
+        assert_eq!(catalog.key.as_deref(), Some("catalog.items"));
+        assert_eq!(catalog.format.as_deref(), Some("toml"));
+        assert_eq!(catalog.section, "");
+        assert_eq!(catalog.template, "page.html");
+        assert_eq!(catalog.slug, "slug");
+        assert_eq!(catalog.date, "date");
+        assert_eq!(catalog.description, "description");
+    }
+
+    #[test]
+    fn test_images_config() {
+        let tmp = TempDir::new().unwrap();
+        write_config(&tmp, "base_url = \"https://example.com\"\n");
+        let config = Config::load(tmp.path()).unwrap();
+        assert!(!config.images.responsive);
+        assert_eq!(config.images.widths, [480, 960, 1440]);
+        assert_eq!(config.images.sizes, "100vw");
+
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+
+[images]
+responsive = true
+widths = [320, 640]
+formats = ["avif", "png"]
+quality = 60
+sizes = "(min-width: 60em) 50vw, 100vw"
+"#,
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        assert!(config.images.responsive);
+        assert_eq!(config.images.widths, [320, 640]);
+        assert_eq!(config.images.formats, [ImageFormat::Avif, ImageFormat::Png]);
+        assert_eq!(config.images.quality, 60);
+
+        write_config(
+            &tmp,
+            "base_url = \"https://example.com\"\n[images]\nformats = [\"webp\"]\n",
+        );
+        let err = Config::load(tmp.path()).unwrap_err().to_string();
+        assert!(err.contains("\"webp\" is not supported"), "{err}");
+
+        write_config(
+            &tmp,
+            "base_url = \"https://example.com\"\n[images]\nquality = 0\n",
+        );
+        let err = Config::load(tmp.path()).unwrap_err().to_string();
+        assert!(err.contains("[images] quality"), "{err}");
+    }
+
+    #[test]
+    fn test_diagrams_config() {
+        let tmp = TempDir::new().unwrap();
+        write_config(&tmp, "base_url = \"https://example.com\"\n");
+        let config = Config::load(tmp.path()).unwrap();
+        assert!(!config.diagrams.render);
+        assert_eq!(config.diagrams.tool("dot").unwrap(), ["dot", "-Tsvg"]);
+        assert_eq!(
+            config.diagrams.tool("graphviz"),
+            config.diagrams.tool("dot")
+        );
+        assert!(config.diagrams.tool("plantuml").is_none());
+
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+
+[diagrams]
+render = true
+
+[diagrams.tools]
+mermaid = ["npx", "mmdc", "-i", "-", "-o", "-"]
+plantuml = ["plantuml", "-tsvg", "-pipe"]
+"#,
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        assert!(config.diagrams.render);
+        assert_eq!(config.diagrams.tool("mermaid").unwrap()[0], "npx");
+        assert_eq!(config.diagrams.tool("plantuml").unwrap()[0], "plantuml");
+        assert_eq!(config.diagrams.tool("d2").unwrap(), ["d2", "-", "-"]);
+
+        write_config(
+            &tmp,
+            "base_url = \"https://example.com\"\n[diagrams.tools]\ndot = []\n",
+        );
+        let err = Config::load(tmp.path()).unwrap_err().to_string();
+        assert!(err.contains("[diagrams.tools] dot"), "{err}");
+    }
+
     #[test]
     fn test_generate_feed_enabled() {
         let tmp = TempDir::new().unwrap();
@@ -612,6 +1670,150 @@ generate_md_files = true
         assert!(config.generate_md_files);
     }
 
+    #[test]
+    fn test_execute_sandbox_config() {
+        let tmp = TempDir::new().unwrap();
+        write_config(&tmp, r#"base_url = "https://example.com""#);
+        let config = Config::load(tmp.path()).unwrap();
+        assert!(!config.execute.sandbox.enable);
+        assert!(config.execute.sandbox.allow_network);
+        assert!(
+            config
+                .execute
+                .sandbox
+                .env_allow
+                .contains(&"PATH".to_string())
+        );
+
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+
+[execute.sandbox]
+enable = true
+env_allow = ["PATH", "CI"]
+memory_mb = 256
+max_file_bytes = 4096
+allow_network = false
+"#,
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        let sandbox = &config.execute.sandbox;
+        assert!(sandbox.enable);
+        assert_eq!(sandbox.env_allow, ["PATH", "CI"]);
+        assert_eq!(sandbox.memory_mb, 256);
+        assert_eq!(sandbox.cpu_seconds, 60);
+        assert_eq!(sandbox.max_output_bytes, 1024 * 1024);
+        assert_eq!(sandbox.max_file_bytes, 4096);
+        assert!(!sandbox.allow_network);
+        assert_eq!(config.execute.timeout_seconds, DEFAULT_EXEC_TIMEOUT_SECONDS);
+    }
+
+    #[test]
+    fn test_execute_language_runners() {
+        let tmp = TempDir::new().unwrap();
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+
+[execute.languages.r]
+command = ["R", "--vanilla", "-s", "-f"]
+input = "file"
+extension = "R"
+
+[execute.languages.lua]
+command = ["lua", "-e"]
+"#,
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        let r = config.execute.runner("r").unwrap();
+        assert_eq!(r.command, ["R", "--vanilla", "-s", "-f"]);
+        assert_eq!(r.input, RunnerInput::File);
+        assert_eq!(r.extension.as_deref(), Some("R"));
+        let lua = config.execute.runner("lua").unwrap();
+        assert_eq!(lua.input, RunnerInput::Arg);
+        // Presets fill in languages that are not configured
+        let duckdb = config.execute.runner("duckdb").unwrap();
+        assert_eq!(duckdb.command, ["duckdb"]);
+        assert_eq!(duckdb.input, RunnerInput::Stdin);
+        assert!(config.execute.runner("cobol").is_none());
+    }
+
+    #[test]
+    fn test_execute_images() {
+        let tmp = TempDir::new().unwrap();
+        write_config(&tmp, "base_url = \"https://example.com\"\n");
+        let config = Config::load(tmp.path()).unwrap();
+        assert_eq!(config.execute.images, ImageOutput::Inline);
+
+        write_config(
+            &tmp,
+            "base_url = \"https://example.com\"\n\n[execute]\nimages = \"files\"\n",
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        assert_eq!(config.execute.images, ImageOutput::Files);
+    }
+
+    #[test]
+    fn test_execute_block_option_defaults() {
+        let tmp = TempDir::new().unwrap();
+        write_config(
+            &tmp,
+            r#"
+base_url = "https://example.com"
+
+[execute]
+echo = false
+output = "asis"
+error = "fail"
+"#,
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        let defaults = &config.execute.block;
+        assert_eq!(defaults.echo, Some(false));
+        assert_eq!(defaults.output, Some(OutputMode::Asis));
+        assert_eq!(defaults.error, Some(ErrorMode::Fail));
+        assert_eq!(defaults.collapse, None);
+
+        // Fence options win over the site's
+        let block = BlockOptions {
+            echo: Some(true),
+            output: Some(OutputMode::Hide),
+            ..Default::default()
+        };
+        let resolved = block.or(defaults);
+        assert!(resolved.echo());
+        assert_eq!(resolved.output(), OutputMode::Hide);
+        assert_eq!(resolved.error(), ErrorMode::Fail);
+        assert!(!resolved.collapse());
+
+        write_config(
+            &tmp,
+            "base_url = \"https://example.com\"\n\n[execute]\noutput = false\n",
+        );
+        let config = Config::load(tmp.path()).unwrap();
+        assert_eq!(config.execute.block.output, Some(OutputMode::Hide));
+
+        write_config(
+            &tmp,
+            "base_url = \"https://example.com\"\n\n[execute]\noutput = \"loud\"\n",
+        );
+        assert!(Config::load(tmp.path()).is_err());
+
+        // Misspelled keys are not silently ignored
+        for key in ["on_eror = \"warn\"", "ecko = false"] {
+            write_config(
+                &tmp,
+                &format!("base_url = \"https://example.com\"\n\n[execute]\n{key}\n"),
+            );
+            let err = Config::load(tmp.path()).unwrap_err().to_string();
+            let name = key.split(' ').next().unwrap();
+            assert!(err.contains(&format!("unknown key `{name}`")), "{err}");
+        }
+    }
+
     #[test]
     fn test_unknown_top_level_keys_accepted() {
         // Config does not use #[serde(deny_unknown_fields)], so unknown keys are silently ignored
@@ -1,9 +1,11 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use walkdir::WalkDir;
 
-use crate::config::{ContentDirConfig, SortBy, default_toml_table};
+use crate::config::{Config, ContentDirConfig, SortBy, default_toml_table};
+use crate::notebook::{CodeCell, parse_notebook};
 
 /// Compute the URL path for a page given its parent directory and slug.
 /// e.g. ("posts", "hello") -> "/posts/hello/"
@@ -63,6 +65,50 @@ pub(crate) fn section_key_for(relative_path: &str) -> String {
     }
 }
 
+/// Section key for a page, keeping its language: a page translated as
+/// `posts/hello.fr.md` belongs to `posts/_index.fr.md`.
+///
+/// A suffixed page with no suffixed section falls back to the unsuffixed
+/// section when that one is in the page's language, as for `notes/beta.en.md`
+/// in an `en` site.
+pub(crate) fn page_section_key(page: &Page, sections: &HashMap<String, Section>) -> String {
+    let canonical = strip_language_suffix(&page.relative_path, &page.lang);
+    let key = section_key_for(&canonical);
+    if canonical == page.relative_path {
+        return key;
+    }
+    let translated = with_language_suffix(&key, &page.lang);
+    if !sections.contains_key(&translated)
+        && sections.get(&key).is_some_and(|s| s.lang == page.lang)
+    {
+        key
+    } else {
+        translated
+    }
+}
+
+/// Strip a `.{lang}` suffix from a content path.
+/// e.g. ("about.fr.md", "fr") -> "about.md"
+///      ("about.md", "fr") -> "about.md"
+pub(crate) fn strip_language_suffix(relative_path: &str, lang: &str) -> String {
+    if lang.is_empty() {
+        return relative_path.to_string();
+    }
+    match relative_path.strip_suffix(&format!(".{lang}.md")) {
+        Some(stem) => format!("{stem}.md"),
+        None => relative_path.to_string(),
+    }
+}
+
+/// Add a `.{lang}` suffix to a content path.
+/// e.g. ("posts/_index.md", "fr") -> "posts/_index.fr.md"
+pub(crate) fn with_language_suffix(relative_path: &str, lang: &str) -> String {
+    match relative_path.strip_suffix(".md") {
+        Some(stem) => format!("{stem}.{lang}.md"),
+        None => relative_path.to_string(),
+    }
+}
+
 /// TOML frontmatter parsed from `+++` delimiters.
 ///
 /// Unknown top-level keys (e.g. `tags`, `categories`) are captured in [`rest`](Self::rest)
@@ -158,6 +204,51 @@ pub struct Page {
     pub reading_time: usize,
     /// Path of the source file relative to the content directory.
     pub relative_path: String,
+    /// Language code of this page (e.g. `"en"`, or `"fr"` for `about.fr.md`).
+    pub lang: String,
+    /// Every language variant of this page, including itself, default language first.
+    pub translations: Vec<Translation>,
+    /// Links to downloadable versions of the page keyed by format (`"ipynb"`,
+    /// `"py"`), written when `[execute] export` is set and the page has
+    /// executable code blocks.
+    pub downloads: BTreeMap<String, String>,
+    /// Values the page's code blocks passed to templates (`zorto.set()` in
+    /// Python, `$ZORTO_DATA` elsewhere), later blocks overriding earlier ones.
+    pub exec_data: serde_json::Map<String, serde_json::Value>,
+    /// Table of contents: the page's headings down to `[markdown] toc_depth`,
+    /// nested by level.
+    pub toc: Vec<Heading>,
+    /// Code cells with their stored outputs, for pages loaded from `.ipynb` files.
+    #[serde(skip)]
+    pub notebook: Option<Arc<Vec<CodeCell>>>,
+}
+
+/// A heading in a table of contents ([`Page::toc`], [`Section::toc`]).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Heading {
+    /// Heading level, 1 for `<h1>` to 6 for `<h6>`.
+    pub level: u32,
+    /// The heading's `id` attribute.
+    pub id: String,
+    /// Heading text without markup.
+    pub title: String,
+    /// Permalink of the page or section with `#id` appended.
+    pub permalink: String,
+    /// Headings of a deeper level up to the next heading of this level or above.
+    pub children: Vec<Heading>,
+}
+
+/// One language variant of a page or section, listed in `page.translations`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Translation {
+    /// Language code (e.g. `"fr"`).
+    pub lang: String,
+    /// Title of the variant.
+    pub title: String,
+    /// URL path relative to the site root (e.g. `"/fr/about/"`).
+    pub path: String,
+    /// Full permalink including base URL.
+    pub permalink: String,
 }
 
 /// A section defined by an `_index.md` file.
@@ -191,6 +282,15 @@ pub struct Section {
     pub extra: serde_json::Value,
     /// Path of the source `_index.md` relative to the content directory.
     pub relative_path: String,
+    /// Language code of this section (e.g. `"en"`, or `"fr"` for `_index.fr.md`).
+    pub lang: String,
+    /// Every language variant of this section, including itself, default language first.
+    pub translations: Vec<Translation>,
+    /// Values the section's code blocks passed to templates, as for
+    /// [`Page::exec_data`].
+    pub exec_data: serde_json::Map<String, serde_json::Value>,
+    /// Table of contents of the section's `_index.md` body, as for [`Page::toc`].
+    pub toc: Vec<Heading>,
 }
 
 /// Parse TOML frontmatter from `+++` delimiters.
@@ -219,6 +319,19 @@ pub fn parse_frontmatter(content: &str) -> anyhow::Result<(Frontmatter, String)>
     Ok((fm, body.to_string()))
 }
 
+/// Number of lines before the body that [`parse_frontmatter`] returns.
+pub(crate) fn body_line_offset(content: &str) -> usize {
+    let content = content.trim_start_matches('\u{feff}');
+    if !content.starts_with("+++") {
+        return 0;
+    }
+    match content[3..].find("\n+++") {
+        // The closing delimiter's line, and the newline after it
+        Some(end) => content[..3 + end + 4].matches('\n').count() + 1,
+        None => 0,
+    }
+}
+
 /// Convert a TOML value (datetime or string) to a date string
 fn value_to_date_string(v: &toml::Value) -> String {
     match v {
@@ -317,6 +430,12 @@ pub fn build_page(
         word_count,
         reading_time,
         relative_path: relative_path.to_string(),
+        lang: String::new(),               // filled by the content loader
+        translations: Vec::new(),          // filled by the content loader
+        downloads: BTreeMap::new(),        // filled during rendering
+        exec_data: serde_json::Map::new(), // filled during rendering
+        toc: Vec::new(),                   // filled during rendering
+        notebook: None,
     }
 }
 
@@ -347,14 +466,131 @@ pub fn build_section(
         render_pages: fm.render_pages,
         extra,
         relative_path: relative_path.to_string(),
+        lang: String::new(),               // filled by the content loader
+        translations: Vec::new(),          // filled by the content loader
+        exec_data: serde_json::Map::new(), // filled during rendering
+        toc: Vec::new(),                   // filled during rendering
+    }
+}
+
+/// Nest the flat `headings` of a page or section at `permalink` into a table
+/// of contents, leaving out headings deeper than `max_level`.
+///
+/// A heading becomes a child of the closest heading before it with a lower
+/// level, or a top-level entry if there is none.
+pub fn build_toc(headings: &[Heading], permalink: &str, max_level: u32) -> Vec<Heading> {
+    fn insert(siblings: &mut Vec<Heading>, heading: Heading) {
+        match siblings.last_mut() {
+            Some(last) if last.level < heading.level => insert(&mut last.children, heading),
+            _ => siblings.push(heading),
+        }
+    }
+
+    let mut toc = Vec::new();
+    for heading in headings.iter().filter(|h| h.level <= max_level) {
+        insert(
+            &mut toc,
+            Heading {
+                permalink: format!("{permalink}#{}", heading.id),
+                children: Vec::new(),
+                ..heading.clone()
+            },
+        );
+    }
+    toc
+}
+
+/// Languages recognized when loading content.
+#[derive(Debug, Clone)]
+pub struct ContentLanguages {
+    /// Language of files without a language suffix.
+    pub default: String,
+    /// Additional language codes recognized as filename suffixes
+    /// (`about.fr.md`). Their content is published under `/{code}/`.
+    pub others: Vec<String>,
+    /// Whether the site configures `[languages]`. Without it no suffix is
+    /// split off, so `notes/beta.en.md` keeps its own URL.
+    pub configured: bool,
+}
+
+impl Default for ContentLanguages {
+    fn default() -> Self {
+        Self {
+            default: "en".to_string(),
+            others: Vec::new(),
+            configured: false,
+        }
+    }
+}
+
+impl ContentLanguages {
+    /// Languages configured for a site.
+    pub fn from_config(config: &Config) -> Self {
+        let mut codes = config.language_codes().into_iter().map(String::from);
+        Self {
+            default: codes.next().unwrap_or_default(),
+            others: codes.collect(),
+            configured: !config.languages.is_empty(),
+        }
+    }
+
+    /// Split a content path into its language-neutral form and language code.
+    /// e.g. "posts/hello.fr.md" -> ("posts/hello.md", "fr")
+    ///      "posts/hello.md" -> ("posts/hello.md", default)
+    fn split(&self, relative_path: &str) -> (String, String) {
+        if !self.configured {
+            return (relative_path.to_string(), self.default.clone());
+        }
+        for lang in self.others.iter().chain(std::iter::once(&self.default)) {
+            let stripped = strip_language_suffix(relative_path, lang);
+            if stripped != relative_path {
+                return (stripped, lang.clone());
+            }
+        }
+        (relative_path.to_string(), self.default.clone())
+    }
+
+    /// `relative_path` without the suffix of `lang`, if suffixes are split.
+    fn strip(&self, relative_path: &str, lang: &str) -> String {
+        if self.configured {
+            strip_language_suffix(relative_path, lang)
+        } else {
+            relative_path.to_string()
+        }
+    }
+
+    /// URL prefix for `lang`: empty for the default language, `"/fr"` otherwise.
+    fn prefix(&self, lang: &str) -> String {
+        if lang == self.default {
+            String::new()
+        } else {
+            format!("/{lang}")
+        }
+    }
+}
+
+/// Prefix a page's or section's URL with its language and record the language.
+fn localize(
+    path: &mut String,
+    permalink: &mut String,
+    lang: &mut String,
+    code: String,
+    languages: &ContentLanguages,
+    base_url: &str,
+) {
+    let prefix = languages.prefix(&code);
+    if !prefix.is_empty() {
+        *path = format!("{prefix}{path}");
+        *permalink = format!("{base_url}{path}");
     }
+    *lang = code;
 }
 
 /// Content loaded from disk: sections, pages, and co-located asset paths.
 pub struct LoadedContent {
     /// Sections keyed by their relative `_index.md` path (e.g. `"posts/_index.md"`).
     pub sections: HashMap<String, Section>,
-    /// Pages keyed by their relative `.md` path (e.g. `"posts/hello.md"`).
+    /// Pages keyed by their relative source path (e.g. `"posts/hello.md"`).
     pub pages: HashMap<String, Page>,
     /// Absolute paths to non-markdown files co-located with content.
     pub assets: Vec<PathBuf>,
@@ -362,11 +598,23 @@ pub struct LoadedContent {
 
 /// Walk the content directory and return all sections, pages, and co-located assets.
 ///
+/// Files with a language suffix from `languages` (`about.fr.md`,
+/// `_index.fr.md`) are loaded as translations: they keep their own key but
+/// get the URL of the unsuffixed file under `/{lang}/`.
+///
+/// Jupyter notebooks (`.ipynb`) are loaded as pages in the default language;
+/// see [`crate::notebook`].
+///
 /// # Errors
 ///
-/// Returns an error if the content directory cannot be walked or any markdown
-/// file has invalid frontmatter.
-pub fn load_content(content_dir: &Path, base_url: &str) -> anyhow::Result<LoadedContent> {
+/// Returns an error if the content directory cannot be walked, any markdown
+/// file has invalid frontmatter, a notebook cannot be parsed, or two files
+/// would be published at the same URL.
+pub fn load_content(
+    content_dir: &Path,
+    base_url: &str,
+    languages: &ContentLanguages,
+) -> anyhow::Result<LoadedContent> {
     let mut sections = HashMap::new();
     let mut pages = HashMap::new();
     let mut assets = Vec::new();
@@ -398,19 +646,48 @@ pub fn load_content(content_dir: &Path, base_url: &str) -> anyhow::Result<Loaded
             .ok_or_else(|| anyhow::anyhow!("content entry has no filename: {}", path.display()))?
             .to_string_lossy();
 
-        if filename == "_index.md" {
+        let (canonical, lang) = languages.split(&relative);
+        let canonical_name = Path::new(&canonical)
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        if canonical_name == "_index.md" {
             let content = std::fs::read_to_string(path)
                 .map_err(|e| anyhow::anyhow!("cannot read {}: {e}", path.display()))?;
             let (fm, body) = parse_frontmatter(&content)
                 .map_err(|e| e.context(format!("in {}", path.display())))?;
-            let section = build_section(fm, body, &relative, base_url);
+            let mut section = build_section(fm, body, &canonical, base_url);
+            section.relative_path = relative.clone();
+            localize(
+                &mut section.path,
+                &mut section.permalink,
+                &mut section.lang,
+                lang,
+                languages,
+                base_url,
+            );
             sections.insert(relative, section);
         } else if filename.ends_with(".md") {
             let content = std::fs::read_to_string(path)
                 .map_err(|e| anyhow::anyhow!("cannot read {}: {e}", path.display()))?;
             let (fm, body) = parse_frontmatter(&content)
                 .map_err(|e| e.context(format!("in {}", path.display())))?;
-            let page = build_page(fm, body, &relative, base_url);
+            let mut page = build_page(fm, body, &canonical, base_url);
+            page.relative_path = relative.clone();
+            localize(
+                &mut page.path,
+                &mut page.permalink,
+                &mut page.lang,
+                lang,
+                languages,
+                base_url,
+            );
+            pages.insert(relative, page);
+        } else if filename.ends_with(".ipynb") {
+            let mut page = load_notebook_page(path, &relative, base_url)?;
+            page.lang = languages.default.clone();
             pages.insert(relative, page);
         } else {
             // Static asset co-located with content
@@ -418,6 +695,8 @@ pub fn load_content(content_dir: &Path, base_url: &str) -> anyhow::Result<Loaded
         }
     }
 
+    check_duplicate_paths(&sections, &pages)?;
+
     Ok(LoadedContent {
         sections,
         pages,
@@ -425,6 +704,63 @@ pub fn load_content(content_dir: &Path, base_url: &str) -> anyhow::Result<Loaded
     })
 }
 
+/// Fail when two content files map to the same URL (e.g. `notes/beta.md`
+/// and `notes/beta.en.md` in a site whose default language is `en`), since
+/// one would silently overwrite the other.
+fn check_duplicate_paths(
+    sections: &HashMap<String, Section>,
+    pages: &HashMap<String, Page>,
+) -> anyhow::Result<()> {
+    let mut entries: Vec<(&str, &str)> = sections
+        .iter()
+        .map(|(key, section)| (section.path.as_str(), key.as_str()))
+        .chain(
+            pages
+                .iter()
+                .map(|(key, page)| (page.path.as_str(), key.as_str())),
+        )
+        .collect();
+    entries.sort_unstable();
+    for pair in entries.windows(2) {
+        if pair[0].0 == pair[1].0 {
+            anyhow::bail!(
+                "content/{} and content/{} both map to {}",
+                pair[0].1,
+                pair[1].1,
+                pair[0].0
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Load a Jupyter notebook as a page in the default language.
+///
+/// Like external content directories, a notebook without a frontmatter title
+/// takes its title and description from its first `# Heading` and paragraph.
+fn load_notebook_page(path: &Path, relative: &str, base_url: &str) -> anyhow::Result<Page> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("cannot read {}: {e}", path.display()))?;
+    let notebook =
+        parse_notebook(&json).map_err(|e| e.context(format!("in {}", path.display())))?;
+    let mut fm = notebook.frontmatter;
+    let mut body = notebook.body;
+    if fm.title.is_none() {
+        let (title, description) = extract_title_description(&body);
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        if title.is_some() {
+            body = strip_title_heading(&body);
+        }
+        fm.title = Some(title.unwrap_or_else(|| title_from_filename(&stem)));
+        if fm.description.is_none() {
+            fm.description = description;
+        }
+    }
+    let mut page = build_page(fm, body, relative, base_url);
+    page.notebook = Some(Arc::new(notebook.cells));
+    Ok(page)
+}
+
 /// Load an external directory of plain markdown files as content pages and sections.
 ///
 /// - `README.md` files become sections (like `_index.md`)
@@ -433,10 +769,12 @@ pub fn load_content(content_dir: &Path, base_url: &str) -> anyhow::Result<Loaded
 /// - Title is extracted from frontmatter, then the first `# Heading`
 /// - Description is extracted from frontmatter, then the first paragraph after the heading
 /// - Files listed in `config.exclude` are skipped
+/// - All content is in `default_language`
 pub fn load_content_dir(
     dir: &Path,
     config: &ContentDirConfig,
     base_url: &str,
+    default_language: &str,
 ) -> anyhow::Result<LoadedContent> {
     let mut sections = HashMap::new();
     let mut pages = HashMap::new();
@@ -535,7 +873,8 @@ pub fn load_content_dir(
             if fm.sort_by.is_none() {
                 fm.sort_by = config.sort_by;
             }
-            let section = build_section(fm, body, &rel_path, base_url);
+            let mut section = build_section(fm, body, &rel_path, base_url);
+            section.lang = default_language.to_string();
             sections.insert(rel_path, section);
         } else {
             // Regular .md → page
@@ -552,7 +891,8 @@ pub fn load_content_dir(
             if fm.template.is_none() {
                 fm.template = Some(config.template.clone());
             }
-            let page = build_page(fm, body, &rel_path, base_url);
+            let mut page = build_page(fm, body, &rel_path, base_url);
+            page.lang = default_language.to_string();
             pages.insert(rel_path, page);
         }
     }
@@ -711,8 +1051,8 @@ pub fn assign_pages_to_sections(
         section.pages.clear();
     }
 
-    for (rel_path, page) in pages {
-        let key = section_key_for(rel_path);
+    for page in pages.values() {
+        let key = page_section_key(page, sections);
         if let Some(section) = sections.get_mut(&key) {
             section.pages.push(page.clone());
         }
@@ -733,6 +1073,59 @@ pub fn assign_pages_to_sections(
     }
 }
 
+/// Fill `translations` on every page and section by grouping language
+/// variants of the same content path (`about.md`, `about.fr.md`, ...).
+///
+/// Variants are listed in `languages` order: the default language first.
+pub fn link_translations(loaded: &mut LoadedContent, languages: &ContentLanguages) {
+    let order = |lang: &str| {
+        std::iter::once(&languages.default)
+            .chain(&languages.others)
+            .position(|l| l == lang)
+            .unwrap_or(usize::MAX)
+    };
+    let group = |relative_path: &str, lang: &str| languages.strip(relative_path, lang);
+
+    let mut page_groups: HashMap<String, Vec<Translation>> = HashMap::new();
+    for page in loaded.pages.values() {
+        page_groups
+            .entry(group(&page.relative_path, &page.lang))
+            .or_default()
+            .push(Translation {
+                lang: page.lang.clone(),
+                title: page.title.clone(),
+                path: page.path.clone(),
+                permalink: page.permalink.clone(),
+            });
+    }
+    for translations in page_groups.values_mut() {
+        translations.sort_by_key(|t| order(&t.lang));
+    }
+    for page in loaded.pages.values_mut() {
+        page.translations = page_groups[&group(&page.relative_path, &page.lang)].clone();
+    }
+
+    let mut section_groups: HashMap<String, Vec<Translation>> = HashMap::new();
+    for section in loaded.sections.values() {
+        section_groups
+            .entry(group(&section.relative_path, &section.lang))
+            .or_default()
+            .push(Translation {
+                lang: section.lang.clone(),
+                title: section.title.clone(),
+                path: section.path.clone(),
+                permalink: section.permalink.clone(),
+            });
+    }
+    for translations in section_groups.values_mut() {
+        translations.sort_by_key(|t| order(&t.lang));
+    }
+    for section in loaded.sections.values_mut() {
+        section.translations =
+            section_groups[&group(&section.relative_path, &section.lang)].clone();
+    }
+}
+
 /// Escape special characters for HTML/XML output.
 ///
 /// Escapes `&`, `<`, `>`, `"`, and `'`. Safe for use in element content,
@@ -745,6 +1138,16 @@ pub fn escape_html(s: &str) -> String {
         .replace('\'', "&#x27;")
 }
 
+/// Undo [`escape_html`] (and the escaping pulldown-cmark does).
+pub(crate) fn unescape_html(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&#x27;", "'")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
 /// Alias for [`escape_html`] — used in XML contexts (Atom, sitemap) where
 /// the escaping requirements are identical.
 pub(crate) fn escape_xml(s: &str) -> String {
@@ -784,6 +1187,15 @@ mod tests {
         assert_eq!(body, "Body text here");
     }
 
+    #[test]
+    fn test_body_line_offset() {
+        let input = "+++\ntitle = \"Hello\"\n+++\nBody\n";
+        let (_, body) = parse_frontmatter(input).unwrap();
+        assert!(body.starts_with("Body"));
+        assert_eq!(input.lines().nth(body_line_offset(input)), Some("Body"));
+        assert_eq!(body_line_offset("Just markdown"), 0);
+    }
+
     #[test]
     fn test_parse_frontmatter_no_frontmatter() {
         let input = "Just plain markdown content";
@@ -887,6 +1299,37 @@ Content goes here"#;
         assert_eq!(page.path, "/hello/");
     }
 
+    #[test]
+    fn test_build_toc_nests_by_level() {
+        let heading = |level, id: &str| Heading {
+            level,
+            id: id.into(),
+            title: id.to_uppercase(),
+            ..Default::default()
+        };
+        let headings = [
+            heading(3, "early"),
+            heading(2, "a"),
+            heading(3, "a1"),
+            heading(4, "a1x"),
+            heading(3, "a2"),
+            heading(2, "b"),
+            heading(4, "b-deep"),
+        ];
+        let toc = build_toc(&headings, "https://example.com/post/", 6);
+        let ids = |hs: &[Heading]| hs.iter().map(|h| h.id.clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&toc), ["early", "a", "b"]);
+        assert_eq!(ids(&toc[1].children), ["a1", "a2"]);
+        assert_eq!(ids(&toc[1].children[0].children), ["a1x"]);
+        assert_eq!(ids(&toc[2].children), ["b-deep"]);
+        assert_eq!(toc[1].title, "A");
+        assert_eq!(toc[1].children[0].permalink, "https://example.com/post/#a1");
+
+        let shallow = build_toc(&headings, "/", 2);
+        assert_eq!(ids(&shallow), ["a", "b"]);
+        assert!(shallow.iter().all(|h| h.children.is_empty()));
+    }
+
     #[test]
     fn test_build_page_permalink() {
         let fm = Frontmatter::default();
@@ -1543,7 +1986,12 @@ Content goes here"#;
         let tmp = tempfile::TempDir::new().unwrap();
         let content_dir = tmp.path().join("content");
         std::fs::create_dir_all(&content_dir).unwrap();
-        let loaded = load_content(&content_dir, "https://example.com").unwrap();
+        let loaded = load_content(
+            &content_dir,
+            "https://example.com",
+            &ContentLanguages::default(),
+        )
+        .unwrap();
         assert!(loaded.sections.is_empty());
         assert!(loaded.pages.is_empty());
         assert!(loaded.assets.is_empty());
@@ -1568,7 +2016,12 @@ Content goes here"#;
         )
         .unwrap();
 
-        let loaded = load_content(&content_dir, "https://example.com").unwrap();
+        let loaded = load_content(
+            &content_dir,
+            "https://example.com",
+            &ContentLanguages::default(),
+        )
+        .unwrap();
         assert_eq!(loaded.sections.len(), 2);
         assert_eq!(loaded.pages.len(), 1);
         assert!(loaded.sections.contains_key("_index.md"));
@@ -1590,7 +2043,12 @@ Content goes here"#;
         .unwrap();
         std::fs::write(post_dir.join("image.png"), "fake png").unwrap();
 
-        let loaded = load_content(&content_dir, "https://example.com").unwrap();
+        let loaded = load_content(
+            &content_dir,
+            "https://example.com",
+            &ContentLanguages::default(),
+        )
+        .unwrap();
         assert_eq!(loaded.pages.len(), 1);
         assert_eq!(loaded.assets.len(), 1);
         assert!(loaded.assets[0].to_string_lossy().contains("image.png"));
@@ -1604,13 +2062,176 @@ Content goes here"#;
 
         std::fs::write(content_dir.join("plain.md"), "Just plain markdown").unwrap();
 
-        let loaded = load_content(&content_dir, "https://example.com").unwrap();
+        let loaded = load_content(
+            &content_dir,
+            "https://example.com",
+            &ContentLanguages::default(),
+        )
+        .unwrap();
         assert_eq!(loaded.pages.len(), 1);
         let page = loaded.pages.get("plain.md").unwrap();
         assert_eq!(page.title, "");
         assert_eq!(page.raw_content, "Just plain markdown");
     }
 
+    #[test]
+    fn test_load_content_notebook_page() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let content_dir = tmp.path().join("content");
+        let posts = content_dir.join("posts");
+        std::fs::create_dir_all(&posts).unwrap();
+        let notebook = serde_json::json!({
+            "nbformat": 4,
+            "metadata": {},
+            "cells": [
+                {"cell_type": "markdown", "metadata": {}, "source": "# Sales report\n\nQuarterly numbers."},
+                {"cell_type": "code", "metadata": {}, "source": "print(42)",
+                 "outputs": [{"output_type": "stream", "name": "stdout", "text": "42\n"}]},
+            ],
+        });
+        std::fs::write(posts.join("sales.ipynb"), notebook.to_string()).unwrap();
+
+        let loaded = load_content(
+            &content_dir,
+            "https://example.com",
+            &ContentLanguages::default(),
+        )
+        .unwrap();
+        assert!(loaded.assets.is_empty());
+        let page = &loaded.pages["posts/sales.ipynb"];
+        assert_eq!(page.title, "Sales report");
+        assert_eq!(page.description.as_deref(), Some("Quarterly numbers."));
+        assert_eq!(page.path, "/posts/sales/");
+        assert_eq!(page.lang, "en");
+        assert!(!page.raw_content.contains("# Sales report"));
+        assert!(page.raw_content.contains("```{python}\nprint(42)\n```"));
+        assert_eq!(page.notebook.as_ref().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_load_content_language_variants() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let content_dir = tmp.path().join("content");
+        let posts = content_dir.join("posts");
+        std::fs::create_dir_all(posts.join("deck")).unwrap();
+
+        std::fs::write(
+            content_dir.join("_index.md"),
+            "+++\ntitle = \"Home\"\n+++\n",
+        )
+        .unwrap();
+        std::fs::write(
+            content_dir.join("_index.fr.md"),
+            "+++\ntitle = \"Accueil\"\n+++\n",
+        )
+        .unwrap();
+        std::fs::write(posts.join("_index.fr.md"), "+++\ntitle = \"Blog\"\n+++\n").unwrap();
+        std::fs::write(posts.join("hello.md"), "+++\ntitle = \"Hello\"\n+++\n").unwrap();
+        std::fs::write(posts.join("hello.fr.md"), "+++\ntitle = \"Bonjour\"\n+++\n").unwrap();
+        std::fs::write(
+            posts.join("deck/index.fr.md"),
+            "+++\ntitle = \"Diapo\"\n+++\n",
+        )
+        .unwrap();
+        // Not a configured language: an ordinary page
+        std::fs::write(posts.join("notes.de.md"), "+++\ntitle = \"Notes\"\n+++\n").unwrap();
+
+        let languages = ContentLanguages {
+            default: "en".into(),
+            others: vec!["fr".into()],
+            configured: true,
+        };
+        let mut loaded = load_content(&content_dir, "https://example.com", &languages).unwrap();
+        link_translations(&mut loaded, &languages);
+
+        let hello = &loaded.pages["posts/hello.md"];
+        assert_eq!(hello.lang, "en");
+        assert_eq!(hello.path, "/posts/hello/");
+        let bonjour = &loaded.pages["posts/hello.fr.md"];
+        assert_eq!(bonjour.lang, "fr");
+        assert_eq!(bonjour.path, "/fr/posts/hello/");
+        assert_eq!(bonjour.permalink, "https://example.com/fr/posts/hello/");
+        assert_eq!(bonjour.relative_path, "posts/hello.fr.md");
+        assert_eq!(
+            page_section_key(bonjour, &loaded.sections),
+            "posts/_index.fr.md"
+        );
+
+        let langs: Vec<&str> = hello.translations.iter().map(|t| t.lang.as_str()).collect();
+        assert_eq!(langs, ["en", "fr"]);
+        assert_eq!(bonjour.translations, hello.translations);
+
+        let deck = &loaded.pages["posts/deck/index.fr.md"];
+        assert_eq!(deck.path, "/fr/posts/deck/");
+        assert_eq!(
+            page_section_key(deck, &loaded.sections),
+            "posts/_index.fr.md"
+        );
+
+        assert_eq!(loaded.sections["_index.fr.md"].path, "/fr/");
+        assert_eq!(loaded.sections["_index.md"].translations.len(), 2);
+        assert_eq!(loaded.pages["posts/notes.de.md"].path, "/posts/notes-de/");
+        assert_eq!(loaded.pages["posts/notes.de.md"].lang, "en");
+    }
+
+    #[test]
+    fn test_load_content_single_language_keeps_suffixes() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let content_dir = tmp.path().join("content");
+        let notes = content_dir.join("notes");
+        std::fs::create_dir_all(&notes).unwrap();
+        std::fs::write(notes.join("_index.md"), "+++\ntitle = \"Notes\"\n+++\n").unwrap();
+        std::fs::write(notes.join("beta.md"), "+++\ntitle = \"Beta\"\n+++\n").unwrap();
+        std::fs::write(notes.join("beta.en.md"), "+++\ntitle = \"Beta2\"\n+++\n").unwrap();
+
+        let mut loaded = load_content(
+            &content_dir,
+            "https://example.com",
+            &ContentLanguages::default(),
+        )
+        .unwrap();
+        assert_eq!(loaded.pages["notes/beta.md"].path, "/notes/beta/");
+        assert_eq!(loaded.pages["notes/beta.en.md"].path, "/notes/beta-en/");
+
+        assign_pages_to_sections(&mut loaded.sections, &loaded.pages);
+        assert_eq!(loaded.sections["notes/_index.md"].pages.len(), 2);
+    }
+
+    #[test]
+    fn test_load_content_rejects_duplicate_paths() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        let content_dir = tmp.path().join("content");
+        let notes = content_dir.join("notes");
+        std::fs::create_dir_all(&notes).unwrap();
+        std::fs::write(notes.join("beta.md"), "+++\ntitle = \"Beta\"\n+++\n").unwrap();
+        std::fs::write(notes.join("beta.en.md"), "+++\ntitle = \"Beta2\"\n+++\n").unwrap();
+
+        let languages = ContentLanguages {
+            default: "en".into(),
+            others: vec!["fr".into()],
+            configured: true,
+        };
+        let err = load_content(&content_dir, "https://example.com", &languages)
+            .err()
+            .unwrap()
+            .to_string();
+        assert_eq!(
+            err,
+            "content/notes/beta.en.md and content/notes/beta.md both map to /notes/beta/"
+        );
+    }
+
+    #[test]
+    fn test_language_suffix_helpers() {
+        assert_eq!(strip_language_suffix("about.fr.md", "fr"), "about.md");
+        assert_eq!(strip_language_suffix("about.md", "fr"), "about.md");
+        assert_eq!(strip_language_suffix("about.fr.md", ""), "about.fr.md");
+        assert_eq!(
+            with_language_suffix("posts/_index.md", "fr"),
+            "posts/_index.fr.md"
+        );
+    }
+
     // --- extract_title_description tests ---
 
     #[test]
@@ -0,0 +1,388 @@
+//! Data files for templates.
+//!
+//! Files in the site's `data/` directory are loaded with the site and exposed
+//! as `data.<file stem>` in every template context (`data/team.toml` becomes
+//! `data.team`; subdirectories nest). The `load_data(path=...)` template
+//! function reads any other file inside the sandbox on demand.
+//!
+//! TOML, JSON and YAML files become the values they hold. CSV files become
+//! `{headers: [...], records: [[...], ...]}`, as in Zola. Other files load as
+//! plain text with `load_data`.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Format of a data file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum DataFormat {
+    Toml,
+    Json,
+    Yaml,
+    Csv,
+    Plain,
+}
+
+impl DataFormat {
+    /// Parse a `format=` argument.
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "toml" => Some(Self::Toml),
+            "json" => Some(Self::Json),
+            "yaml" | "yml" => Some(Self::Yaml),
+            "csv" => Some(Self::Csv),
+            "plain" => Some(Self::Plain),
+            _ => None,
+        }
+    }
+
+    /// Structured format of a file, by extension.
+    fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?;
+        Self::from_name(ext).filter(|format| *format != Self::Plain)
+    }
+}
+
+/// The format named by a `format` option, else the one `path`'s extension
+/// names, else plain text.
+pub(crate) fn format_for(path: &Path, format: Option<&str>) -> anyhow::Result<DataFormat> {
+    match format {
+        Some(name) => DataFormat::from_name(name).ok_or_else(|| {
+            anyhow::anyhow!("unknown format '{name}' (expected toml, json, yaml, csv or plain)")
+        }),
+        None => Ok(DataFormat::from_path(path).unwrap_or(DataFormat::Plain)),
+    }
+}
+
+/// Parse the contents of a data file.
+pub(crate) fn parse(text: &str, format: DataFormat) -> anyhow::Result<serde_json::Value> {
+    match format {
+        DataFormat::Toml => {
+            let value: toml::Value =
+                toml::from_str(text).map_err(|e| anyhow::anyhow!("invalid TOML: {e}"))?;
+            Ok(crate::content::toml_to_json(&value))
+        }
+        DataFormat::Json => {
+            serde_json::from_str(text).map_err(|e| anyhow::anyhow!("invalid JSON: {e}"))
+        }
+        DataFormat::Yaml => {
+            let docs = yaml_rust2::YamlLoader::load_from_str(text)
+                .map_err(|e| anyhow::anyhow!("invalid YAML: {e}"))?;
+            Ok(docs.first().map(yaml_to_json).unwrap_or_default())
+        }
+        DataFormat::Csv => parse_csv(text),
+        DataFormat::Plain => Ok(serde_json::Value::String(text.to_string())),
+    }
+}
+
+fn yaml_to_json(yaml: &yaml_rust2::Yaml) -> serde_json::Value {
+    use yaml_rust2::Yaml;
+    match yaml {
+        Yaml::String(s) => serde_json::Value::String(s.clone()),
+        Yaml::Integer(i) => serde_json::json!(*i),
+        Yaml::Real(r) => r
+            .parse::<f64>()
+            .map(|f| serde_json::json!(f))
+            .unwrap_or_else(|_| serde_json::Value::String(r.clone())),
+        Yaml::Boolean(b) => serde_json::Value::Bool(*b),
+        Yaml::Array(items) => serde_json::Value::Array(items.iter().map(yaml_to_json).collect()),
+        Yaml::Hash(hash) => serde_json::Value::Object(
+            hash.iter()
+                .map(|(k, v)| {
+                    let key = match yaml_to_json(k) {
+                        serde_json::Value::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    (key, yaml_to_json(v))
+                })
+                .collect(),
+        ),
+        Yaml::Null | Yaml::Alias(_) | Yaml::BadValue => serde_json::Value::Null,
+    }
+}
+
+/// Parse CSV into its header row and records. Every record must have as
+/// many fields as the header.
+fn parse_csv(text: &str) -> anyhow::Result<serde_json::Value> {
+    let mut reader = csv::Reader::from_reader(text.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| anyhow::anyhow!("invalid CSV: {e}"))?
+        .iter()
+        .map(String::from)
+        .collect();
+    let records = reader
+        .records()
+        .map(|record| {
+            let record = record.map_err(|e| anyhow::anyhow!("invalid CSV: {e}"))?;
+            Ok(record.iter().map(String::from).collect())
+        })
+        .collect::<anyhow::Result<Vec<Vec<String>>>>()?;
+    Ok(serde_json::json!({"headers": headers, "records": records}))
+}
+
+/// Load every TOML, JSON, YAML and CSV file under `dir`, keyed by file stem
+/// and nested by subdirectory. Other files are ignored; a missing directory
+/// is empty.
+pub(crate) fn load_data_dir(
+    dir: &Path,
+) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+    let mut data = serde_json::Map::new();
+    if !dir.is_dir() {
+        return Ok(data);
+    }
+    let mut defined: HashMap<Vec<String>, PathBuf> = HashMap::new();
+    let entries = walkdir::WalkDir::new(dir)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'));
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let Some(format) = DataFormat::from_path(path).filter(|_| entry.file_type().is_file())
+        else {
+            continue;
+        };
+        let rel = path.strip_prefix(dir)?;
+        let mut keys: Vec<String> = rel
+            .parent()
+            .into_iter()
+            .flat_map(|p| p.components())
+            .map(|c| c.as_os_str().to_string_lossy().into_owned())
+            .collect();
+        keys.push(
+            path.file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        );
+        if let Some(other) = defined.insert(keys.clone(), path.to_path_buf()) {
+            anyhow::bail!(
+                "{} and {} both define `data.{}`",
+                other.display(),
+                path.display(),
+                keys.join(".")
+            );
+        }
+
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("cannot read {}: {e}", path.display()))?;
+        let value = parse(&text, format).map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+
+        let (name, parents) = keys.split_last().expect("keys end with the file stem");
+        let mut table = &mut data;
+        for key in parents {
+            let entry = table
+                .entry(key.clone())
+                .or_insert_with(|| serde_json::Value::Object(Default::default()));
+            table = match entry {
+                serde_json::Value::Object(map) => map,
+                _ => anyhow::bail!(
+                    "{} conflicts with the data file `data.{key}`",
+                    path.display()
+                ),
+            };
+        }
+        if table.contains_key(name) {
+            anyhow::bail!(
+                "{} conflicts with the data directory `data.{}`",
+                path.display(),
+                keys.join(".")
+            );
+        }
+        table.insert(name.clone(), value);
+    }
+    Ok(data)
+}
+
+/// Reads files for the `load_data()` template function, keeping each parsed
+/// file for the rest of the build.
+#[derive(Debug, Clone)]
+pub(crate) struct DataLoader {
+    root: PathBuf,
+    sandbox: PathBuf,
+    loaded: Arc<Mutex<HashMap<(PathBuf, DataFormat), serde_json::Value>>>,
+}
+
+impl DataLoader {
+    /// A loader for paths relative to `root` (`@/` paths relative to its
+    /// `content/` directory) that may not leave `sandbox`.
+    pub(crate) fn new(root: &Path, sandbox: &Path) -> Self {
+        DataLoader {
+            root: root.to_path_buf(),
+            sandbox: sandbox.to_path_buf(),
+            loaded: Default::default(),
+        }
+    }
+
+    /// Load and parse the file at `path`, in `format` or the format its
+    /// extension names (plain text otherwise). Returns `None` if the file
+    /// does not exist.
+    pub(crate) fn load(
+        &self,
+        path: &str,
+        format: Option<&str>,
+    ) -> anyhow::Result<Option<serde_json::Value>> {
+        let file_path = match path.strip_prefix("@/") {
+            Some(rel) => self.root.join("content").join(rel),
+            None => self.root.join(path),
+        };
+        if !file_path.exists() {
+            return Ok(None);
+        }
+        let canonical = file_path
+            .canonicalize()
+            .map_err(|e| anyhow::anyhow!("cannot resolve {}: {e}", file_path.display()))?;
+        let canonical_sandbox = self
+            .sandbox
+            .canonicalize()
+            .map_err(|e| anyhow::anyhow!("cannot resolve sandbox root: {e}"))?;
+        if !canonical.starts_with(&canonical_sandbox) {
+            anyhow::bail!(
+                "path '{path}' escapes sandbox '{}'. \
+                 Pass --sandbox <dir> to widen the boundary, or move the file inside --root.",
+                self.sandbox.display()
+            );
+        }
+        let format = format_for(&canonical, format)?;
+
+        let key = (canonical, format);
+        let mut loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(value) = loaded.get(&key) {
+            return Ok(Some(value.clone()));
+        }
+        let text = std::fs::read_to_string(&key.0)
+            .map_err(|e| anyhow::anyhow!("cannot read {}: {e}", file_path.display()))?;
+        let value = parse(&text, format).map_err(|e| anyhow::anyhow!("{path}: {e}"))?;
+        loaded.insert(key, value.clone());
+        Ok(Some(value))
+    }
+
+    /// Canonical paths of the files loaded so far.
+    pub(crate) fn loaded_files(&self) -> BTreeSet<PathBuf> {
+        let loaded = self.loaded.lock().unwrap_or_else(|e| e.into_inner());
+        loaded.keys().map(|(path, _)| path.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse_formats() {
+        let toml = parse("name = \"Ada\"\nyears = [1815, 1852]\n", DataFormat::Toml).unwrap();
+        assert_eq!(
+            toml,
+            serde_json::json!({"name": "Ada", "years": [1815, 1852]})
+        );
+        let json = parse(r#"{"ok": true}"#, DataFormat::Json).unwrap();
+        assert_eq!(json["ok"], true);
+        let yaml = parse(
+            "- name: Ada\n  score: 9.5\n- name: Grace\n  admin: true\n",
+            DataFormat::Yaml,
+        )
+        .unwrap();
+        assert_eq!(
+            yaml,
+            serde_json::json!([{"name": "Ada", "score": 9.5}, {"name": "Grace", "admin": true}])
+        );
+        assert_eq!(
+            parse("", DataFormat::Yaml).unwrap(),
+            serde_json::Value::Null
+        );
+        assert_eq!(parse("hi\n", DataFormat::Plain).unwrap(), "hi\n");
+        let err = parse("name = ", DataFormat::Toml).unwrap_err().to_string();
+        assert!(err.contains("invalid TOML"), "{err}");
+    }
+
+    #[test]
+    fn test_parse_csv() {
+        let csv = "name,role\r\nAda,\"Engineer, lead\"\nGrace,\"Says \"\"hi\"\"\"\n\n";
+        assert_eq!(
+            parse(csv, DataFormat::Csv).unwrap(),
+            serde_json::json!({
+                "headers": ["name", "role"],
+                "records": [["Ada", "Engineer, lead"], ["Grace", "Says \"hi\""]],
+            })
+        );
+        let multiline = parse("a\n\"one\ntwo\"", DataFormat::Csv).unwrap();
+        assert_eq!(multiline["records"][0][0], "one\ntwo");
+        let err = parse("a,b\n1\n", DataFormat::Csv).unwrap_err().to_string();
+        assert!(err.contains("found record with 1 fields"), "{err}");
+        let bom = parse("\u{feff}a\n1\n", DataFormat::Csv).unwrap();
+        assert_eq!(bom["headers"][0], "a");
+    }
+
+    #[test]
+    fn test_load_data_dir() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path().join("data");
+        std::fs::create_dir_all(dir.join("api")).unwrap();
+        std::fs::write(dir.join("team.toml"), "lead = \"Ada\"\n").unwrap();
+        std::fs::write(dir.join("changelog.yaml"), "- v1\n- v2\n").unwrap();
+        std::fs::write(dir.join("api/endpoints.json"), r#"["/users"]"#).unwrap();
+        std::fs::write(dir.join("README.md"), "# ignored").unwrap();
+        std::fs::write(dir.join(".hidden.json"), "not json").unwrap();
+
+        let data = load_data_dir(&dir).unwrap();
+        assert_eq!(data["team"]["lead"], "Ada");
+        assert_eq!(data["changelog"], serde_json::json!(["v1", "v2"]));
+        assert_eq!(data["api"]["endpoints"][0], "/users");
+        assert!(!data.contains_key("README"));
+        assert!(
+            load_data_dir(&tmp.path().join("missing"))
+                .unwrap()
+                .is_empty()
+        );
+
+        std::fs::write(dir.join("team.json"), "{}").unwrap();
+        let err = load_data_dir(&dir).unwrap_err().to_string();
+        assert!(err.contains("both define `data.team`"), "{err}");
+    }
+
+    #[test]
+    fn test_load_data_dir_reports_file() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("broken.json"), "{").unwrap();
+        let err = load_data_dir(tmp.path()).unwrap_err().to_string();
+        assert!(
+            err.contains("broken.json") && err.contains("invalid JSON"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn test_loader_formats_and_sandbox() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("site");
+        std::fs::create_dir_all(root.join("content/blog")).unwrap();
+        std::fs::write(root.join("content/blog/stats.csv"), "n\n1\n").unwrap();
+        std::fs::write(root.join("notes.txt"), "x = 1").unwrap();
+        std::fs::write(tmp.path().join("secret.toml"), "key = 1").unwrap();
+
+        let loader = DataLoader::new(&root, &root);
+        let csv = loader.load("@/blog/stats.csv", None).unwrap().unwrap();
+        assert_eq!(csv["records"][0][0], "1");
+        assert_eq!(loader.load("notes.txt", None).unwrap().unwrap(), "x = 1");
+        let toml = loader.load("notes.txt", Some("toml")).unwrap().unwrap();
+        assert_eq!(toml["x"], 1);
+        assert!(loader.load("missing.json", None).unwrap().is_none());
+        assert_eq!(loader.loaded_files().len(), 2);
+
+        let err = loader.load("../secret.toml", None).unwrap_err().to_string();
+        assert!(err.contains("escapes sandbox"), "{err}");
+        let err = loader
+            .load("notes.txt", Some("xml"))
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("unknown format"), "{err}");
+
+        let wide = DataLoader::new(&root, tmp.path());
+        assert_eq!(
+            wide.load("../secret.toml", None).unwrap().unwrap()["key"],
+            1
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Dependency tracking for incremental rebuilds.
+//!
+//! During a build every page and section records what its output was derived
+//! from: the shortcodes it invoked, the local files it pulled in with
+//! `include`, the files its code blocks declared with `deps=`, and the
+//! template it was rendered with. Templates additionally
+//! record which other templates they `extends`, `include` or `import`. When a
+//! file changes, [`DependencyGraph`] answers "which outputs are stale?" so
+//! [`Site::rebuild`](crate::site::Site::rebuild) can re-render only those.
+
+use regex::Regex;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+/// Matches a Tera tag that references other templates by name.
+static TEMPLATE_REF_TAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\{%-?\s*(?:extends|include|import)\s+([^%]*)-?%\}"#).unwrap());
+/// Matches a double- or single-quoted string literal inside a tag.
+static QUOTED_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#""([^"]+)"|'([^']+)'"#).unwrap());
+
+/// What a single page or section depended on during its last render.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContentDeps {
+    /// Shortcode names invoked by the content (built-in or template-based).
+    pub shortcodes: BTreeSet<String>,
+    /// Local files pulled in via the `include` shortcode.
+    pub includes: BTreeSet<PathBuf>,
+    /// Files its executed code blocks read, from their `deps=` attributes.
+    pub data: BTreeSet<DataPattern>,
+    /// Top-level template the output was rendered with (e.g. `"page.html"`).
+    pub template: String,
+}
+
+/// A code block's `deps=` glob, anchored at the directory its matches live
+/// under.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DataPattern {
+    /// Directory the glob is relative to (canonical where it exists).
+    pub dir: PathBuf,
+    /// Glob matched against paths relative to `dir`.
+    pub glob: String,
+}
+
+impl DataPattern {
+    /// Whether `path` is a file this pattern matches.
+    pub fn matches(&self, path: &Path) -> bool {
+        let Ok(rel) = path.strip_prefix(&self.dir) else {
+            return false;
+        };
+        let rel = rel.to_string_lossy().replace('\\', "/");
+        crate::cache::compile_glob(&self.glob).is_ok_and(|glob| glob.is_match(rel))
+    }
+}
+
+/// Dependency graph recorded by the last build of a [`Site`](crate::site::Site).
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Per-content dependencies, keyed like `Site::pages` / `Site::sections`.
+    pub content: HashMap<String, ContentDeps>,
+    /// Template name → templates it directly extends, includes or imports.
+    pub templates: HashMap<String, BTreeSet<String>>,
+    /// Files templates read with `load_data()` (canonical paths).
+    pub loaded_data: BTreeSet<PathBuf>,
+}
+
+impl DependencyGraph {
+    /// Content keys that invoked the shortcode `name`.
+    pub fn dependents_of_shortcode(&self, name: &str) -> HashSet<String> {
+        self.content
+            .iter()
+            .filter(|(_, deps)| deps.shortcodes.contains(name))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Content keys that included the local file at `path`.
+    pub fn dependents_of_include(&self, path: &Path) -> HashSet<String> {
+        self.content
+            .iter()
+            .filter(|(_, deps)| deps.includes.iter().any(|p| p == path))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Content keys with a code block whose `deps=` matches the file at `path`.
+    pub fn dependents_of_data(&self, path: &Path) -> HashSet<String> {
+        self.content
+            .iter()
+            .filter(|(_, deps)| deps.data.iter().any(|p| p.matches(path)))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Directories holding files that code blocks declared with `deps=` or
+    /// templates read with `load_data()`, for a file watcher to observe.
+    pub fn data_dirs(&self) -> BTreeSet<PathBuf> {
+        self.content
+            .values()
+            .flat_map(|deps| deps.data.iter().map(|p| p.dir.clone()))
+            .chain(
+                self.loaded_data
+                    .iter()
+                    .filter_map(|path| path.parent().map(Path::to_path_buf)),
+            )
+            .collect()
+    }
+
+    /// All templates whose rendered output changes when `name` changes:
+    /// `name` itself plus every template that transitively references it.
+    pub fn templates_affected_by(&self, name: &str) -> HashSet<String> {
+        let mut affected = HashSet::from([name.to_string()]);
+        let mut frontier = vec![name.to_string()];
+        while let Some(current) = frontier.pop() {
+            for (template, refs) in &self.templates {
+                if refs.contains(&current) && affected.insert(template.clone()) {
+                    frontier.push(template.clone());
+                }
+            }
+        }
+        affected
+    }
+
+    /// Content keys whose output was rendered with a template in `templates`.
+    pub fn dependents_of_templates(&self, templates: &HashSet<String>) -> HashSet<String> {
+        self.content
+            .iter()
+            .filter(|(_, deps)| templates.contains(&deps.template))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+}
+
+/// Extract the names of templates referenced by `{% extends %}`,
+/// `{% include %}` and `{% import %}` tags in a template source.
+pub fn template_references(source: &str) -> BTreeSet<String> {
+    let mut refs = BTreeSet::new();
+    for tag in TEMPLATE_REF_TAG_RE.captures_iter(source) {
+        for quoted in QUOTED_RE.captures_iter(&tag[1]) {
+            if let Some(name) = quoted.get(1).or_else(|| quoted.get(2)) {
+                refs.insert(name.as_str().to_string());
+            }
+        }
+    }
+    refs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn graph() -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        graph.templates.insert("base.html".into(), BTreeSet::new());
+        graph.templates.insert(
+            "page.html".into(),
+            BTreeSet::from(["base.html".to_string(), "macros/post.html".to_string()]),
+        );
+        graph.templates.insert(
+            "section.html".into(),
+            BTreeSet::from(["base.html".to_string()]),
+        );
+        graph.content.insert(
+            "posts/hello.md".into(),
+            ContentDeps {
+                shortcodes: BTreeSet::from(["note".to_string()]),
+                includes: BTreeSet::from([PathBuf::from("/site/snippets/a.md")]),
+                data: BTreeSet::from([DataPattern {
+                    dir: PathBuf::from("/site/data"),
+                    glob: "*.csv".into(),
+                }]),
+                template: "page.html".into(),
+            },
+        );
+        graph.content.insert(
+            "posts/_index.md".into(),
+            ContentDeps {
+                template: "section.html".into(),
+                ..Default::default()
+            },
+        );
+        graph
+    }
+
+    #[test]
+    fn test_template_references() {
+        let src = r#"{% extends "base.html" %}
+{% import 'macros/post.html' as post %}
+{%- include ["partials/a.html", "partials/b.html"] ignore missing -%}"#;
+        let refs = template_references(src);
+        assert!(refs.contains("base.html"));
+        assert!(refs.contains("macros/post.html"));
+        assert!(refs.contains("partials/a.html"));
+        assert!(refs.contains("partials/b.html"));
+        assert_eq!(refs.len(), 4);
+    }
+
+    #[test]
+    fn test_templates_affected_transitively() {
+        let graph = graph();
+        let affected = graph.templates_affected_by("base.html");
+        assert!(affected.contains("base.html"));
+        assert!(affected.contains("page.html"));
+        assert!(affected.contains("section.html"));
+
+        let affected = graph.templates_affected_by("macros/post.html");
+        assert!(affected.contains("page.html"));
+        assert!(!affected.contains("section.html"));
+    }
+
+    #[test]
+    fn test_dependents_lookup() {
+        let mut graph = graph();
+        assert_eq!(
+            graph.dependents_of_shortcode("note"),
+            HashSet::from(["posts/hello.md".to_string()])
+        );
+        assert!(graph.dependents_of_shortcode("tabs").is_empty());
+        assert_eq!(
+            graph.dependents_of_include(Path::new("/site/snippets/a.md")),
+            HashSet::from(["posts/hello.md".to_string()])
+        );
+        assert_eq!(
+            graph.dependents_of_data(Path::new("/site/data/sales.csv")),
+            HashSet::from(["posts/hello.md".to_string()])
+        );
+        assert!(
+            graph
+                .dependents_of_data(Path::new("/site/data/raw/sales.csv"))
+                .is_empty()
+        );
+        assert!(
+            graph
+                .dependents_of_data(Path::new("/site/data/notes.txt"))
+                .is_empty()
+        );
+        assert_eq!(
+            graph.data_dirs(),
+            BTreeSet::from([PathBuf::from("/site/data")])
+        );
+        graph
+            .loaded_data
+            .insert(PathBuf::from("/site/static/team.csv"));
+        assert_eq!(
+            graph.data_dirs(),
+            BTreeSet::from([PathBuf::from("/site/data"), PathBuf::from("/site/static")])
+        );
+        let templates = graph.templates_affected_by("section.html");
+        assert_eq!(
+            graph.dependents_of_templates(&templates),
+            HashSet::from(["posts/_index.md".to_string()])
+        );
+    }
+}
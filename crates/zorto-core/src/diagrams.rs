@@ -0,0 +1,352 @@
+//! Build-time diagrams: `mermaid`, `dot` and `d2` code blocks (and blocks
+//! of any language in `[diagrams.tools]`) and the `mermaid` shortcode
+//! rendered to inline SVG by local command-line tools.
+//!
+//! Each tool reads the diagram source on stdin and writes SVG to stdout.
+//! SVGs are kept in `.zorto/cache/diagrams/` under a hash of the tool
+//! command and the source, so an unchanged diagram never runs its tool
+//! again. A diagram whose tool is missing or fails keeps its source.
+//!
+//! Tools such as `mmdc` give every SVG the same root id and scope its styles
+//! and markers by it, so the root id is replaced with one derived from the
+//! hash; otherwise the diagrams on a page would restyle each other.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::{LazyLock, Mutex};
+
+use regex::Regex;
+
+use crate::cache;
+use crate::config::{Config, DiagramsConfig, MarkdownConfig};
+use crate::content::{escape_xml, unescape_html};
+use crate::execute;
+use crate::markdown;
+
+/// Directory of rendered diagrams inside the cache directory.
+const CACHE_DIR: &str = "diagrams";
+
+static PLACEHOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!-- DIAGRAM_(\d+) -->").unwrap());
+/// Output of the `mermaid` shortcode.
+static MERMAID_PRE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"(?s)<pre class="mermaid">(.*?)</pre>"#).unwrap());
+/// The `id` of an SVG's root element.
+static ROOT_ID_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"^<svg\b[^>]*?\sid="([^"]+)""#).unwrap());
+
+/// Diagram code blocks taken out of markdown by
+/// [`DiagramRenderer::extract_blocks`].
+pub(crate) struct ExtractedDiagrams {
+    /// The markdown, with a placeholder for each rendered block.
+    pub(crate) content: String,
+    /// The rendered blocks' HTML, by placeholder index.
+    pub(crate) html: Vec<String>,
+}
+
+/// Renders diagrams with the tools in `[diagrams]`.
+pub(crate) struct DiagramRenderer {
+    root: PathBuf,
+    config: DiagramsConfig,
+    timeout_seconds: u64,
+    /// Programs already reported missing, so each is warned about once.
+    missing: Mutex<HashSet<String>>,
+    /// Cached SVGs by the key of the content that rendered them.
+    used: Mutex<HashMap<String, BTreeSet<PathBuf>>>,
+}
+
+impl DiagramRenderer {
+    pub(crate) fn new(root: &Path, config: &Config) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            config: config.diagrams.clone(),
+            timeout_seconds: config.execute.timeout_seconds,
+            missing: Mutex::new(HashSet::new()),
+            used: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// The cached SVGs the content at `key` rendered since the last call,
+    /// recorded in the cache manifest so `zorto cache prune` keeps them.
+    pub(crate) fn take_used(&self, key: &str) -> Vec<PathBuf> {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        used.remove(key).unwrap_or_default().into_iter().collect()
+    }
+
+    /// Replace the diagram code blocks in `content` that render with
+    /// placeholders for [`insert`](Self::insert). Blocks that do not render
+    /// stay, and show as code. `key` names the content in warnings.
+    pub(crate) fn extract_blocks(
+        &self,
+        content: &str,
+        markdown_config: &MarkdownConfig,
+        key: &str,
+    ) -> ExtractedDiagrams {
+        let mut out = String::new();
+        let mut html = Vec::new();
+        let mut last = 0;
+        for block in markdown::fenced_code_blocks(content, markdown_config) {
+            let Some(svg) = self.render(&block.language, &block.source, key) else {
+                continue;
+            };
+            out.push_str(&content[last..block.range.start]);
+            out.push_str(&format!("<!-- DIAGRAM_{} -->", html.len()));
+            if content[block.range.clone()].ends_with('\n') {
+                out.push('\n');
+            }
+            last = block.range.end;
+            html.push(svg);
+        }
+        out.push_str(&content[last..]);
+        ExtractedDiagrams { content: out, html }
+    }
+
+    /// Put the blocks from [`extract_blocks`](Self::extract_blocks) into
+    /// rendered `html`, and render the `mermaid` shortcode's diagrams.
+    pub(crate) fn insert(&self, html: &str, diagrams: &[String], key: &str) -> String {
+        let html = PLACEHOLDER_RE.replace_all(html, |caps: &regex::Captures| {
+            caps[1]
+                .parse::<usize>()
+                .ok()
+                .and_then(|i| diagrams.get(i))
+                .cloned()
+                .unwrap_or_else(|| caps[0].to_string())
+        });
+        MERMAID_PRE_RE
+            .replace_all(&html, |caps: &regex::Captures| {
+                self.render("mermaid", &unescape_html(&caps[1]), key)
+                    .unwrap_or_else(|| caps[0].to_string())
+            })
+            .into_owned()
+    }
+
+    /// A `language` diagram as HTML, from the cache or its tool. `None`,
+    /// after a warning if the tool could not render it, if it does not
+    /// render.
+    fn render(&self, language: &str, source: &str, key: &str) -> Option<String> {
+        let command = self.config.tool(language)?;
+        let hash = cache::hash_source(&format!("{}\0{source}", command.join("\0")));
+        let cached = cache::cache_dir(&self.root)
+            .join(CACHE_DIR)
+            .join(format!("{hash}.svg"));
+        let svg = match std::fs::read_to_string(&cached) {
+            Ok(svg) => {
+                self.record(key, cached);
+                svg
+            }
+            Err(_) => {
+                let svg = self.run_tool(&command, source).map_err(|e| {
+                    if let Some(program) = e.downcast_ref::<MissingTool>() {
+                        if self.missing.lock().unwrap().insert(program.0.clone()) {
+                            eprintln!(
+                                "warning: `{}` not found; {language} diagrams show their source \
+                                 (configure it in [diagrams.tools])",
+                                program.0
+                            );
+                        }
+                    } else {
+                        eprintln!("warning: {key}: {language} diagram: {e}");
+                    }
+                });
+                let svg = svg.ok()?;
+                match cache::write_atomic(&cached, svg.as_bytes()) {
+                    Ok(()) => self.record(key, cached),
+                    Err(e) => {
+                        eprintln!("warning: failed to cache {language} diagram for {key}: {e}")
+                    }
+                }
+                svg
+            }
+        };
+        Some(format!(
+            "<div class=\"diagram diagram-{}\">{}</div>",
+            escape_xml(language),
+            unique_root_id(&svg, &hash)
+        ))
+    }
+
+    fn record(&self, key: &str, cached: PathBuf) {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        used.entry(key.to_string()).or_default().insert(cached);
+    }
+
+    /// Run `command` on `source`, returning the `<svg>` element it printed.
+    fn run_tool(&self, command: &[String], source: &str) -> anyhow::Result<String> {
+        let (program, args) = command
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("empty command"))?;
+        let mut cmd = Command::new(program);
+        cmd.args(args).current_dir(&self.root);
+        let output = execute::run_with_timeout(cmd, self.timeout_seconds, None, Some(source))
+            .map_err(|e| match e.downcast_ref::<std::io::Error>() {
+                Some(io) if io.kind() == std::io::ErrorKind::NotFound => {
+                    anyhow::Error::new(MissingTool(program.clone()))
+                }
+                _ => anyhow::anyhow!("failed to run `{program}`: {e}"),
+            })?;
+        if !output.status.success() {
+            anyhow::bail!(
+                "`{program}` failed ({}): {}",
+                output.status,
+                output.stderr.trim()
+            );
+        }
+        // Drop the XML declaration and doctype some tools print first
+        match output.stdout.find("<svg") {
+            Some(start) => Ok(output.stdout[start..].trim_end().to_string()),
+            None => anyhow::bail!("`{program}` printed no <svg> element"),
+        }
+    }
+}
+
+/// `svg` with its root element's id, and the references to it in styles,
+/// `url(#...)` and derived ids such as `my-svg_flowchart-pointEnd`,
+/// replaced with `diagram-` and the start of `hash`.
+fn unique_root_id(svg: &str, hash: &str) -> String {
+    let Some(id) = ROOT_ID_RE.captures(svg).map(|caps| caps[1].to_string()) else {
+        return svg.to_string();
+    };
+    let unique = format!("diagram-{}", &hash[..16]);
+    let re = Regex::new(&format!(
+        r##"(["#]){}([^A-Za-z0-9]|$)"##,
+        regex::escape(&id)
+    ))
+    .unwrap();
+    re.replace_all(svg, |caps: &regex::Captures| {
+        format!("{}{unique}{}", &caps[1], &caps[2])
+    })
+    .into_owned()
+}
+
+/// A diagram tool that is not installed.
+#[derive(Debug)]
+struct MissingTool(String);
+
+impl std::fmt::Display for MissingTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "`{}` not found", self.0)
+    }
+}
+
+impl std::error::Error for MissingTool {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A renderer whose `dot` tool wraps its input in `<svg>` after an XML
+    /// declaration, and counts its runs in `runs`.
+    fn renderer(tmp: &TempDir, tools: &str) -> DiagramRenderer {
+        let config_toml = format!(
+            "base_url = \"https://example.com\"\n[diagrams]\nrender = true\n[diagrams.tools]\n{tools}"
+        );
+        std::fs::write(tmp.path().join("config.toml"), config_toml).unwrap();
+        let config = Config::load(tmp.path()).unwrap();
+        DiagramRenderer::new(tmp.path(), &config)
+    }
+
+    const ECHO_TOOL: &str = r#"dot = ["sh", "-c", "echo run >> runs; printf '<?xml version=\"1.0\"?>\n<svg>%s</svg>\n' \"$(cat)\""]
+mermaid = ["sh", "-c", "printf '<svg>%s</svg>' \"$(cat)\""]
+"#;
+
+    #[test]
+    fn test_extract_and_insert_blocks() {
+        let tmp = TempDir::new().unwrap();
+        let diagrams = renderer(&tmp, ECHO_TOOL);
+        let content =
+            "Intro\n\n- item\n\n  ```dot\n  a -> b\n  ```\n\n```rust\nfn main() {}\n```\n";
+        let extracted = diagrams.extract_blocks(content, &MarkdownConfig::default(), "page.md");
+        assert_eq!(
+            extracted.content,
+            "Intro\n\n- item\n\n  <!-- DIAGRAM_0 -->\n\n```rust\nfn main() {}\n```\n"
+        );
+        assert_eq!(
+            extracted.html,
+            ["<div class=\"diagram diagram-dot\"><svg>a -> b</svg></div>"]
+        );
+
+        let html = markdown::render_markdown(
+            &extracted.content,
+            &MarkdownConfig::default(),
+            &mut Vec::new(),
+            "",
+        );
+        let html = diagrams.insert(&html, &extracted.html, "page.md");
+        assert!(html.contains(
+            "<li>\n<p>item</p>\n<div class=\"diagram diagram-dot\"><svg>a -> b</svg></div>"
+        ));
+        assert!(!html.contains("DIAGRAM_"));
+    }
+
+    #[test]
+    fn test_diagrams_are_cached_by_source() {
+        let tmp = TempDir::new().unwrap();
+        let diagrams = renderer(&tmp, ECHO_TOOL);
+        let config = MarkdownConfig::default();
+        diagrams.extract_blocks("```dot\na\n```\n", &config, "a.md");
+        diagrams.extract_blocks("```dot\na\n```\n", &config, "b.md");
+        let runs = || std::fs::read_to_string(tmp.path().join("runs")).unwrap();
+        assert_eq!(runs().lines().count(), 1);
+
+        diagrams.extract_blocks("```dot\nb\n```\n", &config, "a.md");
+        assert_eq!(runs().lines().count(), 2);
+    }
+
+    #[test]
+    fn test_mermaid_shortcode_output() {
+        let tmp = TempDir::new().unwrap();
+        let diagrams = renderer(&tmp, ECHO_TOOL);
+        let html = "<p>x</p>\n<pre class=\"mermaid\">A --&gt; B</pre>\n";
+        assert_eq!(
+            diagrams.insert(html, &[], "page.md"),
+            "<p>x</p>\n<div class=\"diagram diagram-mermaid\"><svg>A --> B</svg></div>\n"
+        );
+    }
+
+    #[test]
+    fn test_root_ids_are_unique_per_diagram() {
+        let tmp = TempDir::new().unwrap();
+        let tool = r##"mermaid = ["sh", "-c", "printf '<svg id=\"my-svg\" width=\"10\"><style>#my-svg{fill:red}</style><marker id=\"my-svg_end\"/><path marker-end=\"url(#my-svg_end)\" data-src=\"%s\"/></svg>' \"$(cat)\""]"##;
+        let diagrams = renderer(&tmp, tool);
+        let html = "<pre class=\"mermaid\">A</pre>\n<pre class=\"mermaid\">B</pre>";
+        let out = diagrams.insert(html, &[], "page.md");
+        let (a, b) = out.split_once('\n').unwrap();
+        assert!(!out.contains("my-svg"), "{out}");
+        let id = |svg: &str| {
+            ROOT_ID_RE
+                .captures(&svg[svg.find("<svg").unwrap()..])
+                .unwrap()[1]
+                .to_string()
+        };
+        let (id_a, id_b) = (id(a), id(b));
+        assert!(id_a.starts_with("diagram-"));
+        assert_ne!(id_a, id_b);
+        assert!(a.contains(&format!("#{id_a}{{fill:red}}")));
+        assert!(a.contains(&format!("id=\"{id_a}_end\"")));
+        assert!(a.contains(&format!("url(#{id_a}_end)")));
+
+        assert_eq!(
+            unique_root_id("<svg><g id=\"x\"/></svg>", &"0".repeat(64)),
+            "<svg><g id=\"x\"/></svg>"
+        );
+    }
+
+    #[test]
+    fn test_missing_or_failing_tool_keeps_source() {
+        let tmp = TempDir::new().unwrap();
+        let diagrams = renderer(
+            &tmp,
+            "dot = [\"zorto-no-such-program\"]\nd2 = [\"sh\", \"-c\", \"echo bad >&2; exit 1\"]\n",
+        );
+        let content = "```dot\na -> b\n```\n\n```d2\nx -> y\n```\n";
+        let extracted = diagrams.extract_blocks(content, &MarkdownConfig::default(), "page.md");
+        assert_eq!(extracted.content, content);
+        assert!(extracted.html.is_empty());
+
+        let html = "<pre class=\"mermaid\">A</pre>";
+        let diagrams = renderer(&tmp, "mermaid = [\"sh\", \"-c\", \"echo not svg\"]\n");
+        assert_eq!(diagrams.insert(html, &[], "page.md"), html);
+    }
+}
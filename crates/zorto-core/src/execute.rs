@@ -1,16 +1,119 @@
-use std::io::Read;
+use std::collections::HashMap;
+use std::io::{Read, Write};
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Condvar, Mutex};
 #[cfg(feature = "python")]
-use std::sync::Once;
+use std::sync::{MutexGuard, Once};
 use std::time::{Duration, Instant};
 
+use crate::config::{BlockOptions, ExecuteConfig, LanguageRunner, PythonMode, RunnerInput};
+use crate::python_worker::PythonWorker;
+use crate::sandbox::{self, Sandbox};
+
+/// Serializes Python execution across build threads.
+///
+/// Pages render in parallel, but embedded Python blocks share one interpreter:
+/// `sys.stdout` capture, the working directory and imported modules are
+/// process-global. A page holds this lock while running all of its Python
+/// blocks, so blocks from different pages never interleave.
+#[cfg(feature = "python")]
+static PYTHON_LOCK: Mutex<()> = Mutex::new(());
+
+/// Frontmatter `[extra]` key naming a Python namespace shared between pages.
+pub const SHARED_NAMESPACE_KEY: &str = "python_namespace";
+
+/// The shared Python namespace a page opts into, if any.
+pub fn shared_namespace(extra: &serde_json::Value) -> Option<&str> {
+    extra
+        .get(SHARED_NAMESPACE_KEY)
+        .and_then(|v| v.as_str())
+        .filter(|name| !name.is_empty())
+}
+
+/// Python namespaces shared between pages via `[extra] python_namespace`.
+///
+/// Every other page gets a fresh namespace. A site owns one of these and
+/// clears it at the start of each full build, so a build never sees state
+/// left over from the previous one.
+#[derive(Default)]
+pub struct SharedNamespaces {
+    /// Globals dicts for `python_mode = "embedded"`.
+    #[cfg(feature = "python")]
+    embedded: Mutex<HashMap<String, pyo3::Py<pyo3::types::PyDict>>>,
+    /// Idle workers for `python_mode = "subprocess"`. A session takes its
+    /// namespace's worker and puts it back when dropped.
+    workers: Mutex<HashMap<String, PythonWorker>>,
+}
+
+impl SharedNamespaces {
+    /// Drop every shared namespace.
+    pub fn clear(&self) {
+        #[cfg(feature = "python")]
+        self.embedded
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+        self.workers
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+/// Limits how many code blocks run at once across a build
+/// (`[execute] jobs`).
+///
+/// Sessions sharing one take a slot for each bash/sh/node block and each
+/// Python block in a worker process, and hold it while the block runs.
+pub struct JobSlots {
+    available: Mutex<usize>,
+    freed: Condvar,
+}
+
+impl JobSlots {
+    /// Slots for at most `limit` concurrent blocks (at least one).
+    pub fn new(limit: usize) -> Self {
+        Self {
+            available: Mutex::new(limit.max(1)),
+            freed: Condvar::new(),
+        }
+    }
+
+    /// Wait for a free slot, held until the returned guard is dropped.
+    fn acquire(&self) -> JobSlot<'_> {
+        let mut available = self.available.lock().unwrap_or_else(|e| e.into_inner());
+        while *available == 0 {
+            available = self
+                .freed
+                .wait(available)
+                .unwrap_or_else(|e| e.into_inner());
+        }
+        *available -= 1;
+        JobSlot(self)
+    }
+}
+
+/// A slot taken from [`JobSlots`], returned on drop.
+struct JobSlot<'a>(&'a JobSlots);
+
+impl Drop for JobSlot<'_> {
+    fn drop(&mut self) {
+        *self.0.available.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+        self.0.freed.notify_one();
+    }
+}
+
 /// A single visualization captured from a Python code block.
 #[derive(Debug, Clone)]
 pub struct VizOutput {
-    /// `"img"` for base64 data-URI images, `"html"` for inline HTML (plotly/altair).
+    /// `"img"` for base64 data-URI images, `"html"` for inline HTML
+    /// (plotly/altair/bokeh, tables, SVG), `"markdown"` for markdown.
     pub kind: String,
-    /// The data-URI string (for img) or raw HTML fragment (for html).
+    /// The data-URI string (for img), raw HTML fragment (for html) or
+    /// markdown source.
     pub data: String,
 }
 
@@ -20,42 +123,121 @@ pub struct ExecutableBlock {
     pub language: String,
     pub source: String,
     pub file_ref: Option<String>,
+    /// Files the block reads, as globs relative to the site root
+    /// (`deps="data/*.csv"`). Their contents are part of its cache key.
+    pub deps: Vec<String>,
+    /// Display options from the fence (`echo=false`, `output="asis"`, ...).
+    pub options: BlockOptions,
     pub output: Option<String>,
     pub error: Option<String>,
     pub viz: Vec<VizOutput>,
+    /// Values the block passed to templates, via `zorto.set()` in Python or
+    /// the `$ZORTO_DATA` file in other languages (`page.exec_data`).
+    pub data: serde_json::Map<String, serde_json::Value>,
 }
 
-/// Execute all pending code blocks for a page.
+/// Executes one page's code blocks, keeping per-page state between calls.
 ///
-/// `timeout_seconds` bounds the wall-clock runtime of each individual
-/// bash/sh/node block; a value of 0 disables the timeout. Python blocks
-/// run under the embedded PyO3 interpreter and are NOT subject to this
-/// timeout — see `crate::config::ExecuteConfig::timeout_seconds`.
+/// `config.timeout_seconds` bounds the wall-clock runtime of each individual
+/// bash/sh/node block; a value of 0 disables the timeout. With
+/// `config.sandbox.enable`, those blocks also run isolated (see
+/// [`crate::sandbox`]). Python blocks run under the embedded PyO3 interpreter
+/// by default and are subject to neither, so they are refused when the
+/// sandbox is enabled; with `python_mode = "subprocess"` they run in a
+/// worker process that is sandboxed and killed when a block times out.
 ///
-/// Each block's `output` and `error` fields are populated with the execution
-/// results. Errors in individual blocks are stored in `block.error` (they are
-/// rendered inline as `<div class="code-error">`) and also surfaced via the
-/// return value so the caller can decide whether to fail the build.
-pub fn execute_blocks(
-    blocks: &mut [ExecutableBlock],
-    working_dir: &Path,
-    site_root: &Path,
-    timeout_seconds: u64,
-) -> Vec<String> {
-    let mut errors = Vec::new();
-
-    for block in blocks.iter_mut() {
-        match block.language.as_str() {
-            "python" => {
-                #[cfg(feature = "python")]
-                {
-                    match execute_python(block, working_dir, site_root) {
-                        Ok((stdout, stderr, viz)) => {
-                            block.output = Some(stdout);
-                            if !stderr.is_empty() {
-                                block.error = Some(stderr);
+/// Python blocks on a page share a namespace that starts out empty, unless
+/// the page joins a named one with [`ExecSession::shared_namespace`].
+///
+/// Safe to use from several threads at once (one session per page): embedded
+/// Python is serialized via `PYTHON_LOCK`, held from the session's first
+/// Python block until it is dropped; other languages run concurrently.
+pub struct ExecSession<'a> {
+    working_dir: &'a Path,
+    site_root: &'a Path,
+    config: &'a ExecuteConfig,
+    /// Named namespace this session joins, and where it is kept.
+    shared: Option<(&'a str, &'a SharedNamespaces)>,
+    /// Build-wide limit on concurrently running blocks.
+    jobs: Option<&'a JobSlots>,
+    #[cfg(feature = "python")]
+    python_guard: Option<MutexGuard<'static, ()>>,
+    /// The page's globals for embedded Python, created on first use.
+    #[cfg(feature = "python")]
+    python_globals: Option<pyo3::Py<pyo3::types::PyDict>>,
+    /// The page's Python worker (`python_mode = "subprocess"`), started on
+    /// first use.
+    python_worker: Option<PythonWorker>,
+    /// Why the worker is gone, once it has been killed or has crashed. Later
+    /// blocks fail with this rather than silently running without the state
+    /// earlier blocks set up.
+    python_worker_failed: Option<String>,
+}
+
+impl<'a> ExecSession<'a> {
+    pub fn new(working_dir: &'a Path, site_root: &'a Path, config: &'a ExecuteConfig) -> Self {
+        Self {
+            working_dir,
+            site_root,
+            config,
+            shared: None,
+            jobs: None,
+            #[cfg(feature = "python")]
+            python_guard: None,
+            #[cfg(feature = "python")]
+            python_globals: None,
+            python_worker: None,
+            python_worker_failed: None,
+        }
+    }
+
+    /// Run Python blocks in the named namespace kept in `namespaces`, shared
+    /// with every other session that joins it, instead of a fresh one.
+    ///
+    /// Sessions sharing a namespace must run one after another, in a fixed
+    /// order, for their output to be reproducible.
+    pub fn shared_namespace(mut self, name: &'a str, namespaces: &'a SharedNamespaces) -> Self {
+        self.shared = Some((name, namespaces));
+        self
+    }
+
+    /// Take a slot from `jobs` for every block that runs in a separate
+    /// process, so sessions on other threads share its limit.
+    pub fn jobs(mut self, jobs: &'a JobSlots) -> Self {
+        self.jobs = Some(jobs);
+        self
+    }
+
+    fn job_slot(&self) -> Option<JobSlot<'a>> {
+        self.jobs.map(JobSlots::acquire)
+    }
+
+    /// Execute `blocks` in order.
+    ///
+    /// Each block's `output` and `error` fields are populated with the
+    /// execution results. Errors in individual blocks are stored in
+    /// `block.error` (they are rendered inline as `<div class="code-error">`)
+    /// and also surfaced via the return value so the caller can decide
+    /// whether to fail the build.
+    pub fn run(&mut self, blocks: &mut [ExecutableBlock]) -> Vec<String> {
+        let mut errors = Vec::new();
+
+        for block in blocks.iter_mut() {
+            match block.language.as_str() {
+                "python" => {
+                    let result = match self.config.python_mode {
+                        PythonMode::Embedded => self.execute_python_embedded(block),
+                        PythonMode::Subprocess => self.execute_python_subprocess(block),
+                    };
+                    match result {
+                        Ok(out) => {
+                            block.output = Some(out.stdout);
+                            if !out.stderr.is_empty() {
+                                block.error = Some(out.stderr);
                             }
-                            block.viz = viz;
+                            block.viz = out.viz;
+                            block.data = out.data;
+                            errors.extend(out.exception.map(|e| format!("Python error: {e}")));
                         }
                         Err(e) => {
                             let msg = format!("Python execution error: {e}");
@@ -64,67 +246,251 @@ pub fn execute_blocks(
                         }
                     }
                 }
-                #[cfg(not(feature = "python"))]
-                {
-                    let _ = site_root;
-                    let msg =
-                        "Python execution not available (built without python feature)".to_string();
-                    block.error = Some(msg.clone());
-                    errors.push(msg);
+                "node" | "javascript" | "js" => {
+                    let _slot = self.job_slot();
+                    let result = execute_node(block, self.working_dir, self.config);
+                    record_process(block, result, "Node.js", &mut errors);
                 }
-            }
-            "node" | "javascript" | "js" => {
-                match execute_node(block, working_dir, timeout_seconds) {
-                    Ok((stdout, stderr)) => {
-                        block.output = Some(stdout);
-                        if !stderr.is_empty() {
-                            block.error = Some(stderr);
-                        }
+                "bash" | "sh" => {
+                    let _slot = self.job_slot();
+                    let result = execute_bash(block, self.working_dir, self.config);
+                    record_process(block, result, "Bash", &mut errors);
+                }
+                lang => match self.config.runner(lang) {
+                    Some(runner) => {
+                        let _slot = self.job_slot();
+                        let result = execute_command(block, &runner, self.working_dir, self.config);
+                        let label = lang.to_string();
+                        record_process(block, result, &label, &mut errors);
                     }
-                    Err(e) => {
-                        let msg = format!("Node.js execution error: {e}");
+                    None => {
+                        let msg = format!("Unsupported executable language: {lang}");
                         block.error = Some(msg.clone());
                         errors.push(msg);
                     }
-                }
+                },
             }
-            "bash" | "sh" => match execute_bash(block, working_dir, timeout_seconds) {
-                Ok((stdout, stderr)) => {
-                    block.output = Some(stdout);
-                    if !stderr.is_empty() {
-                        block.error = Some(stderr);
-                    }
-                }
-                Err(e) => {
-                    let msg = format!("Bash execution error: {e}");
-                    block.error = Some(msg.clone());
-                    errors.push(msg);
-                }
-            },
-            lang => {
-                let msg = format!("Unsupported executable language: {lang}");
-                block.error = Some(msg.clone());
-                errors.push(msg);
+        }
+
+        errors
+    }
+
+    /// Run a Python block in the embedded interpreter, which cannot be
+    /// sandboxed: with `[execute.sandbox]` enabled the block does not run.
+    fn execute_python_embedded(&mut self, block: &ExecutableBlock) -> PythonResult {
+        if self.config.sandbox.enable {
+            anyhow::bail!(
+                "not run: the embedded Python interpreter cannot be sandboxed (set `[execute] python_mode = \"subprocess\"` in config.toml to run Python blocks in the sandbox)"
+            );
+        }
+        #[cfg(feature = "python")]
+        {
+            // A panic in another thread only poisons the lock; the
+            // interpreter state it protects is still usable.
+            self.python_guard
+                .get_or_insert_with(|| PYTHON_LOCK.lock().unwrap_or_else(|e| e.into_inner()));
+            if self.python_globals.is_none() {
+                self.python_globals = Some(self.embedded_globals());
+            }
+            let globals = self.python_globals.as_ref().expect("globals set above");
+            execute_python(block, self.working_dir, self.site_root, globals)
+        }
+        #[cfg(not(feature = "python"))]
+        {
+            let _ = block;
+            anyhow::bail!(
+                "embedded Python not available (built without python feature); set `[execute] python_mode = \"subprocess\"` in config.toml to use an external interpreter"
+            )
+        }
+    }
+
+    /// The globals dict for this session: the named shared one, or a fresh
+    /// `__main__`-like dict.
+    #[cfg(feature = "python")]
+    fn embedded_globals(&self) -> pyo3::Py<pyo3::types::PyDict> {
+        pyo3::Python::attach(|py| {
+            if let Some((name, namespaces)) = self.shared {
+                let mut embedded = namespaces
+                    .embedded
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner());
+                embedded
+                    .entry(name.to_string())
+                    .or_insert_with(|| fresh_globals(py))
+                    .clone_ref(py)
+            } else {
+                fresh_globals(py)
+            }
+        })
+    }
+
+    /// Run a Python block in this page's worker process.
+    fn execute_python_subprocess(&mut self, block: &ExecutableBlock) -> PythonResult {
+        if let Some(reason) = &self.python_worker_failed {
+            anyhow::bail!("skipped: {reason}");
+        }
+        let code = if let Some(ref file) = block.file_ref {
+            std::fs::read_to_string(self.working_dir.join(file))?
+        } else {
+            block.source.clone()
+        };
+        let worker = match &mut self.python_worker {
+            Some(worker) => worker,
+            None => {
+                let idle = self.shared.and_then(|(name, namespaces)| {
+                    namespaces
+                        .workers
+                        .lock()
+                        .unwrap_or_else(|e| e.into_inner())
+                        .remove(name)
+                });
+                let worker = match idle {
+                    Some(worker) => worker,
+                    None => PythonWorker::spawn(self.config, self.site_root)?,
+                };
+                self.python_worker.insert(worker)
+            }
+        };
+        let _slot = self.jobs.map(JobSlots::acquire);
+        match worker.run(&code, self.working_dir, self.config.timeout_seconds) {
+            Ok(out) => Ok(out),
+            Err(e) => {
+                // The worker is killed on timeout and unusable after a crash.
+                self.python_worker = None;
+                self.python_worker_failed = Some(format!(
+                    "the page's Python worker stopped after an earlier block ({e})"
+                ));
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for ExecSession<'_> {
+    fn drop(&mut self) {
+        // Hand a shared namespace's worker back for the next page to use.
+        if let (Some((name, namespaces)), Some(worker)) = (self.shared, self.python_worker.take()) {
+            namespaces
+                .workers
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .insert(name.to_string(), worker);
+        }
+    }
+}
+
+/// A new, empty globals dict that behaves like a fresh `__main__`.
+#[cfg(feature = "python")]
+fn fresh_globals(py: pyo3::Python<'_>) -> pyo3::Py<pyo3::types::PyDict> {
+    use pyo3::prelude::*;
+    use pyo3::types::PyDict;
+
+    let globals = PyDict::new(py);
+    // Neither call can fail on a new dict with string keys.
+    let _ = globals.set_item("__name__", "__main__");
+    if let Ok(builtins) = py.import("builtins") {
+        let _ = globals.set_item("__builtins__", builtins);
+    }
+    globals.unbind()
+}
+
+/// Store the result of a block run as a separate process in `block`.
+///
+/// Failures, including non-zero exits, are pushed to `errors` as
+/// "`label` ...". The block's error is its stderr, or the failure when
+/// there is none.
+fn record_process(
+    block: &mut ExecutableBlock,
+    result: anyhow::Result<ProcessOutput>,
+    label: &str,
+    errors: &mut Vec<String>,
+) {
+    match result {
+        Ok(out) => {
+            block.output = Some(out.stdout);
+            block.data = out.data;
+            let failure =
+                (!out.status.success()).then(|| format!("{label} block failed ({})", out.status));
+            if !out.stderr.is_empty() {
+                block.error = Some(out.stderr);
+            } else {
+                block.error.clone_from(&failure);
             }
+            errors.extend(failure);
+        }
+        Err(e) => {
+            let msg = format!("{label} execution error: {e}");
+            block.error = Some(msg.clone());
+            errors.push(msg);
         }
     }
+}
 
-    errors
+/// What a block run as a separate process printed, and how it exited.
+pub(crate) struct ProcessOutput {
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
+    pub(crate) status: ExitStatus,
+    /// Objects the block wrote to `$ZORTO_DATA`, merged.
+    data: serde_json::Map<String, serde_json::Value>,
+}
+
+/// What a Python block printed and displayed, and whether it raised.
+pub(crate) struct PythonOutput {
+    pub stdout: String,
+    /// Captured stderr, followed by the exception if there was one.
+    pub stderr: String,
+    pub viz: Vec<VizOutput>,
+    /// The exception the block raised, e.g. `NameError: name 'x' is not defined`.
+    pub exception: Option<String>,
+    /// Values passed to `zorto.set()`.
+    pub data: serde_json::Map<String, serde_json::Value>,
 }
 
+type PythonResult = anyhow::Result<PythonOutput>;
+
 /// Run a `Command` with stdout/stderr captured and an optional wall-clock
 /// timeout. `timeout_seconds == 0` means no timeout (legacy `Command::output`
 /// behaviour).
 ///
+/// `input`, if any, is written to the command's standard input.
+///
+/// With a `sandbox`, already applied to `cmd` (see [`crate::sandbox`]), the
+/// command is killed once its combined output exceeds the sandbox's output
+/// limit. Exits caused by a sandbox limit are reported as errors.
+///
 /// stdout and stderr are drained on background threads while the parent polls
 /// for completion. Without that the child can block writing to a full pipe
 /// while we sit in the wait loop, producing a spurious "timeout" for a
 /// program that was actually trying to finish quickly.
-fn run_with_timeout(mut cmd: Command, timeout_seconds: u64) -> anyhow::Result<(String, String)> {
+pub(crate) fn run_with_timeout(
+    mut cmd: Command,
+    timeout_seconds: u64,
+    sandbox: Option<&Sandbox>,
+    input: Option<&str>,
+) -> anyhow::Result<ProcessOutput> {
     cmd.stdout(Stdio::piped())
         .stderr(Stdio::piped())
-        .stdin(Stdio::null());
-    let mut child = cmd.spawn()?;
+        .stdin(if input.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        });
+    let mut child = match cmd.spawn() {
+        Ok(child) => child,
+        Err(e) => match sandbox {
+            Some(sandbox) => return Err(sandbox.spawn_error(e)),
+            None => return Err(e.into()),
+        },
+    };
+
+    // Written from a thread so a child that fills its output pipes before
+    // reading all of its input cannot deadlock us. A child that exits
+    // without reading it all makes the write fail, which is fine.
+    if let (Some(input), Some(mut stdin)) = (input, child.stdin.take()) {
+        let input = input.to_string();
+        std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+    }
 
     let stdout_pipe = child
         .stdout
@@ -135,36 +501,41 @@ fn run_with_timeout(mut cmd: Command, timeout_seconds: u64) -> anyhow::Result<(S
         .take()
         .ok_or_else(|| anyhow::anyhow!("failed to capture child stderr"))?;
 
-    let stdout_thread = std::thread::spawn(move || -> std::io::Result<String> {
-        let mut buf = String::new();
-        let mut pipe = stdout_pipe;
-        pipe.read_to_string(&mut buf)?;
-        Ok(buf)
-    });
-    let stderr_thread = std::thread::spawn(move || -> std::io::Result<String> {
-        let mut buf = String::new();
-        let mut pipe = stderr_pipe;
-        pipe.read_to_string(&mut buf)?;
-        Ok(buf)
-    });
+    let output_limit = sandbox.and_then(Sandbox::output_limit);
+    let output_bytes = Arc::new(AtomicU64::new(0));
+    let stdout_thread = {
+        let output_bytes = Arc::clone(&output_bytes);
+        std::thread::spawn(move || read_limited(stdout_pipe, &output_bytes, output_limit))
+    };
+    let stderr_thread = {
+        let output_bytes = Arc::clone(&output_bytes);
+        std::thread::spawn(move || read_limited(stderr_pipe, &output_bytes, output_limit))
+    };
+    let over_limit = || output_limit.is_some_and(|max| output_bytes.load(Ordering::Relaxed) > max);
 
-    let timed_out = if timeout_seconds == 0 {
-        let _status = child.wait()?;
-        false
+    let mut timed_out = false;
+    let mut output_exceeded = false;
+    // Reaped with `sandbox::wait` for the CPU time `limit_error` needs.
+    let reap = |child: &mut Child| {
+        sandbox::wait(child, true)?
+            .ok_or_else(|| anyhow::anyhow!("failed to wait for child process"))
+    };
+    let exit = if timeout_seconds == 0 && output_limit.is_none() {
+        reap(&mut child)?
     } else {
-        let deadline = Instant::now() + Duration::from_secs(timeout_seconds);
+        let deadline =
+            (timeout_seconds > 0).then(|| Instant::now() + Duration::from_secs(timeout_seconds));
         loop {
-            match child.try_wait()? {
-                Some(_status) => break false,
-                None => {
-                    if Instant::now() >= deadline {
-                        let _ = child.kill();
-                        let _ = child.wait();
-                        break true;
-                    }
-                    std::thread::sleep(Duration::from_millis(50));
-                }
+            if let Some(exit) = sandbox::wait(&mut child, false)? {
+                break exit;
             }
+            timed_out = deadline.is_some_and(|d| Instant::now() >= d);
+            output_exceeded = over_limit();
+            if timed_out || output_exceeded {
+                let _ = child.kill();
+                break reap(&mut child)?;
+            }
+            std::thread::sleep(Duration::from_millis(50));
         }
     };
 
@@ -176,12 +547,54 @@ fn run_with_timeout(mut cmd: Command, timeout_seconds: u64) -> anyhow::Result<(S
             "execution exceeded the {timeout_seconds}-second timeout (configurable via `[execute] timeout_seconds` in config.toml; set to 0 to disable)"
         );
     }
+    if let Some(sandbox) = sandbox {
+        // The child may exit on its own (e.g. SIGPIPE) before the poll above
+        // notices that the readers stopped at the limit.
+        if let Some(max) = output_limit
+            && (output_exceeded || over_limit())
+        {
+            anyhow::bail!(
+                "output exceeded {max} bytes (configurable via `[execute.sandbox] max_output_bytes` in config.toml)"
+            );
+        }
+        if let Some(msg) = sandbox.limit_error(exit, &stderr) {
+            anyhow::bail!("{msg}");
+        }
+    }
 
-    Ok((stdout, stderr))
+    Ok(ProcessOutput {
+        stdout,
+        stderr,
+        status: exit.status,
+        data: serde_json::Map::new(),
+    })
+}
+
+/// Read a pipe to the end, or until the combined byte count shared with the
+/// other pipe passes `limit`. Dropping the pipe early makes further writes by
+/// the child fail with `EPIPE`.
+fn read_limited(
+    mut pipe: impl Read,
+    total: &AtomicU64,
+    limit: Option<u64>,
+) -> std::io::Result<String> {
+    let mut buf = Vec::new();
+    let mut chunk = [0u8; 8192];
+    loop {
+        let n = pipe.read(&mut chunk)?;
+        if n == 0 {
+            break;
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        let seen = total.fetch_add(n as u64, Ordering::Relaxed) + n as u64;
+        if limit.is_some_and(|max| seen > max) {
+            break;
+        }
+    }
+    String::from_utf8(buf).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
 }
 
 /// Find a .venv directory: check site root, walk up parents, then fall back to VIRTUAL_ENV env var
-#[cfg(feature = "python")]
 pub(crate) fn find_venv(site_root: &Path) -> Option<std::path::PathBuf> {
     // Walk up from site root looking for .venv
     let mut dir = Some(site_root);
@@ -238,25 +651,94 @@ pub(crate) fn activate_venv(py: pyo3::Python<'_>, site_root: &Path) -> pyo3::PyR
     Ok(())
 }
 
-/// Execute a Python code block using the embedded PyO3 interpreter.
+/// Python code run before each block to set up its namespace.
 ///
-/// # Thread safety
+/// Defines `zorto`: `zorto.set(key, value)` stores a value for templates;
+/// after the block, `__zorto_internal_module__._take()` returns the stored
+/// values as a JSON object and forgets them. Values must be
+/// JSON-serializable, or have a `tolist()` or `to_dict()` method (NumPy and
+/// pandas objects). A user variable named `zorto` is left alone.
 ///
-/// This function calls `os.chdir()` to set the working directory for the
-/// executed code. `chdir` is process-global state, so this is not safe to call
-/// from multiple threads concurrently. Page rendering is currently sequential,
-/// so this is fine — but must be revisited if parallel rendering is added.
+/// So that `import zorto` works, in blocks and in modules they import, the
+/// process also gets a `zorto._exec` module whose `set` stores into the
+/// running block's namespace. It is registered as `zorto` too unless the
+/// zorto Python package is installed, whose `zorto.set` forwards to it.
+///
+/// Also defines `__zorto_internal_run__(source)`, which runs a block like a
+/// notebook cell: the value of a final expression statement, unless it ends
+/// in `;`, is kept as `__zorto_internal_result__` for [`VIZ_DETECTION_CODE`]
+/// to display.
+pub(crate) const BLOCK_PRELUDE_CODE: &str = r#"
+if '__zorto_internal_module__' not in globals():
+    def _zorto_module():
+        import json, types
+        module = types.ModuleType('zorto', 'Pass values from code blocks to templates.')
+        data = {}
+        def set(key, value):
+            """Make `value` available to templates as `page.exec_data[key]`."""
+            if not isinstance(key, str):
+                raise TypeError(f'zorto.set() key must be a str, not {type(key).__name__}')
+            data[key] = value
+        def default(obj):
+            for method in ('tolist', 'to_dict'):
+                if callable(getattr(obj, method, None)):
+                    return getattr(obj, method)()
+            raise TypeError(f'zorto.set() value of type {type(obj).__name__} is not JSON serializable')
+        def take():
+            try:
+                return json.dumps(data, default=default, allow_nan=False)
+            finally:
+                data.clear()
+        module.set = set
+        module._take = take
+        return module
+    __zorto_internal_module__ = _zorto_module()
+    del _zorto_module
+globals().setdefault('zorto', __zorto_internal_module__)
+if 'zorto._exec' not in __import__('sys').modules:
+    def _zorto_register():
+        import importlib.util, sys, types
+        module = types.ModuleType('zorto._exec', 'Pass values from code blocks to templates.')
+        def set(key, value):
+            """Make `value` available to templates as `page.exec_data[key]`."""
+            module._current.set(key, value)
+        module.set = set
+        sys.modules['zorto._exec'] = module
+        if 'zorto' not in sys.modules and importlib.util.find_spec('zorto') is None:
+            sys.modules['zorto'] = module
+    _zorto_register()
+    del _zorto_register
+__import__('sys').modules['zorto._exec']._current = __zorto_internal_module__
+
+if '__zorto_internal_run__' not in globals():
+    def __zorto_internal_run__(source):
+        import ast
+        ns = globals()
+        ns['__zorto_internal_result__'] = None
+        tree = ast.parse(source, '<block>')
+        last = None
+        if tree.body and isinstance(tree.body[-1], ast.Expr) and not source.rstrip().endswith(';'):
+            last = ast.Expression(tree.body.pop().value)
+        exec(compile(tree, '<block>', 'exec'), ns)
+        if last is not None:
+            ns['__zorto_internal_result__'] = eval(compile(last, '<block>', 'eval'), ns)
+"#;
+
 /// Python code injected after user code to detect visualization objects.
 ///
-/// `__main__` persists across all blocks/posts in one build, so we track
-/// already-rendered objects in a `WeakSet` keyed by object identity. A block
+/// A page's namespace persists across its blocks (and across pages sharing a
+/// named namespace), so we track already-rendered objects in a `WeakSet`
+/// keyed by object identity. A block
 /// that rebinds `fig = ...` creates a new object → not in the set → rendered;
 /// a block that just mutates or re-references an existing rendered object →
 /// already in the set → skipped. Weak references avoid `id()` recycling: when
 /// the original object is GC'd, its entry auto-clears, so a new object that
 /// happens to land at the same id is treated as unseen.
-#[cfg(feature = "python")]
-const VIZ_DETECTION_CODE: &str = r#"
+///
+/// The value of the block's final expression is displayed as in a notebook:
+/// charts every time, anything else through its richest `_repr_*_` method
+/// (DataFrames, great_tables, seaborn objects plots, ...).
+pub(crate) const VIZ_DETECTION_CODE: &str = r#"
 import sys as _sys, weakref as _weakref
 __zorto_internal_viz_output__ = []
 if '__zorto_internal_rendered__' not in dir():
@@ -315,7 +797,7 @@ def _zorto_mark(_obj):
 def _zorto_typed_globals(_types):
     """Yield (name, obj) for globals bound to any instance of _types."""
     for _k, _v in list(globals().items()):
-        if _k.startswith('__zorto_') or _k in ('_zorto_mark', '_zorto_typed_globals'):
+        if _k.startswith(('_zorto_', '__zorto_')):
             continue
         if isinstance(_v, _types):
             yield _k, _v
@@ -339,13 +821,19 @@ if 'matplotlib' in _sys.modules or 'matplotlib.pyplot' in _sys.modules:
     except:
         pass
 
+# Interactive chart libraries: (name, types, render to HTML). Charts bound to
+# globals are shown once each; a chart that is the block's final expression
+# is shown every time, as in a notebook.
+_zorto_chart_types = []
+
 # plotly
 if 'plotly' in _sys.modules:
     try:
         import plotly.graph_objects as _go
-        for _name, _obj in _zorto_typed_globals(_go.Figure):
-            if _zorto_mark(_obj):
-                __zorto_internal_viz_output__.append(('html', _obj.to_html(full_html=False, include_plotlyjs='cdn')))
+        _zorto_chart_types.append((
+            'plotly', (_go.Figure,),
+            lambda _obj: _obj.to_html(full_html=False, include_plotlyjs='cdn'),
+        ))
     except Exception as _e:
         print(f'zorto: warning: plotly capture failed: {_e}', file=_sys.stderr)
 
@@ -364,16 +852,15 @@ if 'altair' in _sys.modules:
             _cls = getattr(_alt, _cls_name, None)
             if _cls is not None:
                 _alt_types = _alt_types + (_cls,)
-        for _name, _obj in _zorto_typed_globals(_alt_types):
-            if not _zorto_mark(_obj):
-                continue
+
+        def _zorto_altair_html(_obj):
             _vid = 'vega-' + _uuid.uuid4().hex
             _sid = _vid + '-spec'
             _spec_json = _json.dumps(_obj.to_dict()).replace('<', '\\u003c')
             # SRI pins on the CDN bundles so a jsdelivr republish or npm
             # compromise can't serve attacker JS to readers. Bump the version
             # → regenerate the hash with `openssl dgst -sha384`.
-            _html = (
+            return (
                 '<script src="https://cdn.jsdelivr.net/npm/vega@6.1.2" '
                 'integrity="sha384-3Zeq0Gb8jqDivp2a+zwPu5uJJZV+yM0iuorzVFGDPuzChE4tO/3/TecDON0JtQEl" '
                 'crossorigin="anonymous"></script>'
@@ -390,17 +877,125 @@ if 'altair' in _sys.modules:
                 f'JSON.parse(document.getElementById("{_sid}").textContent), '
                 f'{{mode: "vega-lite"}});</script>'
             )
-            __zorto_internal_viz_output__.append(('html', _html))
+
+        _zorto_chart_types.append(('altair', _alt_types, _zorto_altair_html))
     except Exception as _e:
         print(f'zorto: warning: altair capture failed: {_e}', file=_sys.stderr)
+
+# bokeh — components() gives the plot's div and script; BokehJS comes from
+# the CDN at the installed version.
+if 'bokeh' in _sys.modules:
+    try:
+        from bokeh.embed import components as _bk_components
+        from bokeh.models import LayoutDOM as _bk_LayoutDOM
+        from bokeh.resources import CDN as _bk_CDN
+
+        def _zorto_bokeh_html(_obj):
+            _script, _div = _bk_components(_obj)
+            return _bk_CDN.render_js() + _script + _div
+
+        _zorto_chart_types.append(('bokeh', (_bk_LayoutDOM,), _zorto_bokeh_html))
+    except Exception as _e:
+        print(f'zorto: warning: bokeh capture failed: {_e}', file=_sys.stderr)
+
+_zorto_shown = set()
+for _zorto_lib, _zorto_types, _zorto_render in _zorto_chart_types:
+    try:
+        _zorto_objs = [_v for _, _v in _zorto_typed_globals(_zorto_types)]
+        if _zorto_lib == 'bokeh':
+            # Plots inside a layout are shown as part of it
+            _zorto_nested = {
+                id(_r) for _v in _zorto_objs for _r in _v.references() if _r is not _v
+            }
+            _zorto_objs = [_v for _v in _zorto_objs if id(_v) not in _zorto_nested]
+        for _zorto_obj in _zorto_objs:
+            if _zorto_mark(_zorto_obj):
+                __zorto_internal_viz_output__.append(('html', _zorto_render(_zorto_obj)))
+                _zorto_shown.add(id(_zorto_obj))
+    except Exception as _e:
+        print(f'zorto: warning: {_zorto_lib} capture failed: {_e}', file=_sys.stderr)
+
+def _zorto_rich_repr(_obj):
+    """The richest IPython-style display of _obj as (kind, data), or None.
+
+    Covers pandas/polars DataFrames, great_tables, pandas Stylers, seaborn
+    objects plots and anything else with `_repr_mimebundle_` or
+    `_repr_html_`/`_repr_svg_`/`_repr_png_`/`_repr_jpeg_`/`_repr_markdown_`.
+    """
+    import base64 as _b64
+    if isinstance(_obj, type):
+        return None
+    _bundle = {}
+    _method = getattr(_obj, '_repr_mimebundle_', None)
+    if callable(_method):
+        # Called as IPython does
+        _value = _method(include=None, exclude=None)
+        if isinstance(_value, tuple):
+            _value = _value[0]
+        if isinstance(_value, dict):
+            _bundle = _value
+    for _mime, _name in (
+        ('text/html', '_repr_html_'),
+        ('image/svg+xml', '_repr_svg_'),
+        ('image/png', '_repr_png_'),
+        ('image/jpeg', '_repr_jpeg_'),
+        ('text/markdown', '_repr_markdown_'),
+    ):
+        _value = _bundle.get(_mime)
+        _method = getattr(_obj, _name, None)
+        if _value is None and callable(_method):
+            _value = _method()
+            if isinstance(_value, tuple):
+                _value = _value[0]
+        if _value is None:
+            continue
+        if _mime == 'text/html':
+            return ('html', _value)
+        if _mime == 'text/markdown':
+            return ('markdown', _value)
+        if _mime == 'image/svg+xml':
+            if isinstance(_value, bytes):
+                _value = _value.decode()
+            # Inline SVG, without an XML declaration or doctype
+            return ('html', _value[max(_value.find('<svg'), 0):])
+        if isinstance(_value, str):
+            # Already base64, as in a MIME bundle
+            return ('img', f'data:{_mime};base64,' + ''.join(_value.split()))
+        return ('img', f'data:{_mime};base64,' + _b64.b64encode(_value).decode())
+    return None
+
+# The value of the block's final expression, as in a notebook
+_zorto_result = globals().get('__zorto_internal_result__')
+if _zorto_result is not None and id(_zorto_result) not in _zorto_shown:
+    try:
+        for _zorto_lib, _zorto_types, _zorto_render in _zorto_chart_types:
+            if isinstance(_zorto_result, _zorto_types):
+                _zorto_mark(_zorto_result)
+                __zorto_internal_viz_output__.append(('html', _zorto_render(_zorto_result)))
+                break
+        else:
+            _zorto_display = _zorto_rich_repr(_zorto_result)
+            if _zorto_display is not None:
+                __zorto_internal_viz_output__.append(_zorto_display)
+    except Exception as _e:
+        print(f'zorto: warning: display of {type(_zorto_result).__name__} failed: {_e}', file=_sys.stderr)
+__zorto_internal_result__ = _zorto_result = None
 "#;
 
+/// Execute a Python code block in `globals` using the embedded PyO3
+/// interpreter.
+///
+/// # Thread safety
+///
+/// This function calls `os.chdir()` and swaps `sys.stdout`, both
+/// process-global, so callers must hold `PYTHON_LOCK`.
 #[cfg(feature = "python")]
 fn execute_python(
     block: &ExecutableBlock,
     working_dir: &Path,
     site_root: &Path,
-) -> anyhow::Result<(String, String, Vec<VizOutput>)> {
+    globals: &pyo3::Py<pyo3::types::PyDict>,
+) -> PythonResult {
     use pyo3::prelude::*;
     use std::ffi::CString;
 
@@ -410,114 +1005,183 @@ fn execute_python(
         block.source.clone()
     };
 
-    let code_cstr = CString::new(code.as_bytes())?;
     let site_root = site_root.to_path_buf();
 
-    let result = Python::attach(
-        |py: Python<'_>| -> PyResult<(String, String, Vec<VizOutput>)> {
-            // Activate venv if present (once per process)
-            activate_venv(py, &site_root)?;
-
-            // Set up stdout/stderr capture
-            let sys = py.import("sys")?;
-            let io = py.import("io")?;
-            let stdout_capture = io.call_method0("StringIO")?;
-            let stderr_capture = io.call_method0("StringIO")?;
-
-            let old_stdout = sys.getattr("stdout")?;
-            let old_stderr = sys.getattr("stderr")?;
-
-            sys.setattr("stdout", &stdout_capture)?;
-            sys.setattr("stderr", &stderr_capture)?;
-
-            // Set working directory
-            let os = py.import("os")?;
-            os.call_method1("chdir", (working_dir.to_string_lossy().as_ref(),))?;
-
-            // Execute user code
-            let exec_result = py.run(code_cstr.as_c_str(), None, None);
-
-            // Detect visualizations (only if user code succeeded)
-            let mut viz = Vec::new();
-            if exec_result.is_ok() {
-                let viz_code = CString::new(VIZ_DETECTION_CODE)?;
-                if let Err(e) = py.run(viz_code.as_c_str(), None, None) {
-                    eprintln!("zorto: viz detection error: {e}");
-                } else {
-                    // Read __zorto_internal_viz_output__ from __main__ namespace
-                    if let Ok(main_mod) = py.import("__main__") {
-                        if let Ok(viz_list) = main_mod.getattr("__zorto_internal_viz_output__") {
-                            if let Ok(items) = viz_list.extract::<Vec<(String, String)>>() {
-                                for (kind, data) in items {
-                                    viz.push(VizOutput { kind, data });
-                                }
-                            }
-                        }
-                    }
+    let result = Python::attach(|py: Python<'_>| -> PyResult<PythonOutput> {
+        // Activate venv if present (once per process)
+        activate_venv(py, &site_root)?;
+
+        // Set up stdout/stderr capture
+        let sys = py.import("sys")?;
+        let io = py.import("io")?;
+        let stdout_capture = io.call_method0("StringIO")?;
+        let stderr_capture = io.call_method0("StringIO")?;
+
+        let old_stdout = sys.getattr("stdout")?;
+        let old_stderr = sys.getattr("stderr")?;
+
+        sys.setattr("stdout", &stdout_capture)?;
+        sys.setattr("stderr", &stderr_capture)?;
+
+        // Set working directory
+        let os = py.import("os")?;
+        os.call_method1("chdir", (working_dir.to_string_lossy().as_ref(),))?;
+
+        // Execute user code in the page's namespace
+        let globals = globals.bind(py);
+        py.run(&CString::new(BLOCK_PRELUDE_CODE)?, Some(globals), None)?;
+        let mut exec_result = globals
+            .get_item("__zorto_internal_run__")?
+            .ok_or_else(|| pyo3::exceptions::PyRuntimeError::new_err("block prelude not run"))?
+            .call1((code.as_str(),))
+            .map(drop);
+
+        // Collect values passed to zorto.set(), even from a failed block
+        let mut data = serde_json::Map::new();
+        match py
+            .eval(c"__zorto_internal_module__._take()", Some(globals), None)
+            .and_then(|json| json.extract::<String>())
+        {
+            // json.dumps() of a dict always yields an object
+            Ok(json) => data = serde_json::from_str(&json).unwrap_or_default(),
+            Err(e) => exec_result = exec_result.and(Err(e)),
+        }
+
+        // Detect visualizations (only if user code succeeded)
+        let mut viz = Vec::new();
+        if exec_result.is_ok() {
+            let viz_code = CString::new(VIZ_DETECTION_CODE)?;
+            if let Err(e) = py.run(viz_code.as_c_str(), Some(globals), None) {
+                eprintln!("zorto: viz detection error: {e}");
+            } else if let Ok(Some(viz_list)) = globals.get_item("__zorto_internal_viz_output__")
+                && let Ok(items) = viz_list.extract::<Vec<(String, String)>>()
+            {
+                for (kind, data) in items {
+                    viz.push(VizOutput { kind, data });
                 }
             }
+        }
 
-            // Restore stdout/stderr
-            sys.setattr("stdout", &old_stdout)?;
-            sys.setattr("stderr", &old_stderr)?;
+        // Restore stdout/stderr
+        sys.setattr("stdout", &old_stdout)?;
+        sys.setattr("stderr", &old_stderr)?;
 
-            // Get captured output
-            let stdout: String = stdout_capture.call_method0("getvalue")?.extract()?;
-            let stderr: String = stderr_capture.call_method0("getvalue")?.extract()?;
+        // Get captured output
+        let stdout: String = stdout_capture.call_method0("getvalue")?.extract()?;
+        let stderr: String = stderr_capture.call_method0("getvalue")?.extract()?;
 
-            if let Err(e) = exec_result {
-                let err_msg = format!("{stderr}\n{e}");
-                Ok((stdout, err_msg.trim().to_string(), viz))
-            } else {
-                Ok((stdout, stderr, viz))
-            }
-        },
-    )?;
+        Ok(match exec_result {
+            Err(e) => PythonOutput {
+                stdout,
+                stderr: format!("{stderr}\n{e}").trim().to_string(),
+                viz,
+                exception: Some(e.to_string()),
+                data,
+            },
+            Ok(_) => PythonOutput {
+                stdout,
+                stderr,
+                viz,
+                exception: None,
+                data,
+            },
+        })
+    })?;
 
     Ok(result)
 }
 
-/// Execute a bash code block.
-fn execute_bash(
-    block: &ExecutableBlock,
+/// Run a subprocess block command, sandboxed when `[execute.sandbox]` is enabled.
+///
+/// The command finds the path of an empty file in `$ZORTO_DATA`; the JSON
+/// objects it writes there become the block's `data`.
+fn run_subprocess(
+    mut cmd: Command,
     working_dir: &Path,
-    timeout_seconds: u64,
-) -> anyhow::Result<(String, String)> {
-    let code = if let Some(ref file) = block.file_ref {
-        std::fs::read_to_string(working_dir.join(file))?
+    config: &ExecuteConfig,
+    input: Option<&str>,
+) -> anyhow::Result<ProcessOutput> {
+    let sandbox = if config.sandbox.enable {
+        Some(Sandbox::new(&config.sandbox)?)
     } else {
-        block.source.clone()
+        None
     };
+    match &sandbox {
+        Some(sandbox) => sandbox.apply(&mut cmd),
+        None => {
+            cmd.current_dir(working_dir);
+        }
+    }
+    // Set after the sandbox scrubs the environment
+    let data_file = tempfile::Builder::new()
+        .prefix("zorto-data-")
+        .suffix(".json")
+        .tempfile()?;
+    cmd.env("ZORTO_DATA", data_file.path());
+
+    let mut out = run_with_timeout(cmd, config.timeout_seconds, sandbox.as_ref(), input)?;
+    out.data = read_data_file(data_file.path())?;
+    Ok(out)
+}
+
+/// Merge the JSON objects written to a block's `$ZORTO_DATA` file, in order.
+fn read_data_file(path: &Path) -> anyhow::Result<serde_json::Map<String, serde_json::Value>> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read $ZORTO_DATA: {e}"))?;
+    let mut data = serde_json::Map::new();
+    for value in serde_json::Deserializer::from_str(&contents).into_iter() {
+        match value {
+            Ok(serde_json::Value::Object(object)) => data.extend(object),
+            Ok(other) => anyhow::bail!("$ZORTO_DATA must contain JSON objects, found `{other}`"),
+            Err(e) => anyhow::bail!("invalid JSON in $ZORTO_DATA: {e}"),
+        }
+    }
+    Ok(data)
+}
+
+/// A block's code: the contents of its `file=` reference, else its body.
+fn block_code(block: &ExecutableBlock, working_dir: &Path) -> std::io::Result<String> {
+    match &block.file_ref {
+        Some(file) => std::fs::read_to_string(working_dir.join(file)),
+        None => Ok(block.source.clone()),
+    }
+}
+
+/// Whether a block command failed because its program could not be found.
+fn is_not_found(e: &anyhow::Error) -> bool {
+    let msg = e.to_string();
+    msg.contains("No such file or directory")
+        || msg.contains("program not found")
+        || msg.contains("cannot find the file")
+}
 
+/// Execute a bash code block.
+fn execute_bash(
+    block: &ExecutableBlock,
+    working_dir: &Path,
+    config: &ExecuteConfig,
+) -> anyhow::Result<ProcessOutput> {
+    let code = block_code(block, working_dir)?;
     let mut cmd = Command::new("bash");
-    cmd.arg("-c").arg(&code).current_dir(working_dir);
-    run_with_timeout(cmd, timeout_seconds)
+    cmd.arg("-c").arg(&code);
+    run_subprocess(cmd, working_dir, config, None)
 }
 
 /// Execute a Node.js code block via `node -e`.
 fn execute_node(
     block: &ExecutableBlock,
     working_dir: &Path,
-    timeout_seconds: u64,
-) -> anyhow::Result<(String, String)> {
-    let code = if let Some(ref file) = block.file_ref {
-        std::fs::read_to_string(working_dir.join(file))?
-    } else {
-        block.source.clone()
-    };
-
+    config: &ExecuteConfig,
+) -> anyhow::Result<ProcessOutput> {
+    let code = block_code(block, working_dir)?;
     let mut cmd = Command::new("node");
-    cmd.arg("-e").arg(&code).current_dir(working_dir);
-    match run_with_timeout(cmd, timeout_seconds) {
+    cmd.arg("-e").arg(&code);
+    match run_subprocess(cmd, working_dir, config, None) {
         Ok(out) => Ok(out),
         Err(e) => {
             // Distinguish "node not installed" from runtime failures so the
             // user gets an actionable message instead of a generic spawn error.
-            let msg = e.to_string();
-            if msg.contains("No such file or directory")
-                || msg.contains("program not found")
-                || msg.contains("cannot find the file")
-            {
+            if is_not_found(&e) {
                 Err(anyhow::anyhow!(
                     "Node.js is not installed or not in PATH. \
                      Install it from https://nodejs.org to use {{node}} code blocks."
@@ -529,11 +1193,111 @@ fn execute_node(
     }
 }
 
+/// Execute a block with the runner configured for its language
+/// (`[execute.languages]` or a built-in preset).
+fn execute_command(
+    block: &ExecutableBlock,
+    runner: &LanguageRunner,
+    working_dir: &Path,
+    config: &ExecuteConfig,
+) -> anyhow::Result<ProcessOutput> {
+    let language = &block.language;
+    let Some((program, args)) = runner.command.split_first() else {
+        anyhow::bail!("`[execute.languages.{language}] command` is empty");
+    };
+    let mut cmd = Command::new(program);
+    cmd.args(args);
+
+    let mut input = None;
+    // The code's file, removed once the command has finished
+    let mut _script = None;
+    match runner.input {
+        RunnerInput::Arg => {
+            cmd.arg(block_code(block, working_dir)?);
+        }
+        RunnerInput::Stdin => input = Some(block_code(block, working_dir)?),
+        RunnerInput::File => match &block.file_ref {
+            // Absolute, since sandboxed commands run in a directory of their own
+            Some(file) => {
+                cmd.arg(std::path::absolute(working_dir.join(file))?);
+            }
+            None => {
+                let suffix = runner
+                    .extension
+                    .as_deref()
+                    .map(|ext| format!(".{ext}"))
+                    .unwrap_or_default();
+                let mut script = tempfile::Builder::new()
+                    .prefix("zorto-block-")
+                    .suffix(&suffix)
+                    .tempfile()?;
+                script.write_all(block.source.as_bytes())?;
+                script.flush()?;
+                cmd.arg(script.path());
+                _script = Some(script);
+            }
+        },
+    }
+
+    run_subprocess(cmd, working_dir, config, input.as_deref()).map_err(|e| {
+        if is_not_found(&e) {
+            anyhow::anyhow!(
+                "`{program}` is not installed or not in PATH; it runs {{{language}}} code blocks \
+                 (configurable via `[execute.languages.{language}]` in config.toml)"
+            )
+        } else {
+            e
+        }
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::SandboxConfig;
     use tempfile::TempDir;
 
+    /// Execute `blocks` in a fresh session.
+    fn execute_blocks(
+        blocks: &mut [ExecutableBlock],
+        working_dir: &Path,
+        site_root: &Path,
+        config: &ExecuteConfig,
+    ) -> Vec<String> {
+        ExecSession::new(working_dir, site_root, config).run(blocks)
+    }
+
+    fn timeout(timeout_seconds: u64) -> ExecuteConfig {
+        ExecuteConfig {
+            timeout_seconds,
+            ..Default::default()
+        }
+    }
+
+    fn sandboxed(sandbox: SandboxConfig) -> ExecuteConfig {
+        ExecuteConfig {
+            sandbox: SandboxConfig {
+                enable: true,
+                ..sandbox
+            },
+            ..Default::default()
+        }
+    }
+
+    fn bash_block(source: &str) -> ExecutableBlock {
+        ExecutableBlock {
+            language: "bash".into(),
+            source: source.into(),
+            file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
+            output: None,
+            error: None,
+            viz: Vec::new(),
+        }
+    }
+
     #[test]
     fn test_execute_bash_stdout() {
         let tmp = TempDir::new().unwrap();
@@ -541,11 +1305,14 @@ mod tests {
             language: "bash".into(),
             source: "echo hello".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert_eq!(blocks[0].output.as_deref(), Some("hello\n"));
         assert!(blocks[0].error.is_none());
     }
@@ -557,11 +1324,14 @@ mod tests {
             language: "bash".into(),
             source: "echo oops >&2".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert_eq!(blocks[0].output.as_deref(), Some(""));
         assert_eq!(blocks[0].error.as_deref(), Some("oops\n"));
     }
@@ -574,11 +1344,14 @@ mod tests {
             language: "bash".into(),
             source: String::new(),
             file_ref: Some("script.sh".into()),
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert_eq!(blocks[0].output.as_deref(), Some("from-file\n"));
     }
 
@@ -589,11 +1362,14 @@ mod tests {
             language: "node".into(),
             source: "console.log('hello from node')".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert_eq!(blocks[0].output.as_deref(), Some("hello from node\n"));
         assert!(blocks[0].error.is_none());
     }
@@ -605,11 +1381,14 @@ mod tests {
             language: "node".into(),
             source: "console.error('oops')".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert_eq!(blocks[0].output.as_deref(), Some(""));
         assert_eq!(blocks[0].error.as_deref(), Some("oops\n"));
     }
@@ -621,11 +1400,14 @@ mod tests {
             language: "javascript".into(),
             source: "console.log(1 + 2)".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert_eq!(blocks[0].output.as_deref(), Some("3\n"));
         assert!(blocks[0].error.is_none());
     }
@@ -637,11 +1419,14 @@ mod tests {
             language: "js".into(),
             source: "console.log('js alias')".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert_eq!(blocks[0].output.as_deref(), Some("js alias\n"));
         assert!(blocks[0].error.is_none());
     }
@@ -654,11 +1439,14 @@ mod tests {
             language: "node".into(),
             source: String::new(),
             file_ref: Some("script.js".into()),
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert_eq!(blocks[0].output.as_deref(), Some("from-file\n"));
     }
 
@@ -674,12 +1462,15 @@ mod tests {
             // test hangs (and CI catches it).
             source: "sleep 30".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
         let started = std::time::Instant::now();
-        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), 1);
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(1));
         let elapsed = started.elapsed();
         assert!(
             elapsed < std::time::Duration::from_secs(10),
@@ -707,12 +1498,721 @@ mod tests {
             language: "bash".into(),
             source: "echo unbounded".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: None,
             viz: Vec::new(),
         }];
-        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), 0);
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(0));
         assert!(errors.is_empty(), "no errors expected: {errors:?}");
         assert_eq!(blocks[0].output.as_deref(), Some("unbounded\n"));
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_bash_sandbox_isolates_env_and_dir() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("data.txt"), "page data").unwrap();
+        let mut blocks = vec![bash_block(
+            "echo \"${CARGO_MANIFEST_DIR:-unset}\"; ls; touch scratch; [ -f data.txt ] || echo private",
+        )];
+        let errors = execute_blocks(
+            &mut blocks,
+            tmp.path(),
+            tmp.path(),
+            &sandboxed(SandboxConfig::default()),
+        );
+        assert!(errors.is_empty(), "no errors expected: {errors:?}");
+        assert_eq!(blocks[0].output.as_deref(), Some("unset\nprivate\n"));
+        assert!(!tmp.path().join("scratch").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_bash_sandbox_output_limit() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = vec![bash_block("yes | head -c 100000")];
+        let errors = execute_blocks(
+            &mut blocks,
+            tmp.path(),
+            tmp.path(),
+            &sandboxed(SandboxConfig {
+                max_output_bytes: 1000,
+                ..Default::default()
+            }),
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("output exceeded 1000 bytes"),
+            "{}",
+            errors[0]
+        );
+        assert!(blocks[0].error.is_some());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_bash_sandbox_file_limit() {
+        let tmp = TempDir::new().unwrap();
+        let limits = SandboxConfig {
+            max_output_bytes: 1000,
+            max_file_bytes: 10_000,
+            ..Default::default()
+        };
+        // The output limit does not cap files
+        let mut blocks = vec![bash_block("head -c 5000 /dev/zero > small; wc -c < small")];
+        let errors = execute_blocks(
+            &mut blocks,
+            tmp.path(),
+            tmp.path(),
+            &sandboxed(limits.clone()),
+        );
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(blocks[0].output.as_deref().map(str::trim), Some("5000"));
+
+        let mut blocks = vec![bash_block("exec head -c 20000 /dev/zero > big")];
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &sandboxed(limits));
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("file larger than 10000 bytes")
+                && errors[0].contains("max_file_bytes"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_bash_sandbox_memory_limit() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = vec![bash_block(
+            "x=$(head -c 200000000 /dev/zero | tr '\\0' a); echo ${#x}",
+        )];
+        let errors = execute_blocks(
+            &mut blocks,
+            tmp.path(),
+            tmp.path(),
+            &sandboxed(SandboxConfig {
+                memory_mb: 64,
+                max_output_bytes: 0,
+                ..Default::default()
+            }),
+        );
+        assert_eq!(errors.len(), 1, "expected a memory error: {:?}", blocks[0]);
+        assert!(
+            errors[0].contains("memory limit of 64 MiB"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_bash_sandbox_cpu_limit() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = vec![bash_block("while :; do :; done")];
+        let errors = execute_blocks(
+            &mut blocks,
+            tmp.path(),
+            tmp.path(),
+            &ExecuteConfig {
+                timeout_seconds: 30,
+                ..sandboxed(SandboxConfig {
+                    cpu_seconds: 1,
+                    ..Default::default()
+                })
+            },
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("CPU limit of 1 seconds"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_execute_bash_sandbox_denies_network() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = vec![bash_block(
+            "tail -n +3 /proc/net/dev | cut -d: -f1 | tr -d ' '",
+        )];
+        let errors = execute_blocks(
+            &mut blocks,
+            tmp.path(),
+            tmp.path(),
+            &sandboxed(SandboxConfig {
+                allow_network: false,
+                ..Default::default()
+            }),
+        );
+        if errors.is_empty() {
+            // Only the (down) loopback interface exists in the new namespace
+            assert_eq!(blocks[0].output.as_deref(), Some("lo\n"));
+        } else {
+            // Kernels without unprivileged user namespaces fail clearly
+            assert!(
+                errors[0].contains("could not isolate the network"),
+                "{}",
+                errors[0]
+            );
+        }
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn test_execute_python_serialized_across_threads() {
+        // Python blocks share `__main__`; each call's blocks must run without
+        // another thread's blocks interleaving between them.
+        let tmp = TempDir::new().unwrap();
+        std::thread::scope(|scope| {
+            for n in 0..4 {
+                let dir = tmp.path();
+                scope.spawn(move || {
+                    let block = |source: String| ExecutableBlock {
+                        language: "python".into(),
+                        source,
+                        file_ref: None,
+                        deps: Vec::new(),
+                        options: Default::default(),
+                        data: Default::default(),
+                        output: None,
+                        error: None,
+                        viz: Vec::new(),
+                    };
+                    let mut blocks = vec![
+                        block(format!("zorto_thread_value = {n}")),
+                        block("import time\ntime.sleep(0.02)\nprint(zorto_thread_value)".into()),
+                    ];
+                    let errors = execute_blocks(&mut blocks, dir, dir, &timeout(0));
+                    assert!(errors.is_empty(), "no errors expected: {errors:?}");
+                    assert_eq!(blocks[1].output.as_deref(), Some(format!("{n}\n").as_str()));
+                });
+            }
+        });
+    }
+
+    fn runner(command: &[&str], input: RunnerInput, extension: Option<&str>) -> ExecuteConfig {
+        let runner = LanguageRunner {
+            command: command.iter().map(|s| s.to_string()).collect(),
+            input,
+            extension: extension.map(String::from),
+        };
+        ExecuteConfig {
+            languages: [("shell".to_string(), runner)].into(),
+            ..timeout(10)
+        }
+    }
+
+    fn shell_block(source: &str) -> ExecutableBlock {
+        ExecutableBlock {
+            language: "shell".into(),
+            ..bash_block(source)
+        }
+    }
+
+    #[test]
+    fn test_execute_language_runner_inputs() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let cases = [
+            (runner(&["sh", "-c"], RunnerInput::Arg, None), "echo arg\n"),
+            (runner(&["sh"], RunnerInput::Stdin, None), "echo stdin\n"),
+            (
+                runner(&["sh"], RunnerInput::File, Some("sh")),
+                "echo file\n",
+            ),
+        ];
+        for (config, source) in cases {
+            let mut blocks = vec![shell_block(source)];
+            let errors = execute_blocks(&mut blocks, dir, dir, &config);
+            assert!(errors.is_empty(), "no errors expected: {errors:?}");
+            assert_eq!(
+                blocks[0].output.as_deref(),
+                Some(source.trim_start_matches("echo "))
+            );
+        }
+
+        // Files get the configured extension
+        let config = runner(&["sh", "-c", "echo \"$0\""], RunnerInput::File, Some("R"));
+        let mut blocks = vec![shell_block("")];
+        execute_blocks(&mut blocks, dir, dir, &config);
+        assert!(
+            blocks[0]
+                .output
+                .as_deref()
+                .unwrap()
+                .trim_end()
+                .ends_with(".R")
+        );
+
+        // file= blocks pass their own file
+        std::fs::write(dir.join("script.sh"), "echo from file\n").unwrap();
+        let config = runner(&["sh"], RunnerInput::File, None);
+        let mut blocks = vec![ExecutableBlock {
+            file_ref: Some("script.sh".into()),
+            ..shell_block("")
+        }];
+        execute_blocks(&mut blocks, dir, dir, &config);
+        assert_eq!(blocks[0].output.as_deref(), Some("from file\n"));
+    }
+
+    #[test]
+    fn test_execute_language_runner_errors() {
+        let tmp = TempDir::new().unwrap();
+        let dir = tmp.path();
+        let config = runner(&["zorto-no-such-program"], RunnerInput::Arg, None);
+        let mut blocks = vec![shell_block("1 + 1")];
+        let errors = execute_blocks(&mut blocks, dir, dir, &config);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("`zorto-no-such-program` is not installed"),
+            "{errors:?}"
+        );
+        assert!(
+            errors[0].contains("[execute.languages.shell]"),
+            "{errors:?}"
+        );
+
+        let mut blocks = vec![ExecutableBlock {
+            language: "cobol".into(),
+            ..bash_block("DISPLAY 'HI'.")
+        }];
+        let errors = execute_blocks(&mut blocks, dir, dir, &timeout(10));
+        assert_eq!(errors, ["Unsupported executable language: cobol"]);
+    }
+
+    #[test]
+    fn test_job_slots_limit_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let slots = JobSlots::new(2);
+        let (running, peak) = (AtomicUsize::new(0), AtomicUsize::new(0));
+        std::thread::scope(|scope| {
+            for _ in 0..6 {
+                scope.spawn(|| {
+                    let _slot = slots.acquire();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    std::thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert_eq!(peak.into_inner(), 2);
+    }
+
+    fn subprocess_python(timeout_seconds: u64) -> ExecuteConfig {
+        ExecuteConfig {
+            timeout_seconds,
+            python_mode: PythonMode::Subprocess,
+            ..Default::default()
+        }
+    }
+
+    fn python_block(source: &str) -> ExecutableBlock {
+        ExecutableBlock {
+            language: "python".into(),
+            ..bash_block(source)
+        }
+    }
+
+    #[test]
+    fn test_execute_python_subprocess_keeps_state_within_page() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("data.txt"), "page data").unwrap();
+        let config = subprocess_python(30);
+        let mut session = ExecSession::new(tmp.path(), tmp.path(), &config);
+
+        let mut first = [python_block("x = 41\nprint(open('data.txt').read())")];
+        assert!(session.run(&mut first).is_empty());
+        assert_eq!(first[0].output.as_deref(), Some("page data\n"));
+
+        let mut second = [
+            python_block("import sys\nprint(x + 1)\nprint('warn', file=sys.stderr)"),
+            python_block("raise ValueError('boom')"),
+        ];
+        assert_eq!(session.run(&mut second), ["Python error: ValueError: boom"]);
+        assert_eq!(second[0].output.as_deref(), Some("42\n"));
+        assert_eq!(second[0].error.as_deref(), Some("warn\n"));
+        assert_eq!(second[1].error.as_deref(), Some("ValueError: boom"));
+
+        // A new session (page) starts from a clean namespace
+        let mut other = [python_block("print(x)")];
+        execute_blocks(&mut other, tmp.path(), tmp.path(), &config);
+        assert!(other[0].error.as_deref().unwrap().contains("NameError"));
+    }
+
+    #[test]
+    fn test_execute_python_embedded_refused_when_sandboxed() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = [python_block("open('ran', 'w').close()")];
+        let errors = execute_blocks(
+            &mut blocks,
+            tmp.path(),
+            tmp.path(),
+            &sandboxed(SandboxConfig::default()),
+        );
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("cannot be sandboxed"), "{}", errors[0]);
+        assert!(!tmp.path().join("ran").exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_python_subprocess_sandboxed() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("data.txt"), "page data").unwrap();
+        let config = ExecuteConfig {
+            python_mode: PythonMode::Subprocess,
+            ..sandboxed(SandboxConfig::default())
+        };
+        let mut blocks = [
+            python_block(
+                "import os\nprint(os.environ.get('CARGO_MANIFEST_DIR', 'unset'))\n\
+                 print(os.path.exists('data.txt'))\nprint(os.getcwd() == os.environ['HOME'])\n\
+                 open('scratch', 'w').close()",
+            ),
+            python_block("print('x' * 5000)"),
+        ];
+        let mut session = ExecSession::new(tmp.path(), tmp.path(), &config);
+        let errors = session.run(&mut blocks[..1]);
+        assert!(errors.is_empty(), "no errors expected: {errors:?}");
+        assert_eq!(blocks[0].output.as_deref(), Some("unset\nFalse\nTrue\n"));
+        assert!(!tmp.path().join("scratch").exists());
+
+        let config = ExecuteConfig {
+            python_mode: PythonMode::Subprocess,
+            ..sandboxed(SandboxConfig {
+                max_output_bytes: 1000,
+                ..Default::default()
+            })
+        };
+        let errors = execute_blocks(&mut blocks[1..], tmp.path(), tmp.path(), &config);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("output exceeded 1000 bytes"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[test]
+    fn test_execute_python_subprocess_timeout_kills_worker() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = vec![
+            python_block("while True:\n    pass"),
+            python_block("print('after')"),
+        ];
+        let started = std::time::Instant::now();
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &subprocess_python(1));
+        assert!(
+            started.elapsed() < std::time::Duration::from_secs(10),
+            "timeout did not fire"
+        );
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(errors[0].contains("1-second timeout"), "{}", errors[0]);
+        // Later blocks would run without the state they expect; skip them
+        assert!(errors[1].contains("skipped"), "{}", errors[1]);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_execute_python_subprocess_timeout_kills_process_group() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = [python_block(
+            "import subprocess, time\n\
+             p = subprocess.Popen(['sleep', '60'])\n\
+             open('pid', 'w').write(str(p.pid))\n\
+             time.sleep(60)",
+        )];
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &subprocess_python(1));
+        assert!(errors[0].contains("1-second timeout"), "{errors:?}");
+        let pid = std::fs::read_to_string(tmp.path().join("pid")).unwrap();
+        // Gone, or a zombie waiting for init to reap it
+        let alive = || {
+            std::fs::read_to_string(format!("/proc/{pid}/stat")).is_ok_and(|stat| {
+                !stat
+                    .rsplit(')')
+                    .next()
+                    .unwrap()
+                    .trim_start()
+                    .starts_with('Z')
+            })
+        };
+        let started = std::time::Instant::now();
+        while alive() && started.elapsed() < std::time::Duration::from_secs(5) {
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        assert!(!alive(), "the block's `sleep` outlived the worker");
+    }
+
+    #[test]
+    fn test_execute_python_subprocess_captures_viz() {
+        let tmp = TempDir::new().unwrap();
+        // A stand-in for plotly, so the test does not need it installed
+        let mut blocks = vec![python_block(
+            "import sys, types\n\
+             plotly = types.ModuleType('plotly')\n\
+             go = types.ModuleType('plotly.graph_objects')\n\
+             class Figure:\n    \
+                 def to_html(self, full_html, include_plotlyjs):\n        \
+                     return '<div>figure</div>'\n\
+             go.Figure = Figure\n\
+             plotly.graph_objects = go\n\
+             sys.modules.update({'plotly': plotly, 'plotly.graph_objects': go})\n\
+             fig = Figure()",
+        )];
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &subprocess_python(30));
+        assert!(errors.is_empty(), "{errors:?}");
+        assert_eq!(blocks[0].viz.len(), 1, "{:?}", blocks[0]);
+        assert_eq!(blocks[0].viz[0].kind, "html");
+        assert_eq!(blocks[0].viz[0].data, "<div>figure</div>");
+    }
+
+    #[test]
+    fn test_execute_python_subprocess_missing_interpreter() {
+        let tmp = TempDir::new().unwrap();
+        let config = ExecuteConfig {
+            python: Some("zorto-no-such-python".into()),
+            ..subprocess_python(30)
+        };
+        let mut blocks = vec![python_block("print(1)")];
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &config);
+        assert_eq!(errors.len(), 1);
+        assert!(
+            errors[0].contains("`zorto-no-such-python` not found"),
+            "{}",
+            errors[0]
+        );
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn test_execute_python_embedded_namespace_per_session() {
+        let tmp = TempDir::new().unwrap();
+        let config = ExecuteConfig::default();
+        let shared = SharedNamespaces::default();
+
+        let mut define = [python_block("zorto_ns_value = 'kept'")];
+        ExecSession::new(tmp.path(), tmp.path(), &config)
+            .shared_namespace("tour", &shared)
+            .run(&mut define);
+
+        let mut fresh = [python_block(
+            "print(globals().get('zorto_ns_value', 'fresh'))",
+        )];
+        execute_blocks(&mut fresh, tmp.path(), tmp.path(), &config);
+        assert_eq!(fresh[0].output.as_deref(), Some("fresh\n"));
+
+        let mut joined = [python_block("print(zorto_ns_value)")];
+        ExecSession::new(tmp.path(), tmp.path(), &config)
+            .shared_namespace("tour", &shared)
+            .run(&mut joined);
+        assert_eq!(joined[0].output.as_deref(), Some("kept\n"));
+
+        shared.clear();
+        let mut cleared = [python_block(
+            "print(globals().get('zorto_ns_value', 'cleared'))",
+        )];
+        ExecSession::new(tmp.path(), tmp.path(), &config)
+            .shared_namespace("tour", &shared)
+            .run(&mut cleared);
+        assert_eq!(cleared[0].output.as_deref(), Some("cleared\n"));
+    }
+
+    #[test]
+    fn test_execute_python_subprocess_shared_namespace() {
+        let tmp = TempDir::new().unwrap();
+        let config = subprocess_python(30);
+        let shared = SharedNamespaces::default();
+        let mut define = [python_block("value = 7")];
+        ExecSession::new(tmp.path(), tmp.path(), &config)
+            .shared_namespace("tour", &shared)
+            .run(&mut define);
+        let mut joined = [python_block("print(value * 6)")];
+        ExecSession::new(tmp.path(), tmp.path(), &config)
+            .shared_namespace("tour", &shared)
+            .run(&mut joined);
+        assert_eq!(joined[0].output.as_deref(), Some("42\n"));
+    }
+
+    #[test]
+    fn test_execute_bash_data_file() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = vec![
+            bash_block(
+                "echo '{\"total\": 1, \"name\": \"a\"}' > \"$ZORTO_DATA\"\n\
+                 echo '{\"total\": 2}' >> \"$ZORTO_DATA\"",
+            ),
+            bash_block("echo nothing"),
+        ];
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(30));
+        assert!(errors.is_empty(), "no errors expected: {errors:?}");
+        assert_eq!(
+            serde_json::Value::Object(blocks[0].data.clone()),
+            serde_json::json!({"total": 2, "name": "a"})
+        );
+        assert!(blocks[1].data.is_empty());
+
+        for (source, expected) in [
+            (
+                "echo '{\"total\":' > \"$ZORTO_DATA\"",
+                "invalid JSON in $ZORTO_DATA",
+            ),
+            (
+                "echo '[1]' > \"$ZORTO_DATA\"",
+                "must contain JSON objects, found `[1]`",
+            ),
+        ] {
+            let mut blocks = vec![bash_block(source)];
+            let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), &timeout(30));
+            assert_eq!(errors.len(), 1);
+            assert!(errors[0].contains(expected), "got: {}", errors[0]);
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_execute_bash_sandbox_data_file() {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = vec![bash_block("echo '{\"sandboxed\": true}' > \"$ZORTO_DATA\"")];
+        let errors = execute_blocks(
+            &mut blocks,
+            tmp.path(),
+            tmp.path(),
+            &sandboxed(SandboxConfig::default()),
+        );
+        assert!(errors.is_empty(), "no errors expected: {errors:?}");
+        assert_eq!(blocks[0].data["sandboxed"], true);
+    }
+
+    /// Check that `zorto.set()` values become the blocks' data.
+    fn check_zorto_set(config: &ExecuteConfig) {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = [
+            python_block(
+                "class Scalar:\n    def tolist(self):\n        return 7\n\
+                 zorto.set('total', 1234)\nzorto.set('rows', [1, 2])\nzorto.set('scalar', Scalar())",
+            ),
+            python_block("print('no data')"),
+            python_block("zorto.set('seen', True)\nraise ValueError('boom')"),
+            python_block("zorto.set('bad', object())"),
+            python_block(
+                "import types, zorto\n\
+                 zorto.set('imported', 1)\n\
+                 helper = types.ModuleType('helper')\n\
+                 exec('import zorto\\ndef record():\\n    zorto.set(\"helper\", 2)', helper.__dict__)\n\
+                 helper.record()",
+            ),
+        ];
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), config);
+        assert_eq!(
+            serde_json::Value::Object(blocks[0].data.clone()),
+            serde_json::json!({"total": 1234, "rows": [1, 2], "scalar": 7})
+        );
+        // Values are collected after each block
+        assert!(blocks[1].data.is_empty());
+        assert_eq!(blocks[2].data["seen"], true);
+        // `import zorto` reaches the running block, also from other modules
+        assert_eq!(
+            serde_json::Value::Object(blocks[4].data.clone()),
+            serde_json::json!({"imported": 1, "helper": 2})
+        );
+        assert_eq!(errors.len(), 2, "got: {errors:?}");
+        assert!(errors[0].contains("boom"), "got: {}", errors[0]);
+        assert!(
+            errors[1].contains("type object is not JSON serializable"),
+            "got: {}",
+            errors[1]
+        );
+    }
+
+    #[test]
+    fn test_execute_python_subprocess_zorto_set() {
+        check_zorto_set(&subprocess_python(30));
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn test_execute_python_embedded_zorto_set() {
+        check_zorto_set(&ExecuteConfig::default());
+    }
+
+    /// Check that final expressions display through their `_repr_*_` methods.
+    fn check_rich_display(config: &ExecuteConfig) {
+        let tmp = TempDir::new().unwrap();
+        let mut blocks = [
+            python_block(
+                "class Show:\n\
+                 \x20   def __init__(self, **reprs):\n\
+                 \x20       for name, value in reprs.items():\n\
+                 \x20           setattr(self, f'_repr_{name}_', lambda value=value: value)\n\
+                 table = Show(html='<table></table>')\n\
+                 table",
+            ),
+            python_block("table  # seen before, but shown again"),
+            python_block("table;"),
+            python_block("Show(markdown='**bold**', png=b'png')"),
+            python_block("Show(svg='<?xml version=\"1.0\"?>\\n<svg></svg>')"),
+            python_block("Show(png=(b'png', {'width': 10}))"),
+            python_block(
+                "class Bundle:\n\
+                 \x20   def _repr_mimebundle_(self, include=None, exclude=None):\n\
+                 \x20       return {'text/markdown': '# md', 'image/png': 'cG5n\\n'}\n\
+                 Bundle()",
+            ),
+            python_block("shown = Show(html='<p>global</p>')\nprint('no display')\nShow"),
+        ];
+        let errors = execute_blocks(&mut blocks, tmp.path(), tmp.path(), config);
+        assert!(errors.is_empty(), "no errors expected: {errors:?}");
+        let viz: Vec<Vec<(&str, &str)>> = blocks
+            .iter()
+            .map(|b| {
+                b.viz
+                    .iter()
+                    .map(|v| (v.kind.as_str(), v.data.as_str()))
+                    .collect()
+            })
+            .collect();
+        assert_eq!(viz[0], [("html", "<table></table>")]);
+        assert_eq!(viz[1], [("html", "<table></table>")]);
+        // A trailing `;` suppresses the display
+        assert!(viz[2].is_empty());
+        // HTML, SVG, PNG and JPEG come before markdown
+        assert_eq!(viz[3], [("img", "data:image/png;base64,cG5n")]);
+        assert_eq!(viz[4], [("html", "<svg></svg>")]);
+        assert_eq!(viz[5], [("img", "data:image/png;base64,cG5n")]);
+        assert_eq!(viz[6], [("img", "data:image/png;base64,cG5n")]);
+        // Neither globals nor classes are displayed
+        assert!(viz[7].is_empty());
+        assert_eq!(blocks[7].output.as_deref(), Some("no display\n"));
+    }
+
+    #[test]
+    fn test_execute_python_subprocess_rich_display() {
+        check_rich_display(&subprocess_python(30));
+    }
+
+    #[cfg(feature = "python")]
+    #[test]
+    fn test_execute_python_embedded_rich_display() {
+        check_rich_display(&ExecuteConfig::default());
+    }
+
+    #[test]
+    fn test_shared_namespace_from_extra() {
+        assert_eq!(
+            shared_namespace(&serde_json::json!({"python_namespace": "tour"})),
+            Some("tour")
+        );
+        assert_eq!(
+            shared_namespace(&serde_json::json!({"python_namespace": ""})),
+            None
+        );
+        assert_eq!(shared_namespace(&serde_json::json!({})), None);
+    }
 }
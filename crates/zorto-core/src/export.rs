@@ -0,0 +1,435 @@
+//! Downloadable notebook and script versions of pages with executable code
+//! blocks (`[execute] export = ["ipynb", "py"]`).
+//!
+//! The page's post-shortcode markdown is split at its executable blocks:
+//! prose becomes markdown cells and each block becomes a code cell. The
+//! notebook carries the outputs the build produced; the script is in the
+//! percent format (`# %%` cells) understood by Jupytext, VS Code and Spyder.
+//! Blocks in other languages become `%%bash`/`%%script` cells in the
+//! notebook when their command can read code from standard input, and
+//! fenced code in a markdown cell otherwise (always, in the script).
+
+use std::path::Path;
+
+use crate::config::{ExecuteConfig, ExportFormat, MarkdownConfig, RunnerInput};
+use crate::execute::ExecutableBlock;
+use crate::markdown::executable_block_ranges;
+
+/// One generated download for a page.
+#[derive(Debug, Clone)]
+pub(crate) struct Download {
+    pub format: ExportFormat,
+    pub contents: String,
+}
+
+impl Download {
+    /// File name of the download for a page with the given slug.
+    pub(crate) fn file_name(&self, slug: &str) -> String {
+        let stem = if slug.is_empty() { "index" } else { slug };
+        format!("{stem}.{}", self.format.extension())
+    }
+}
+
+/// A page split into prose and executable blocks.
+enum Cell<'a> {
+    Markdown(String),
+    Code {
+        block: &'a ExecutableBlock,
+        source: String,
+    },
+}
+
+/// Build the downloads in `[execute] export` for a page whose `markdown`
+/// rendered to the executed `blocks`. Pages without executable blocks get
+/// none.
+///
+/// `file=` blocks are read from `working_dir`, as when they were executed.
+pub(crate) fn export_page(
+    title: &str,
+    markdown: &str,
+    blocks: &[ExecutableBlock],
+    working_dir: &Path,
+    config: &MarkdownConfig,
+    execute: &ExecuteConfig,
+) -> Vec<Download> {
+    if blocks.is_empty() || execute.export.is_empty() {
+        return Vec::new();
+    }
+    let cells = split_cells(title, markdown, blocks, working_dir, config);
+    let mut formats = execute.export.clone();
+    formats.sort();
+    formats.dedup();
+    formats
+        .into_iter()
+        .map(|format| Download {
+            format,
+            contents: match format {
+                ExportFormat::Ipynb => to_ipynb(&cells, execute),
+                ExportFormat::Py => to_script(&cells),
+            },
+        })
+        .collect()
+}
+
+fn split_cells<'a>(
+    title: &str,
+    markdown: &str,
+    blocks: &'a [ExecutableBlock],
+    working_dir: &Path,
+    config: &MarkdownConfig,
+) -> Vec<Cell<'a>> {
+    fn push_markdown(cells: &mut Vec<Cell>, text: &str) {
+        let text = text.trim();
+        if !text.is_empty() {
+            cells.push(Cell::Markdown(text.to_string()));
+        }
+    }
+
+    let mut cells = Vec::new();
+    if !title.is_empty() {
+        push_markdown(&mut cells, &format!("# {title}"));
+    }
+    let mut pos = 0;
+    for (range, block) in executable_block_ranges(markdown, config)
+        .into_iter()
+        .zip(blocks)
+    {
+        push_markdown(&mut cells, &markdown[pos..range.start]);
+        pos = range.end;
+        let source = match &block.file_ref {
+            Some(file) => std::fs::read_to_string(working_dir.join(file))
+                .unwrap_or_else(|e| format!("# {file}: {e}\n")),
+            None => block.source.clone(),
+        };
+        cells.push(Cell::Code { block, source });
+    }
+    push_markdown(&mut cells, &markdown[pos..]);
+    cells
+}
+
+fn is_python(block: &ExecutableBlock) -> bool {
+    block.language == "python"
+}
+
+/// The cell magic that runs a non-Python block in IPython, which pipes the
+/// cell to the command's standard input. `None` if the language's runner
+/// cannot read code from there.
+fn cell_magic(language: &str, execute: &ExecuteConfig) -> Option<String> {
+    match language {
+        "bash" | "sh" => return Some("%%bash".to_string()),
+        "node" | "js" => return Some("%%script node".to_string()),
+        _ => {}
+    }
+    let runner = execute.runner(language)?;
+    let mut command = runner.command;
+    match runner.input {
+        RunnerInput::Stdin => {}
+        // `-e <code>` (Rscript, julia, ruby, perl) becomes `-`, read stdin
+        RunnerInput::Arg if command.last().is_some_and(|arg| arg == "-e") => {
+            command.pop();
+            command.push("-".to_string());
+        }
+        RunnerInput::Arg | RunnerInput::File => return None,
+    }
+    let words: Vec<String> = command.iter().map(|word| shell_quote(word)).collect();
+    Some(format!("%%script {}", words.join(" ")))
+}
+
+/// Quote `word` for the POSIX-style splitting `%%script` does.
+fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', "'\"'\"'"))
+    }
+}
+
+/// Fenced code for a block that cannot run as a cell.
+fn fenced(block: &ExecutableBlock, source: &str) -> String {
+    format!("```{}\n{}\n```", block.language, source.trim_end())
+}
+
+/// Split text into nbformat's list-of-lines form.
+fn lines(text: &str) -> Vec<&str> {
+    text.split_inclusive('\n').collect()
+}
+
+fn to_ipynb(cells: &[Cell], execute: &ExecuteConfig) -> String {
+    let mut execution_count = 0;
+    let cells: Vec<serde_json::Value> = cells
+        .iter()
+        .enumerate()
+        .map(|(i, cell)| match cell {
+            Cell::Markdown(text) => serde_json::json!({
+                "cell_type": "markdown",
+                "id": format!("cell-{i}"),
+                "metadata": {},
+                "source": lines(text),
+            }),
+            Cell::Code { block, source } => {
+                let source = source.trim_end();
+                let source = if is_python(block) {
+                    source.to_string()
+                } else if let Some(magic) = cell_magic(&block.language, execute) {
+                    format!("{magic}\n{source}")
+                } else {
+                    return serde_json::json!({
+                        "cell_type": "markdown",
+                        "id": format!("cell-{i}"),
+                        "metadata": {},
+                        "source": lines(&fenced(block, source)),
+                    });
+                };
+                let outputs = notebook_outputs(block);
+                let count = if block.output.is_some() || !outputs.is_empty() {
+                    execution_count += 1;
+                    serde_json::json!(execution_count)
+                } else {
+                    serde_json::Value::Null
+                };
+                serde_json::json!({
+                    "cell_type": "code",
+                    "execution_count": count,
+                    "id": format!("cell-{i}"),
+                    "metadata": {},
+                    "outputs": outputs,
+                    "source": lines(&source),
+                })
+            }
+        })
+        .collect();
+    let notebook = serde_json::json!({
+        "cells": cells,
+        "metadata": {
+            "kernelspec": {"display_name": "Python 3", "language": "python", "name": "python3"},
+            "language_info": {"name": "python"},
+        },
+        "nbformat": 4,
+        "nbformat_minor": 5,
+    });
+    // serde_json::to_string_pretty cannot fail for a Value
+    let mut json = serde_json::to_string_pretty(&notebook).unwrap_or_default();
+    json.push('\n');
+    json
+}
+
+/// A block's output, error and visualizations as nbformat outputs.
+fn notebook_outputs(block: &ExecutableBlock) -> Vec<serde_json::Value> {
+    let mut outputs = Vec::new();
+    let stream = |name: &str, text: &str| serde_json::json!({"output_type": "stream", "name": name, "text": lines(text)});
+    if let Some(output) = block.output.as_deref().filter(|o| !o.is_empty()) {
+        outputs.push(stream("stdout", output));
+    }
+    if let Some(error) = block.error.as_deref().filter(|e| !e.is_empty()) {
+        outputs.push(stream("stderr", error));
+    }
+    for viz in &block.viz {
+        let data = match viz.kind.as_str() {
+            "img" => match data_uri_parts(&viz.data) {
+                Some((mime, data)) => serde_json::json!({mime: data, "text/plain": ["<image>"]}),
+                None => continue,
+            },
+            "html" => serde_json::json!({"text/html": lines(&viz.data)}),
+            "markdown" => serde_json::json!({"text/markdown": lines(&viz.data)}),
+            _ => continue,
+        };
+        outputs.push(serde_json::json!({
+            "output_type": "display_data",
+            "data": data,
+            "metadata": {},
+        }));
+    }
+    outputs
+}
+
+/// Split a base64 `data:` URI into its MIME type and payload.
+pub(crate) fn data_uri_parts(uri: &str) -> Option<(&str, &str)> {
+    let (header, data) = uri.strip_prefix("data:")?.split_once(',')?;
+    let mime = header.strip_suffix(";base64")?;
+    Some((mime, data))
+}
+
+fn to_script(cells: &[Cell]) -> String {
+    let comment = |text: &str| -> String {
+        text.lines()
+            .map(|line| {
+                if line.is_empty() {
+                    "#".to_string()
+                } else {
+                    format!("# {line}")
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    let chunks: Vec<String> = cells
+        .iter()
+        .map(|cell| match cell {
+            Cell::Markdown(text) => format!("# %% [markdown]\n{}", comment(text)),
+            Cell::Code { block, source } if is_python(block) => {
+                format!("# %%\n{}", source.trim_end())
+            }
+            Cell::Code { block, source } => {
+                format!("# %% [markdown]\n{}", comment(&fenced(block, source)))
+            }
+        })
+        .collect();
+    format!("{}\n", chunks.join("\n\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::LanguageRunner;
+    use crate::execute::VizOutput;
+
+    fn block(language: &str, source: &str, output: Option<&str>) -> ExecutableBlock {
+        ExecutableBlock {
+            language: language.into(),
+            source: source.into(),
+            file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
+            output: output.map(String::from),
+            error: None,
+            viz: Vec::new(),
+        }
+    }
+
+    const MARKDOWN: &str = "Intro text.\n\n```{python}\nx = 21\nprint(x * 2)\n```\n\nMiddle.\n\n```{bash}\necho hi\n```\n\nOutro.\n";
+
+    fn blocks() -> Vec<ExecutableBlock> {
+        let mut python = block("python", "x = 21\nprint(x * 2)\n", Some("42\n"));
+        python.viz.push(VizOutput {
+            kind: "img".into(),
+            data: "data:image/png;base64,iVBORw0KGgo=".into(),
+        });
+        vec![python, block("bash", "echo hi\n", Some("hi\n"))]
+    }
+
+    fn exporting(formats: &[ExportFormat]) -> ExecuteConfig {
+        ExecuteConfig {
+            export: formats.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn export(format: ExportFormat) -> String {
+        let downloads = export_page(
+            "Report",
+            MARKDOWN,
+            &blocks(),
+            Path::new("."),
+            &MarkdownConfig::default(),
+            &exporting(&[format]),
+        );
+        assert_eq!(downloads.len(), 1);
+        downloads[0].contents.clone()
+    }
+
+    #[test]
+    fn test_export_ipynb_cells_and_outputs() {
+        let nb: serde_json::Value = serde_json::from_str(&export(ExportFormat::Ipynb)).unwrap();
+        assert_eq!(nb["nbformat"], 4);
+        let cells = nb["cells"].as_array().unwrap();
+        let types: Vec<&str> = cells
+            .iter()
+            .map(|c| c["cell_type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "markdown", "markdown", "code", "markdown", "code", "markdown"
+            ]
+        );
+        assert_eq!(cells[0]["source"][0], "# Report");
+        assert_eq!(cells[2]["source"][0], "x = 21\n");
+        assert_eq!(cells[2]["execution_count"], 1);
+        assert_eq!(cells[2]["outputs"][0]["text"][0], "42\n");
+        assert_eq!(cells[2]["outputs"][1]["data"]["image/png"], "iVBORw0KGgo=");
+        assert_eq!(cells[4]["source"][0], "%%bash\n");
+        assert_eq!(cells[5]["source"][0], "Outro.");
+    }
+
+    #[test]
+    fn test_export_script_percent_format() {
+        let script = export(ExportFormat::Py);
+        assert_eq!(
+            script,
+            "# %% [markdown]\n# # Report\n\n# %% [markdown]\n# Intro text.\n\n# %%\nx = 21\nprint(x * 2)\n\n\
+             # %% [markdown]\n# Middle.\n\n# %% [markdown]\n# ```bash\n# echo hi\n# ```\n\n# %% [markdown]\n# Outro.\n"
+        );
+    }
+
+    #[test]
+    fn test_export_page_without_blocks_or_formats() {
+        let config = MarkdownConfig::default();
+        let dir = Path::new(".");
+        let py = exporting(&[ExportFormat::Py]);
+        assert!(export_page("T", "text", &[], dir, &config, &py).is_empty());
+        assert!(export_page("T", MARKDOWN, &blocks(), dir, &config, &exporting(&[])).is_empty());
+    }
+
+    #[test]
+    fn test_export_ipynb_runner_magics() {
+        let mut execute = exporting(&[ExportFormat::Ipynb]);
+        execute.languages.insert(
+            "lua".into(),
+            LanguageRunner {
+                command: vec!["lua 5.4".into(), "-".into()],
+                input: RunnerInput::Stdin,
+                extension: None,
+            },
+        );
+        let languages = ["r", "duckdb", "lua", "rust", "unknown"];
+        let markdown: String = languages
+            .iter()
+            .map(|lang| format!("```{{{lang}}}\ncode\n```\n\n"))
+            .collect();
+        let blocks: Vec<ExecutableBlock> = languages
+            .iter()
+            .map(|lang| block(lang, "code\n", None))
+            .collect();
+        let downloads = export_page(
+            "",
+            &markdown,
+            &blocks,
+            Path::new("."),
+            &MarkdownConfig::default(),
+            &execute,
+        );
+        let nb: serde_json::Value = serde_json::from_str(&downloads[0].contents).unwrap();
+        let cells = nb["cells"].as_array().unwrap();
+        assert_eq!(cells[0]["source"][0], "%%script Rscript -\n");
+        assert_eq!(cells[1]["source"][0], "%%script duckdb\n");
+        assert_eq!(cells[2]["source"][0], "%%script 'lua 5.4' -\n");
+        // No command that reads stdin: shown as code instead
+        for cell in &cells[3..] {
+            assert_eq!(cell["cell_type"], "markdown");
+        }
+        assert_eq!(cells[3]["source"][0], "```rust\n");
+    }
+
+    #[test]
+    fn test_export_reads_file_blocks() {
+        let tmp = tempfile::TempDir::new().unwrap();
+        std::fs::write(tmp.path().join("script.py"), "print('from file')\n").unwrap();
+        let mut file_block = block("python", "", None);
+        file_block.file_ref = Some("script.py".into());
+        let downloads = export_page(
+            "",
+            "```{python file=\"script.py\"}\n```\n",
+            &[file_block],
+            tmp.path(),
+            &MarkdownConfig::default(),
+            &exporting(&[ExportFormat::Py]),
+        );
+        assert_eq!(downloads[0].contents, "# %%\nprint('from file')\n");
+        assert_eq!(downloads[0].file_name("report"), "report.py");
+    }
+}
@@ -0,0 +1,291 @@
+//! Pages generated from data files (`[[generators]]` in `config.toml`).
+//!
+//! Every record of the data file — a row of a CSV file, or an entry of a
+//! JSON, YAML or TOML array — becomes a page in the configured section, as if
+//! a markdown file with the record as its frontmatter `extra` lived there.
+//! Generated pages then take part in sections, taxonomies, feeds, the
+//! sitemap and `llms.txt` like any other page.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde_json::Value;
+
+use crate::config::{Config, GeneratorConfig};
+use crate::content::{Frontmatter, Page, build_page};
+use crate::data::{self, DataFormat};
+
+/// Generate the pages of `generator`, keyed by the content path they would
+/// have as markdown files (`<section>/<slug>.md`).
+pub(crate) fn generate_pages(
+    root: &Path,
+    generator: &GeneratorConfig,
+    config: &Config,
+) -> anyhow::Result<HashMap<String, Page>> {
+    let source = root.join(&generator.source);
+    let format = data::format_for(&source, generator.format.as_deref())?;
+    let text = std::fs::read_to_string(&source)
+        .map_err(|e| anyhow::anyhow!("cannot read {}: {e}", source.display()))?;
+    let records = data::parse(&text, format)
+        .and_then(|value| records(value, format, generator.key.as_deref()))
+        .map_err(|e| anyhow::anyhow!("{}: {e}", generator.source))?;
+
+    let section = generator.section.trim_matches('/');
+    let mut pages = HashMap::new();
+    for (i, record) in records.into_iter().enumerate() {
+        let field = |name: &str| record.get(name).filter(|v| !v.is_null());
+        let text_field = |name: &str| {
+            field(name).map(|v| match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+        };
+
+        let title = text_field(&generator.title);
+        let slug = text_field(&generator.slug)
+            .or_else(|| title.clone())
+            .map(slug::slugify)
+            .filter(|s| !s.is_empty())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "{}: record {} has no `{}` or `{}` field to take its slug from",
+                    generator.source,
+                    i + 1,
+                    generator.slug,
+                    generator.title
+                )
+            })?;
+        let rel_path = if section.is_empty() {
+            format!("{slug}.md")
+        } else {
+            format!("{section}/{slug}.md")
+        };
+
+        let body = text_field(&generator.content).unwrap_or_default();
+        let extra = record
+            .iter()
+            .filter_map(|(k, v)| Some((k.clone(), json_to_toml(v)?)));
+        let fm = Frontmatter {
+            title,
+            date: field(&generator.date).and_then(json_to_toml),
+            description: text_field(&generator.description),
+            slug: Some(slug),
+            template: Some(generator.template.clone()),
+            rest: config
+                .taxonomies
+                .iter()
+                .filter_map(|t| Some((t.name.clone(), terms(field(&t.name)?))))
+                .collect(),
+            extra: toml::Value::Table(extra.collect()),
+            ..Default::default()
+        };
+        let mut page = build_page(fm, body, &rel_path, &config.base_url);
+        page.lang = config.default_language.clone();
+        if pages.insert(rel_path.clone(), page).is_some() {
+            anyhow::bail!(
+                "{}: more than one record generates the page {rel_path}",
+                generator.source
+            );
+        }
+    }
+    Ok(pages)
+}
+
+/// The records in a parsed data file: CSV rows keyed by header, or the
+/// objects in the array at `key` (the whole file by default).
+fn records(
+    value: Value,
+    format: DataFormat,
+    key: Option<&str>,
+) -> anyhow::Result<Vec<serde_json::Map<String, Value>>> {
+    if format == DataFormat::Csv {
+        let headers: Vec<String> = serde_json::from_value(value["headers"].clone())?;
+        let rows: Vec<Vec<String>> = serde_json::from_value(value["records"].clone())?;
+        return Ok(rows
+            .into_iter()
+            .map(|row| {
+                headers
+                    .iter()
+                    .cloned()
+                    .zip(row.into_iter().map(Value::String))
+                    .collect()
+            })
+            .collect());
+    }
+
+    let mut value = value;
+    for part in key.into_iter().flat_map(|key| key.split('.')) {
+        value = match value {
+            Value::Object(mut map) => map.remove(part),
+            _ => None,
+        }
+        .ok_or_else(|| {
+            anyhow::anyhow!("no `{}` key holding the records", key.unwrap_or_default())
+        })?;
+    }
+    match value {
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Object(map) => Ok(map),
+                other => Err(anyhow::anyhow!(
+                    "expected each record to be a table of fields, found `{other}`"
+                )),
+            })
+            .collect(),
+        _ => Err(anyhow::anyhow!(
+            "expected an array of records (set `key` to the array's key)"
+        )),
+    }
+}
+
+/// Taxonomy terms from a record field: an array, or a comma-separated string.
+fn terms(value: &Value) -> toml::Value {
+    let terms: Vec<String> = match value {
+        Value::Array(items) => items
+            .iter()
+            .map(|v| match v {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect(),
+        Value::String(s) => s
+            .split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .map(String::from)
+            .collect(),
+        other => vec![other.to_string()],
+    };
+    toml::Value::Array(terms.into_iter().map(toml::Value::String).collect())
+}
+
+/// Convert a record value to TOML, dropping nulls (which TOML cannot hold).
+fn json_to_toml(value: &Value) -> Option<toml::Value> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => toml::Value::Boolean(*b),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => toml::Value::Integer(i),
+            None => toml::Value::Float(n.as_f64().unwrap_or_default()),
+        },
+        Value::String(s) => toml::Value::String(s.clone()),
+        Value::Array(items) => toml::Value::Array(items.iter().filter_map(json_to_toml).collect()),
+        Value::Object(map) => toml::Value::Table(
+            map.iter()
+                .filter_map(|(k, v)| Some((k.clone(), json_to_toml(v)?)))
+                .collect(),
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn site(config: &str, source: (&str, &str)) -> (TempDir, Config) {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(
+            tmp.path().join("config.toml"),
+            format!("base_url = \"https://example.com\"\n{config}"),
+        )
+        .unwrap();
+        let path = tmp.path().join(source.0);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, source.1).unwrap();
+        let config = Config::load(tmp.path()).unwrap();
+        (tmp, config)
+    }
+
+    fn generate(config: &str, source: (&str, &str)) -> anyhow::Result<HashMap<String, Page>> {
+        let (tmp, config) = site(config, source);
+        generate_pages(tmp.path(), &config.generators[0], &config)
+    }
+
+    #[test]
+    fn test_generate_pages_from_csv() {
+        let pages = generate(
+            "[[generators]]\nsource = \"data/products.csv\"\nsection = \"products\"\n\
+             template = \"product.html\"\nslug = \"sku\"\ntitle = \"name\"\n",
+            (
+                "data/products.csv",
+                "sku,name,price,tags,content\nAB-1,Anvil,9.99,\"tools, heavy\",Drops **fast**.\n\
+                 CD 2,Rocket Skates,120,,\n",
+            ),
+        )
+        .unwrap();
+        assert_eq!(pages.len(), 2);
+
+        let anvil = &pages["products/ab-1.md"];
+        assert_eq!(anvil.title, "Anvil");
+        assert_eq!(anvil.path, "/products/ab-1/");
+        assert_eq!(anvil.permalink, "https://example.com/products/ab-1/");
+        assert_eq!(anvil.template.as_deref(), Some("product.html"));
+        assert_eq!(anvil.raw_content, "Drops **fast**.");
+        assert_eq!(anvil.extra["price"], "9.99");
+        assert_eq!(anvil.taxonomies["tags"], ["tools", "heavy"]);
+        assert_eq!(anvil.lang, "en");
+
+        let skates = &pages["products/cd-2.md"];
+        assert_eq!(skates.slug, "cd-2");
+        assert!(!skates.taxonomies.contains_key("tags"));
+    }
+
+    #[test]
+    fn test_generate_pages_from_nested_key() {
+        let pages = generate(
+            "[[generators]]\nsource = \"data/catalog.toml\"\nkey = \"catalog.items\"\n",
+            (
+                "data/catalog.toml",
+                "[[catalog.items]]\ntitle = \"First Post\"\ndate = 2024-03-01\n\
+                 description = \"One\"\ntags = [\"a\"]\nstock = { count = 3 }\n",
+            ),
+        )
+        .unwrap();
+        let page = &pages["first-post.md"];
+        assert_eq!(page.path, "/first-post/");
+        assert_eq!(page.date.as_deref(), Some("2024-03-01"));
+        assert_eq!(page.description.as_deref(), Some("One"));
+        assert_eq!(page.taxonomies["tags"], ["a"]);
+        assert_eq!(page.extra["stock"]["count"], 3);
+    }
+
+    #[test]
+    fn test_generate_pages_from_json_array() {
+        let pages = generate(
+            "[[generators]]\nsource = \"data/people.json\"\nsection = \"/team/\"\n",
+            (
+                "data/people.json",
+                r#"[{"title": "Ada", "slug": "ada", "role": null}, {"title": "Grace"}]"#,
+            ),
+        )
+        .unwrap();
+        let mut keys: Vec<_> = pages.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["team/ada.md", "team/grace.md"]);
+        assert!(pages["team/ada.md"].extra.get("role").is_none());
+    }
+
+    #[test]
+    fn test_generate_pages_errors() {
+        let config = "[[generators]]\nsource = \"data/items.json\"\n";
+        let err = |json: &str| {
+            generate(config, ("data/items.json", json))
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(err(r#"{"items": []}"#).contains("expected an array of records"));
+        assert!(err(r#"[1]"#).contains("table of fields"));
+        assert!(err(r#"[{"price": 1}]"#).contains("record 1 has no `slug` or `title`"));
+        assert!(err(r#"[{"title": "A"}, {"slug": "a"}]"#).contains("more than one record"));
+
+        let missing = generate(
+            "[[generators]]\nsource = \"data/items.json\"\nkey = \"products\"\n",
+            ("data/items.json", r#"{"items": []}"#),
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(missing.contains("no `products` key"), "{missing}");
+    }
+}
@@ -0,0 +1,262 @@
+//! Syntax highlighting with syntect: the bundled grammars and themes, plus
+//! a site's own from `syntaxes/` (`.sublime-syntax`) and `highlight_themes/`
+//! (`.tmTheme`).
+//!
+//! Code is highlighted with inline styles from one theme, or, with
+//! `highlight_theme = "css"`, with classes that the generated `syntax.css`
+//! colors in a light and a dark theme.
+
+use std::path::Path;
+use std::sync::LazyLock;
+
+use syntect::highlighting::ThemeSet;
+use syntect::html::{
+    ClassStyle, ClassedHTMLGenerator, css_for_theme_with_class_style, highlighted_html_for_string,
+};
+use syntect::parsing::{SyntaxDefinition, SyntaxSet, SyntaxSetBuilder};
+use syntect::util::LinesWithEndings;
+
+use crate::content::escape_xml;
+
+/// `highlight_theme` value for class-based highlighting.
+pub const CSS_THEME: &str = "css";
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+pub const DEFAULT_LIGHT_THEME: &str = "InspiredGitHub";
+/// Stylesheet written for `highlight_theme = "css"`.
+pub const STYLESHEET: &str = "syntax.css";
+/// The stylesheet's name in sites made before it was generated, when the
+/// built-in theme linked a hand-written one.
+pub const LEGACY_STYLESHEET: &str = "syntax-dark.css";
+/// Directory of a site's `.sublime-syntax` grammars.
+pub const SYNTAXES_DIR: &str = "syntaxes";
+/// Directory of a site's `.tmTheme` themes.
+pub const THEMES_DIR: &str = "highlight_themes";
+
+/// Classes are prefixed so they cannot clash with a theme's own.
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed { prefix: "z-" };
+/// Selectors for the dark and light variants, following the themes'
+/// `data-theme="light"` switch.
+const DARK_SCOPE: &str = ":root:not([data-theme=\"light\"])";
+const LIGHT_SCOPE: &str = ":root[data-theme=\"light\"]";
+
+/// The bundled grammars and themes.
+pub(crate) static BUNDLED: LazyLock<Highlighter> = LazyLock::new(|| Highlighter {
+    syntaxes: bundled_syntaxes().build(),
+    themes: ThemeSet::load_defaults(),
+});
+
+/// Grammars and themes to highlight with.
+#[derive(Debug)]
+pub struct Highlighter {
+    syntaxes: SyntaxSet,
+    themes: ThemeSet,
+}
+
+impl Highlighter {
+    /// The bundled grammars and themes plus those in the site's `syntaxes/`
+    /// and `highlight_themes/` directories, or `None` if it has neither.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a grammar or theme cannot be loaded.
+    pub fn load(root: &Path) -> anyhow::Result<Option<Self>> {
+        let syntaxes_dir = root.join(SYNTAXES_DIR);
+        let themes_dir = root.join(THEMES_DIR);
+        if !syntaxes_dir.is_dir() && !themes_dir.is_dir() {
+            return Ok(None);
+        }
+
+        let syntaxes = if syntaxes_dir.is_dir() {
+            let mut builder = bundled_syntaxes();
+            builder.add_from_folder(&syntaxes_dir, true).map_err(|e| {
+                anyhow::anyhow!(
+                    "failed to load syntaxes from {}: {e}",
+                    syntaxes_dir.display()
+                )
+            })?;
+            builder.build()
+        } else {
+            BUNDLED.syntaxes.clone()
+        };
+        let mut themes = ThemeSet::load_defaults();
+        if themes_dir.is_dir() {
+            themes.add_from_folder(&themes_dir).map_err(|e| {
+                anyhow::anyhow!(
+                    "failed to load highlight themes from {}: {e}",
+                    themes_dir.display()
+                )
+            })?;
+        }
+        Ok(Some(Self { syntaxes, themes }))
+    }
+
+    /// `code` in `lang` as a highlighted `<pre>`, styled with `theme` (or
+    /// classes, for [`CSS_THEME`]). An unknown theme falls back to the
+    /// default; `None` if highlighting fails.
+    pub(crate) fn highlight(&self, code: &str, lang: &str, theme: Option<&str>) -> Option<String> {
+        let ss = &self.syntaxes;
+        let syntax = ss
+            .find_syntax_by_token(lang)
+            .unwrap_or_else(|| ss.find_syntax_plain_text());
+        let theme_name = theme.unwrap_or(DEFAULT_THEME);
+
+        if theme_name == CSS_THEME {
+            let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, ss, CLASS_STYLE);
+            for line in LinesWithEndings::from(code) {
+                generator
+                    .parse_html_for_line_which_includes_newline(line)
+                    .ok()?;
+            }
+            return Some(format!(
+                "<pre class=\"z-code\"><code class=\"language-{}\">{}</code></pre>",
+                escape_xml(lang),
+                generator.finalize()
+            ));
+        }
+
+        let theme = self
+            .themes
+            .themes
+            .get(theme_name)
+            .unwrap_or(&self.themes.themes[DEFAULT_THEME]);
+        highlighted_html_for_string(code, ss, syntax, theme).ok()
+    }
+
+    /// The stylesheet for class-based highlighting: `dark` colors by
+    /// default and `light` colors under `data-theme="light"`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either theme does not exist.
+    pub fn stylesheet(&self, light: &str, dark: &str) -> anyhow::Result<String> {
+        let mut css = String::new();
+        for (name, scope) in [(dark, DARK_SCOPE), (light, LIGHT_SCOPE)] {
+            let theme = self.themes.themes.get(name).ok_or_else(|| {
+                let mut available: Vec<&str> =
+                    self.themes.themes.keys().map(String::as_str).collect();
+                available.sort_unstable();
+                anyhow::anyhow!(
+                    "unknown highlight theme \"{name}\" (available: {})",
+                    available.join(", ")
+                )
+            })?;
+            let theme_css = css_for_theme_with_class_style(theme, CLASS_STYLE)
+                .map_err(|e| anyhow::anyhow!("failed to generate CSS for \"{name}\": {e}"))?;
+            css.push_str(&scope_css(&theme_css, scope));
+        }
+        Ok(css)
+    }
+}
+
+/// syntect's default grammars plus the embedded TOML grammar.
+fn bundled_syntaxes() -> SyntaxSetBuilder {
+    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
+    // Add TOML syntax (not in syntect's defaults)
+    if let Ok(toml_syn) = SyntaxDefinition::load_from_str(
+        include_str!("../syntaxes/TOML.sublime-syntax"),
+        true,
+        Some("TOML"),
+    ) {
+        builder.add(toml_syn);
+    }
+    builder
+}
+
+/// Prefix every selector of syntect's generated `css` with `scope`.
+fn scope_css(css: &str, scope: &str) -> String {
+    let mut out = String::with_capacity(css.len() * 2);
+    for line in css.lines() {
+        match line.strip_suffix(" {") {
+            Some(selectors) if selectors.starts_with('.') => {
+                let scoped: Vec<String> = selectors
+                    .split(", ")
+                    .map(|selector| format!("{scope} {selector}"))
+                    .collect();
+                out.push_str(&scoped.join(", "));
+                out.push_str(" {");
+            }
+            _ => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_toml_syntax_available() {
+        let ss = &BUNDLED.syntaxes;
+        assert!(
+            ss.find_syntax_by_token("toml").is_some(),
+            "TOML syntax should be available for highlighting"
+        );
+    }
+
+    #[test]
+    fn test_load_site_syntaxes_and_themes() {
+        let tmp = TempDir::new().unwrap();
+        assert!(Highlighter::load(tmp.path()).unwrap().is_none());
+
+        std::fs::create_dir_all(tmp.path().join(SYNTAXES_DIR)).unwrap();
+        std::fs::write(
+            tmp.path().join(SYNTAXES_DIR).join("Zed.sublime-syntax"),
+            "%YAML 1.2\n---\nname: Zed\nfile_extensions: [zed]\nscope: source.zed\n\
+             contexts:\n  main:\n    - match: '\\bzap\\b'\n      scope: keyword.control.zed\n",
+        )
+        .unwrap();
+        std::fs::create_dir_all(tmp.path().join(THEMES_DIR)).unwrap();
+        std::fs::write(
+            tmp.path().join(THEMES_DIR).join("Plain.tmTheme"),
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<plist version="1.0"><dict>
+<key>name</key><string>Plain</string>
+<key>settings</key><array>
+<dict><key>settings</key><dict><key>foreground</key><string>#111111</string><key>background</key><string>#fefefe</string></dict></dict>
+<dict><key>scope</key><string>keyword</string><key>settings</key><dict><key>foreground</key><string>#aa0000</string></dict></dict>
+</array></dict></plist>"#,
+        )
+        .unwrap();
+        let highlighter = Highlighter::load(tmp.path()).unwrap().unwrap();
+
+        let html = highlighter
+            .highlight("zap it\n", "zed", Some("Plain"))
+            .unwrap();
+        assert!(html.contains("<span style=\"color:#aa0000;\">zap</span>"));
+        // Bundled grammars stay available
+        assert!(highlighter.syntaxes.find_syntax_by_token("toml").is_some());
+
+        let html = highlighter
+            .highlight("zap it\n", "zed", Some(CSS_THEME))
+            .unwrap();
+        assert!(html.starts_with("<pre class=\"z-code\"><code class=\"language-zed\">"));
+        assert!(html.contains("<span class=\"z-keyword z-control z-zed\">zap</span>"));
+
+        let css = highlighter.stylesheet("Plain", DEFAULT_THEME).unwrap();
+        assert!(css.contains(":root[data-theme=\"light\"] .z-keyword {\n color: #aa0000;"));
+        assert!(css.contains(":root:not([data-theme=\"light\"]) .z-code {"));
+        assert!(
+            highlighter
+                .stylesheet("Missing", DEFAULT_THEME)
+                .unwrap_err()
+                .to_string()
+                .contains("unknown highlight theme \"Missing\"")
+        );
+    }
+
+    #[test]
+    fn test_load_invalid_syntax_errors() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::create_dir_all(tmp.path().join(SYNTAXES_DIR)).unwrap();
+        std::fs::write(
+            tmp.path().join(SYNTAXES_DIR).join("Bad.sublime-syntax"),
+            "not: [valid",
+        )
+        .unwrap();
+        let err = Highlighter::load(tmp.path()).unwrap_err().to_string();
+        assert!(err.contains("failed to load syntaxes"));
+    }
+}
@@ -0,0 +1,873 @@
+//! Image processing: resized and converted variants of local images, and
+//! responsive `<img>` tags that offer them through `srcset`.
+//!
+//! Variants are named by a hash of the source image and the processing
+//! parameters, kept in `.zorto/cache/images/` between builds, and copied to
+//! `processed_images/` in the output directory.
+
+use std::collections::BTreeSet;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use image::DynamicImage;
+use image::imageops::FilterType;
+use regex::Regex;
+use sha2::{Digest, Sha256};
+
+use crate::cache;
+use crate::config::{Config, ImageFormat, ImagesConfig};
+use crate::content::{escape_html, unescape_html};
+
+/// Output directory of processed images, relative to the site output.
+const OUTPUT_DIR: &str = "processed_images";
+
+/// Directory of processed images inside the cache directory.
+const CACHE_DIR: &str = "images";
+
+/// rav1e speed preset for AVIF (0 slowest to 10 fastest).
+const AVIF_SPEED: u8 = 8;
+
+static IMG_TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)<img\b[^>]*>").unwrap());
+
+static ATTR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"([a-zA-Z_:][-a-zA-Z0-9_:.]*)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#)
+        .unwrap()
+});
+
+impl ImageFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "jpeg" | "jpg" => Some(ImageFormat::Jpeg),
+            "png" => Some(ImageFormat::Png),
+            "webp" => Some(ImageFormat::Webp),
+            "avif" => Some(ImageFormat::Avif),
+            _ => None,
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::Png => "png",
+            ImageFormat::Webp => "webp",
+            ImageFormat::Avif => "avif",
+        }
+    }
+
+    fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Png => "image/png",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Avif => "image/avif",
+        }
+    }
+}
+
+/// How [`ImageProcessor::resize_image`] fits an image to the requested size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ResizeOp {
+    /// Exactly `width` × `height`, ignoring the aspect ratio.
+    Scale,
+    /// `width` wide, keeping the aspect ratio.
+    FitWidth,
+    /// `height` high, keeping the aspect ratio.
+    FitHeight,
+    /// Within `width` × `height`, keeping the aspect ratio. Smaller images
+    /// keep their size.
+    Fit,
+    /// Covering `width` × `height`, then cropped to it around the center.
+    Fill,
+}
+
+impl ResizeOp {
+    pub(crate) fn from_name(name: &str) -> anyhow::Result<Self> {
+        Ok(match name {
+            "scale" => ResizeOp::Scale,
+            "fit_width" => ResizeOp::FitWidth,
+            "fit_height" => ResizeOp::FitHeight,
+            "fit" => ResizeOp::Fit,
+            "fill" => ResizeOp::Fill,
+            _ => {
+                anyhow::bail!("unknown op `{name}`: use scale, fit_width, fit_height, fit or fill")
+            }
+        })
+    }
+
+    /// The size `width` × `height` of the result for an image of
+    /// `orig_width` × `orig_height`.
+    fn target_size(
+        self,
+        width: Option<u32>,
+        height: Option<u32>,
+        orig_width: u32,
+        orig_height: u32,
+    ) -> anyhow::Result<(u32, u32)> {
+        let scaled = |size: u32, num: u32, den: u32| {
+            ((size as f64 * num as f64 / den as f64).round() as u32).max(1)
+        };
+        let both = |op: &str| match (width, height) {
+            (Some(w), Some(h)) => Ok((w, h)),
+            _ => Err(anyhow::anyhow!("op `{op}` requires `width` and `height`")),
+        };
+        let size = match self {
+            ResizeOp::Scale => both("scale")?,
+            ResizeOp::Fill => both("fill")?,
+            ResizeOp::FitWidth => {
+                let w = width.ok_or_else(|| anyhow::anyhow!("op `fit_width` requires `width`"))?;
+                (w, scaled(orig_height, w, orig_width))
+            }
+            ResizeOp::FitHeight => {
+                let h =
+                    height.ok_or_else(|| anyhow::anyhow!("op `fit_height` requires `height`"))?;
+                (scaled(orig_width, h, orig_height), h)
+            }
+            ResizeOp::Fit => {
+                let (w, h) = both("fit")?;
+                if orig_width <= w && orig_height <= h {
+                    (orig_width, orig_height)
+                } else if orig_width as u64 * h as u64 > orig_height as u64 * w as u64 {
+                    (w, scaled(orig_height, w, orig_width))
+                } else {
+                    (scaled(orig_width, h, orig_height), h)
+                }
+            }
+        };
+        if size.0 == 0 || size.1 == 0 {
+            anyhow::bail!("`width` and `height` must be greater than 0");
+        }
+        Ok(size)
+    }
+}
+
+/// A processed image in the cache, and where it goes in the output.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct ProcessedImage {
+    /// Path relative to the output directory, e.g.
+    /// `processed_images/photo-3f2a9c0e1b7d4a65.webp`.
+    pub(crate) path: String,
+    /// The file in `.zorto/cache/images/`.
+    pub(crate) cached: PathBuf,
+}
+
+/// A processed image and its size.
+#[derive(Debug, Clone)]
+pub(crate) struct Resized {
+    pub(crate) image: ProcessedImage,
+    pub(crate) url: String,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+/// Images that [`ImageProcessor::responsive_html`] used and produced.
+#[derive(Debug, Default)]
+pub(crate) struct ResponsiveHtml {
+    pub(crate) html: String,
+    pub(crate) images: Vec<ProcessedImage>,
+    /// Canonical paths of the source images.
+    pub(crate) sources: BTreeSet<PathBuf>,
+}
+
+/// A source image, decoded on first use.
+struct Source {
+    path: PathBuf,
+    /// File stem used in variant names.
+    stem: String,
+    /// Hash of the file's contents.
+    hash: String,
+    width: u32,
+    height: u32,
+    /// The source's format, if variants can keep it.
+    format: Option<ImageFormat>,
+    decoded: Option<DynamicImage>,
+}
+
+impl Source {
+    fn open(path: &Path) -> anyhow::Result<Self> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| anyhow::anyhow!("cannot read {}: {e}", path.display()))?;
+        let reader = image::ImageReader::new(Cursor::new(&bytes)).with_guessed_format()?;
+        let format = match reader.format() {
+            Some(image::ImageFormat::Jpeg) => Some(ImageFormat::Jpeg),
+            Some(image::ImageFormat::Png) => Some(ImageFormat::Png),
+            Some(image::ImageFormat::WebP) => Some(ImageFormat::Webp),
+            _ => None,
+        };
+        let (width, height) = reader
+            .into_dimensions()
+            .map_err(|e| anyhow::anyhow!("{}: {e}", path.display()))?;
+        let stem = path
+            .file_stem()
+            .map(|s| slug::slugify(s.to_string_lossy()))
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| "image".to_string());
+        Ok(Source {
+            path: path.to_path_buf(),
+            stem,
+            hash: format!("{:x}", Sha256::digest(&bytes)),
+            width,
+            height,
+            format,
+            decoded: None,
+        })
+    }
+
+    fn decoded(&mut self) -> anyhow::Result<&DynamicImage> {
+        if self.decoded.is_none() {
+            let image = image::ImageReader::open(&self.path)?
+                .with_guessed_format()?
+                .decode()
+                .map_err(|e| anyhow::anyhow!("{}: {e}", self.path.display()))?;
+            self.decoded = Some(image);
+        }
+        Ok(self.decoded.as_ref().expect("decoded above"))
+    }
+}
+
+/// Resizes and converts images for the `resize_image()` and
+/// `responsive_image()` template functions and for `[images] responsive`.
+#[derive(Debug, Clone)]
+pub(crate) struct ImageProcessor {
+    root: PathBuf,
+    sandbox: PathBuf,
+    base_url: String,
+    config: ImagesConfig,
+    /// What the template functions used and produced so far.
+    used: Arc<Mutex<(BTreeSet<ProcessedImage>, BTreeSet<PathBuf>)>>,
+}
+
+impl ImageProcessor {
+    /// A processor for images inside `sandbox`, caching under `root`.
+    pub(crate) fn new(root: &Path, sandbox: &Path, config: &Config) -> Self {
+        ImageProcessor {
+            root: root.to_path_buf(),
+            sandbox: sandbox.to_path_buf(),
+            base_url: config.base_url.clone(),
+            config: config.images.clone(),
+            used: Default::default(),
+        }
+    }
+
+    /// Resolve an image path from a template: relative to `static/`,
+    /// `content/` or the site root, in that order, or to `content/` with an
+    /// `@/` prefix. Returns the canonical path, and the URL the image is
+    /// published at if it is a static file or content asset.
+    fn resolve(&self, path: &str) -> anyhow::Result<(PathBuf, Option<String>)> {
+        let candidates: Vec<(PathBuf, bool)> = match path.strip_prefix("@/") {
+            Some(rel) => vec![(self.root.join("content").join(rel), true)],
+            None => {
+                let rel = path.trim_start_matches('/');
+                vec![
+                    (self.root.join("static").join(rel), true),
+                    (self.root.join("content").join(rel), true),
+                    (self.root.join(rel), false),
+                ]
+            }
+        };
+        let (file, published) = candidates
+            .into_iter()
+            .find(|(file, _)| file.is_file())
+            .ok_or_else(|| anyhow::anyhow!("image not found: {path}"))?;
+        let canonical = self.check_sandbox(&file, path)?;
+        let url = published.then(|| {
+            let rel = path.strip_prefix("@/").unwrap_or(path);
+            format!("{}/{}", self.base_url, rel.trim_start_matches('/'))
+        });
+        Ok((canonical, url))
+    }
+
+    fn check_sandbox(&self, file: &Path, path: &str) -> anyhow::Result<PathBuf> {
+        let canonical = file
+            .canonicalize()
+            .map_err(|e| anyhow::anyhow!("cannot resolve {}: {e}", file.display()))?;
+        let canonical_sandbox = self
+            .sandbox
+            .canonicalize()
+            .map_err(|e| anyhow::anyhow!("cannot resolve sandbox root: {e}"))?;
+        if !canonical.starts_with(&canonical_sandbox) {
+            anyhow::bail!(
+                "path '{path}' escapes sandbox '{}'. \
+                 Pass --sandbox <dir> to widen the boundary, or move the file inside --root.",
+                self.sandbox.display()
+            );
+        }
+        Ok(canonical)
+    }
+
+    /// Resize and convert the image at `path` (see [`resolve`](Self::resolve))
+    /// for the `resize_image()` template function. `format` is `None` to
+    /// keep the source's format (PNG for formats variants cannot be in).
+    /// Returns the result and the source's size.
+    pub(crate) fn resize_image(
+        &self,
+        path: &str,
+        op: ResizeOp,
+        width: Option<u32>,
+        height: Option<u32>,
+        format: Option<&str>,
+        quality: Option<u8>,
+    ) -> anyhow::Result<(Resized, (u32, u32))> {
+        let format = match format {
+            None | Some("auto") => None,
+            Some(name) => Some(ImageFormat::from_name(name).ok_or_else(|| {
+                anyhow::anyhow!("unknown format `{name}`: use auto, jpeg, png, webp or avif")
+            })?),
+        };
+        let quality = quality.unwrap_or(self.config.quality);
+        if !(1..=100).contains(&quality) {
+            anyhow::bail!("quality must be between 1 and 100");
+        }
+        let (file, _) = self.resolve(path)?;
+        let mut source = Source::open(&file)?;
+        if format == Some(ImageFormat::Webp) && source.format == Some(ImageFormat::Jpeg) {
+            anyhow::bail!(
+                "{path}: cannot convert a JPEG to WebP; Zorto only writes lossless WebP, \
+                 which is usually larger than the JPEG. Use avif or jpeg"
+            );
+        }
+        let format = format.or(source.format).unwrap_or(ImageFormat::Png);
+        let resized = self.variant(&mut source, op, width, height, format, quality)?;
+        self.record([resized.image.clone()], file);
+        Ok((resized, (source.width, source.height)))
+    }
+
+    /// An `<img>` tag for the image at `path` (see [`resolve`](Self::resolve))
+    /// with responsive variants, for the `responsive_image()` template
+    /// function. `attrs` are extra attributes, already escaped.
+    pub(crate) fn responsive_image(&self, path: &str, attrs: &str) -> anyhow::Result<String> {
+        let (file, url) = self.resolve(path)?;
+        let mut source = Source::open(&file)?;
+        let mut images = Vec::new();
+        let src = match url {
+            Some(url) => url,
+            None => {
+                // Not published as is: publish a copy
+                let format = source.format.unwrap_or(ImageFormat::Png);
+                let (width, height) = (source.width, source.height);
+                let copy = self.variant(
+                    &mut source,
+                    ResizeOp::Scale,
+                    Some(width),
+                    Some(height),
+                    format,
+                    self.config.quality,
+                )?;
+                images.push(copy.image);
+                copy.url
+            }
+        };
+        let tag = format!("<img src=\"{}\"{attrs}>", escape_html(&src));
+        let html = self.responsive_tag(&tag, &mut source, &mut images)?;
+        self.record(images, file);
+        Ok(html)
+    }
+
+    fn record(&self, images: impl IntoIterator<Item = ProcessedImage>, source: PathBuf) {
+        let mut used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        used.0.extend(images);
+        used.1.insert(source);
+    }
+
+    /// Images the template functions produced so far.
+    pub(crate) fn processed(&self) -> Vec<ProcessedImage> {
+        let used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        used.0.iter().cloned().collect()
+    }
+
+    /// Canonical paths of the images the template functions read so far.
+    pub(crate) fn sources(&self) -> BTreeSet<PathBuf> {
+        let used = self.used.lock().unwrap_or_else(|e| e.into_inner());
+        used.1.clone()
+    }
+
+    /// Give the local images in `html` responsive variants (`[images]
+    /// responsive`). Relative `src`s are relative to `dir`; root-relative
+    /// ones are looked up in `static/`, then `content/`. Images that already
+    /// have a `srcset`, SVGs, GIFs and images that are not local files are
+    /// left alone.
+    pub(crate) fn responsive_html(&self, html: &str, dir: &Path) -> ResponsiveHtml {
+        let mut out = ResponsiveHtml::default();
+        let mut last = 0;
+        for tag in IMG_TAG_RE.find_iter(html) {
+            let attrs = attributes(tag.as_str());
+            if attr(&attrs, "srcset").is_some() {
+                continue;
+            }
+            let Some(file) = attr(&attrs, "src").and_then(|src| self.local_file(src, dir)) else {
+                continue;
+            };
+            let mut images = Vec::new();
+            let result = Source::open(&file).and_then(|mut source| match source.format {
+                Some(_) => self
+                    .responsive_tag(tag.as_str(), &mut source, &mut images)
+                    .map(Some),
+                None => Ok(None),
+            });
+            match result {
+                Ok(Some(responsive)) => {
+                    out.html.push_str(&html[last..tag.start()]);
+                    out.html.push_str(&responsive);
+                    last = tag.end();
+                    out.images.extend(images);
+                    out.sources.insert(file);
+                }
+                Ok(None) => {}
+                Err(e) => eprintln!("warning: image {}: {e}", file.display()),
+            }
+        }
+        out.html.push_str(&html[last..]);
+        out
+    }
+
+    /// The canonical path of the image a `src` in content points at, if it
+    /// is a local file inside the sandbox.
+    fn local_file(&self, src: &str, dir: &Path) -> Option<PathBuf> {
+        let src = unescape_html(src);
+        let src = src.strip_prefix(&self.base_url).unwrap_or(&src);
+        if src.is_empty()
+            || src.starts_with("//")
+            || src.starts_with("data:")
+            || src.contains("://")
+        {
+            return None;
+        }
+        let src = src.split(['?', '#']).next().unwrap_or_default();
+        let file = match src.strip_prefix('/') {
+            Some(rel) => [self.root.join("static"), self.root.join("content")]
+                .into_iter()
+                .map(|dir| dir.join(rel))
+                .find(|file| file.is_file())?,
+            None => dir.join(src),
+        };
+        if !file.is_file() {
+            return None;
+        }
+        self.check_sandbox(&file, src).ok()
+    }
+
+    /// `tag`, an `<img>` of `source`, with `srcset` and `sizes` offering
+    /// the configured widths below the source's own, and `width` and
+    /// `height` unless it sets either. Variants in the configured extra
+    /// formats go in `<source>`s of a `<picture>` around it.
+    fn responsive_tag(
+        &self,
+        tag: &str,
+        source: &mut Source,
+        images: &mut Vec<ProcessedImage>,
+    ) -> anyhow::Result<String> {
+        let attrs = attributes(tag);
+        let src = attr(&attrs, "src").unwrap_or_default().to_string();
+        let sizes = attr(&attrs, "sizes")
+            .map(unescape_html)
+            .unwrap_or_else(|| self.config.sizes.clone());
+        let widths: BTreeSet<u32> = self
+            .config
+            .widths
+            .iter()
+            .copied()
+            .filter(|&w| w < source.width)
+            .collect();
+        let own_format = source.format.unwrap_or(ImageFormat::Png);
+        let (own_width, own_height) = (source.width, source.height);
+        let quality = self.config.quality;
+
+        let mut srcset = |format: ImageFormat, images: &mut Vec<ProcessedImage>| {
+            let mut candidates = Vec::new();
+            for &width in &widths {
+                let variant = self.variant(
+                    source,
+                    ResizeOp::FitWidth,
+                    Some(width),
+                    None,
+                    format,
+                    quality,
+                )?;
+                candidates.push(format!("{} {width}w", escape_html(&variant.url)));
+                images.push(variant.image);
+            }
+            let full = if format == own_format {
+                src.clone()
+            } else {
+                let variant = self.variant(
+                    source,
+                    ResizeOp::Scale,
+                    Some(own_width),
+                    Some(own_height),
+                    format,
+                    quality,
+                )?;
+                images.push(variant.image);
+                escape_html(&variant.url)
+            };
+            candidates.push(format!("{full} {own_width}w"));
+            anyhow::Ok(candidates.join(", "))
+        };
+
+        let mut extra = String::new();
+        if !widths.is_empty() {
+            extra.push_str(&format!(" srcset=\"{}\"", srcset(own_format, images)?));
+            if attr(&attrs, "sizes").is_none() {
+                extra.push_str(&format!(" sizes=\"{}\"", escape_html(&sizes)));
+            }
+        }
+        if attr(&attrs, "width").is_none() && attr(&attrs, "height").is_none() {
+            extra.push_str(&format!(" width=\"{own_width}\" height=\"{own_height}\""));
+        }
+        let (head, close) = match tag.strip_suffix("/>") {
+            Some(head) => (head.trim_end(), " />"),
+            None => (tag[..tag.len() - 1].trim_end(), ">"),
+        };
+        let img = format!("{head}{extra}{close}");
+
+        let mut sources = String::new();
+        let mut formats = Vec::new();
+        for &format in &self.config.formats {
+            if format != own_format && !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        for format in formats {
+            sources.push_str(&format!(
+                "<source type=\"{}\" srcset=\"{}\" sizes=\"{}\">",
+                format.mime_type(),
+                srcset(format, images)?,
+                escape_html(&sizes)
+            ));
+        }
+        Ok(if sources.is_empty() {
+            img
+        } else {
+            format!("<picture>{sources}{img}</picture>")
+        })
+    }
+
+    /// Resize and convert `source`, reusing the cached result from an
+    /// earlier build if there is one.
+    fn variant(
+        &self,
+        source: &mut Source,
+        op: ResizeOp,
+        width: Option<u32>,
+        height: Option<u32>,
+        format: ImageFormat,
+        quality: u8,
+    ) -> anyhow::Result<Resized> {
+        let (width, height) = op.target_size(width, height, source.width, source.height)?;
+        let key = format!(
+            "{}:{op:?}:{width}x{height}:{format:?}:{quality}",
+            source.hash
+        );
+        let hash = format!("{:x}", Sha256::digest(key.as_bytes()));
+        let name = format!("{}-{}.{}", source.stem, &hash[..16], format.extension());
+        let cached = cache::cache_dir(&self.root).join(CACHE_DIR).join(&name);
+        if !cached.exists() {
+            let image = source.decoded()?;
+            let resized = if (width, height) == (image.width(), image.height()) {
+                image.clone()
+            } else if op == ResizeOp::Fill {
+                image.resize_to_fill(width, height, FilterType::Lanczos3)
+            } else {
+                image.resize_exact(width, height, FilterType::Lanczos3)
+            };
+            let bytes = encode(&resized, format, quality)
+                .map_err(|e| anyhow::anyhow!("{}: {e}", source.path.display()))?;
+            cache::write_atomic(&cached, &bytes)?;
+        }
+        let path = format!("{OUTPUT_DIR}/{name}");
+        Ok(Resized {
+            url: format!("{}/{path}", self.base_url),
+            image: ProcessedImage { path, cached },
+            width,
+            height,
+        })
+    }
+}
+
+/// Encode `image` as `format`.
+fn encode(image: &DynamicImage, format: ImageFormat, quality: u8) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    // Encoders take 8-bit RGB(A); JPEG has no alpha channel.
+    let image = if format == ImageFormat::Jpeg || !image.color().has_alpha() {
+        DynamicImage::ImageRgb8(image.to_rgb8())
+    } else {
+        DynamicImage::ImageRgba8(image.to_rgba8())
+    };
+    match format {
+        ImageFormat::Jpeg => image.write_with_encoder(
+            image::codecs::jpeg::JpegEncoder::new_with_quality(&mut bytes, quality),
+        )?,
+        ImageFormat::Png => {
+            image.write_with_encoder(image::codecs::png::PngEncoder::new(&mut bytes))?
+        }
+        ImageFormat::Webp => {
+            image.write_with_encoder(image::codecs::webp::WebPEncoder::new_lossless(&mut bytes))?
+        }
+        ImageFormat::Avif => {
+            image.write_with_encoder(image::codecs::avif::AvifEncoder::new_with_speed_quality(
+                &mut bytes, AVIF_SPEED, quality,
+            ))?
+        }
+    }
+    Ok(bytes)
+}
+
+/// The attributes of an HTML tag, lowercased names with their raw values.
+fn attributes(tag: &str) -> Vec<(String, String)> {
+    let inner = tag
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .trim_end_matches('/');
+    let inner = inner.get(3..).unwrap_or_default();
+    ATTR_RE
+        .captures_iter(inner)
+        .map(|caps| {
+            let value = caps
+                .get(2)
+                .or_else(|| caps.get(3))
+                .or_else(|| caps.get(4))
+                .map_or("", |m| m.as_str());
+            (caps[1].to_ascii_lowercase(), value.to_string())
+        })
+        .collect()
+}
+
+fn attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, v)| v.as_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write_image(path: &Path, width: u32, height: u32) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        image::RgbImage::from_fn(width, height, |x, y| image::Rgb([x as u8, y as u8, 128]))
+            .save(path)
+            .unwrap();
+    }
+
+    fn processor(root: &Path, images: &str) -> ImageProcessor {
+        std::fs::write(
+            root.join("config.toml"),
+            format!("base_url = \"https://example.com\"\n[images]\n{images}"),
+        )
+        .unwrap();
+        let config = Config::load(root).unwrap();
+        ImageProcessor::new(root, root, &config)
+    }
+
+    #[test]
+    fn test_target_size() {
+        // For a 1000 × 500 image
+        let op_size = |op: ResizeOp, w, h| op.target_size(w, h, 1000, 500);
+        let size = |op, w, h| op_size(op, w, h).unwrap();
+        assert_eq!(size(ResizeOp::Scale, Some(10), Some(30)), (10, 30));
+        assert_eq!(size(ResizeOp::Fill, Some(10), Some(30)), (10, 30));
+        assert_eq!(size(ResizeOp::FitWidth, Some(300), None), (300, 150));
+        assert_eq!(size(ResizeOp::FitHeight, None, Some(100)), (200, 100));
+        assert_eq!(size(ResizeOp::Fit, Some(400), Some(400)), (400, 200));
+        assert_eq!(size(ResizeOp::Fit, Some(1000), Some(100)), (200, 100));
+        assert_eq!(size(ResizeOp::Fit, Some(2000), Some(2000)), (1000, 500));
+        assert!(op_size(ResizeOp::Fill, Some(10), None).is_err());
+        assert!(op_size(ResizeOp::FitWidth, None, Some(10)).is_err());
+        assert!(op_size(ResizeOp::Scale, Some(0), Some(10)).is_err());
+        assert!(ResizeOp::from_name("crop").is_err());
+    }
+
+    #[test]
+    fn test_resize_image() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        write_image(&root.join("static/img/photo.png"), 200, 100);
+        let processor = processor(root, "");
+
+        let (resized, orig) = processor
+            .resize_image(
+                "img/photo.png",
+                ResizeOp::FitWidth,
+                Some(50),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(orig, (200, 100));
+        assert_eq!((resized.width, resized.height), (50, 25));
+        assert!(resized.image.path.starts_with("processed_images/photo-"));
+        assert!(resized.image.path.ends_with(".png"));
+        assert_eq!(
+            resized.url,
+            format!("https://example.com/{}", resized.image.path)
+        );
+        assert_eq!(
+            image::image_dimensions(&resized.image.cached).unwrap(),
+            (50, 25)
+        );
+
+        // Cached: the same parameters reuse the file, others make a new one
+        let modified = std::fs::metadata(&resized.image.cached)
+            .unwrap()
+            .modified()
+            .unwrap();
+        let (again, _) = processor
+            .resize_image(
+                "img/photo.png",
+                ResizeOp::FitWidth,
+                Some(50),
+                None,
+                None,
+                None,
+            )
+            .unwrap();
+        assert_eq!(again.image, resized.image);
+        assert_eq!(
+            std::fs::metadata(&again.image.cached)
+                .unwrap()
+                .modified()
+                .unwrap(),
+            modified
+        );
+        let (filled, _) = processor
+            .resize_image(
+                "img/photo.png",
+                ResizeOp::Fill,
+                Some(30),
+                Some(30),
+                Some("jpeg"),
+                Some(50),
+            )
+            .unwrap();
+        assert!(filled.image.path.ends_with(".jpg"));
+        assert_eq!(
+            image::image_dimensions(&filled.image.cached).unwrap(),
+            (30, 30)
+        );
+
+        let sources = processor.sources();
+        assert_eq!(sources.len(), 1);
+        assert_eq!(processor.processed().len(), 2);
+    }
+
+    #[test]
+    fn test_resize_image_formats() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        write_image(&root.join("content/blog/cover.jpg"), 16, 16);
+        write_image(&root.join("content/blog/icon.png"), 16, 16);
+        let processor = processor(root, "");
+        for (path, format, magic) in [
+            ("@/blog/icon.png", "webp", &b"WEBP"[..]),
+            ("@/blog/cover.jpg", "avif", &b"ftypavif"[..]),
+        ] {
+            let (resized, _) = processor
+                .resize_image(path, ResizeOp::Fit, Some(8), Some(8), Some(format), None)
+                .unwrap();
+            let bytes = std::fs::read(&resized.image.cached).unwrap();
+            assert!(
+                bytes.windows(magic.len()).any(|w| w == magic),
+                "{format} output"
+            );
+        }
+        let err = processor
+            .resize_image(
+                "@/blog/cover.jpg",
+                ResizeOp::Fit,
+                Some(8),
+                Some(8),
+                Some("webp"),
+                None,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("cannot convert a JPEG to WebP"));
+    }
+
+    #[test]
+    fn test_resize_image_errors() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("site");
+        std::fs::create_dir_all(&root).unwrap();
+        write_image(&tmp.path().join("outside.png"), 4, 4);
+        std::fs::write(root.join("broken.png"), "not an image").unwrap();
+        let processor = processor(&root, "");
+        let err = |path: &str, format: Option<&str>| {
+            processor
+                .resize_image(path, ResizeOp::Fit, Some(2), Some(2), format, None)
+                .unwrap_err()
+                .to_string()
+        };
+        assert!(err("missing.png", None).contains("image not found"));
+        assert!(err("../outside.png", None).contains("escapes sandbox"));
+        assert!(err("broken.png", None).contains("broken.png"));
+        assert!(err("broken.png", Some("gif")).contains("unknown format"));
+    }
+
+    #[test]
+    fn test_responsive_html() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        write_image(&root.join("static/img/wide.jpg"), 1000, 500);
+        write_image(&root.join("content/blog/post/small.png"), 300, 200);
+        let processor = processor(root, "responsive = true\nformats = [\"jpeg\", \"png\"]\n");
+
+        let html = r#"<p><img src="/img/wide.jpg" alt="Wide" /></p>
+<img src="small.png" width="150">
+<img src="https://cdn.example.org/x.jpg">
+<img src="data:image/png;base64,abc">
+<img src="/img/missing.jpg">
+<img src="/img/wide.jpg" srcset="a.jpg 1x">"#;
+        let out = processor.responsive_html(html, &root.join("content/blog/post"));
+
+        let (wide, rest) = out.html.split_once('\n').unwrap();
+        assert!(wide.starts_with(r#"<p><picture><source type="image/png" srcset=""#));
+        assert!(wide.contains(r#"<img src="/img/wide.jpg" alt="Wide" srcset=""#));
+        assert!(wide.contains(" 480w, "));
+        assert!(wide.contains(" 960w, /img/wide.jpg 1000w\""));
+        assert!(!wide.contains("1440w"));
+        assert!(wide.contains(r#" sizes="100vw" width="1000" height="500" /></picture></p>"#));
+
+        // No width below the image's own: only the other format is offered,
+        // and a set width stays
+        let (small, rest) = rest.split_once('\n').unwrap();
+        assert!(small.starts_with(r#"<picture><source type="image/jpeg" srcset="https://example.com/processed_images/small-"#));
+        assert!(
+            small.ends_with(
+                r#".jpg 300w" sizes="100vw"><img src="small.png" width="150"></picture>"#
+            )
+        );
+
+        assert!(rest.starts_with(r#"<img src="https://cdn.example.org/x.jpg">"#));
+        assert!(rest.ends_with(
+            r#"<img src="/img/missing.jpg">
+<img src="/img/wide.jpg" srcset="a.jpg 1x">"#
+        ));
+
+        // 2 widths in JPEG, 2 widths and the full size in WebP, and the
+        // small image in WebP
+        assert_eq!(out.images.len(), 6);
+        assert!(out.images.iter().all(|image| image.cached.exists()));
+        assert_eq!(out.sources.len(), 2);
+    }
+
+    #[test]
+    fn test_responsive_image() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path();
+        write_image(&root.join("assets/hero.png"), 600, 300);
+        let processor = processor(root, "widths = [200]\nsizes = \"50vw\"\n");
+
+        // Not under static/ or content/: the full size is published as a copy
+        let html = processor
+            .responsive_image("assets/hero.png", r#" alt="Hero""#)
+            .unwrap();
+        assert!(html.starts_with(r#"<img src="https://example.com/processed_images/hero-"#));
+        assert!(html.contains(r#" alt="Hero" srcset="https://example.com/processed_images/hero-"#));
+        assert!(html.contains(r#".png 200w, https://example.com/processed_images/hero-"#));
+        assert!(html.ends_with(r#".png 600w" sizes="50vw" width="600" height="300">"#));
+        assert_eq!(processor.processed().len(), 2);
+    }
+}
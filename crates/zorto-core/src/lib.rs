@@ -25,13 +25,26 @@
 pub mod cache;
 pub mod config;
 pub mod content;
+pub mod deps;
 pub mod markdown;
 pub mod site;
 pub mod themes;
 
+pub(crate) mod data;
+pub(crate) mod diagrams;
 pub(crate) mod execute;
+pub(crate) mod export;
+pub(crate) mod generators;
+pub(crate) mod highlight;
+pub(crate) mod images;
 pub(crate) mod links;
 pub mod lint;
+pub(crate) mod math;
+pub mod notebook;
+pub(crate) mod parallel;
+pub(crate) mod python_worker;
+pub(crate) mod sandbox;
 pub(crate) mod sass;
+pub mod search;
 pub mod shortcodes;
 pub(crate) mod templates;
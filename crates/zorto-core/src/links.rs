@@ -2,10 +2,14 @@ use regex::Regex;
 use std::collections::HashMap;
 use std::sync::LazyLock;
 
+use crate::config::MarkdownConfig;
 use crate::content::{Page, Section};
+use crate::markdown;
 
 static INTERNAL_LINK_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"@/([^)#\s]+\.md)(#[^)\s]+)?").unwrap());
+static WIKI_LINK_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(!?)\[\[([^\[\]\n]+)\]\]").unwrap());
 
 /// Resolve @/ internal links in raw markdown content.
 /// @/path/to/file.md -> /resolved/url/
@@ -48,6 +52,141 @@ pub fn resolve_internal_links(
     Ok(result)
 }
 
+/// Targets of `[[wiki links]]`: pages and sections by path, file name,
+/// title and slug.
+pub(crate) struct WikiIndex {
+    /// Lowercased paths (without `.md`), file names and titles, in that
+    /// order of precedence.
+    names: HashMap<String, String>,
+    slugs: HashMap<String, String>,
+}
+
+impl WikiIndex {
+    pub(crate) fn new(pages: &HashMap<String, Page>, sections: &HashMap<String, Section>) -> Self {
+        // Sorted, so that the first of several pages with a name wins
+        let mut targets: Vec<(&str, &str, &str, Option<&str>)> = pages
+            .values()
+            .map(|p| {
+                (
+                    p.relative_path.as_str(),
+                    p.title.as_str(),
+                    p.permalink.as_str(),
+                    Some(p.slug.as_str()),
+                )
+            })
+            .chain(sections.values().map(|s| {
+                (
+                    s.relative_path.as_str(),
+                    s.title.as_str(),
+                    s.permalink.as_str(),
+                    None,
+                )
+            }))
+            .collect();
+        targets.sort();
+
+        let mut names = HashMap::new();
+        let mut slugs = HashMap::new();
+        let path_of = |relative_path: &str| {
+            let path = relative_path.trim_end_matches(".md");
+            path.strip_suffix("/_index")
+                .or_else(|| path.strip_suffix("_index"))
+                .unwrap_or(path)
+                .to_lowercase()
+        };
+        for &(relative_path, _, permalink, _) in &targets {
+            names
+                .entry(path_of(relative_path))
+                .or_insert(permalink.to_string());
+        }
+        for &(relative_path, _, permalink, slug) in &targets {
+            let path = path_of(relative_path);
+            let stem = path.rsplit('/').next().unwrap_or(&path).to_string();
+            names.entry(stem.clone()).or_insert(permalink.to_string());
+            let slug = slug.map_or_else(|| slug::slugify(&stem), str::to_string);
+            slugs.entry(slug).or_insert(permalink.to_string());
+        }
+        for &(_, title, permalink, _) in &targets {
+            if !title.is_empty() {
+                names
+                    .entry(title.trim().to_lowercase())
+                    .or_insert(permalink.to_string());
+            }
+        }
+        Self { names, slugs }
+    }
+
+    /// Permalink of the page or section `target` names.
+    fn get(&self, target: &str) -> Option<&str> {
+        let target = target.trim().trim_end_matches(".md").to_lowercase();
+        self.names
+            .get(&target)
+            .or_else(|| self.slugs.get(&slug::slugify(&target)))
+            .map(String::as_str)
+    }
+}
+
+/// Rewrite `[[Page]]`, `[[Page|label]]` and `[[Page#Heading]]` wiki links
+/// in raw markdown as markdown links, skipping code and `![[embeds]]`.
+///
+/// Returns the content and the targets that did not resolve, which are left
+/// as their label text.
+pub(crate) fn resolve_wiki_links(
+    content: &str,
+    index: &WikiIndex,
+    config: &MarkdownConfig,
+) -> (String, Vec<String>) {
+    if !content.contains("[[") {
+        return (content.to_string(), Vec::new());
+    }
+    let code = markdown::code_ranges(content, config);
+    let mut unresolved = Vec::new();
+    let mut out = String::with_capacity(content.len());
+    let mut last = 0;
+    for caps in WIKI_LINK_RE.captures_iter(content) {
+        let all = caps.get(0).unwrap();
+        if !caps[1].is_empty() || code.iter().any(|range| range.contains(&all.start())) {
+            continue;
+        }
+        // `\|` is a pipe escaped inside a table
+        let (target, label) = match caps[2].split_once('|') {
+            Some((target, label)) => (target.trim_end_matches('\\'), Some(label.trim())),
+            None => (&caps[2], None),
+        };
+        let (page, heading) = match target.split_once('#') {
+            Some((page, heading)) => (page.trim(), Some(heading.trim())),
+            None => (target.trim(), None),
+        };
+        let label = label.map_or_else(
+            || match heading {
+                Some(heading) if page.is_empty() => heading.to_string(),
+                Some(heading) => format!("{page} > {heading}"),
+                None => page.to_string(),
+            },
+            str::to_string,
+        );
+        let url = if page.is_empty() {
+            Some("")
+        } else {
+            index.get(page)
+        };
+        out.push_str(&content[last..all.start()]);
+        match url {
+            Some(url) => {
+                let anchor = heading.map_or(String::new(), |h| format!("#{}", slug::slugify(h)));
+                out.push_str(&format!("[{label}]({url}{anchor})"));
+            }
+            None => {
+                unresolved.push(target.trim().to_string());
+                out.push_str(&label);
+            }
+        }
+        last = all.end();
+    }
+    out.push_str(&content[last..]);
+    (out, unresolved)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -136,4 +275,37 @@ mod tests {
                 .contains("unresolved internal link")
         );
     }
+
+    #[test]
+    fn test_resolve_wiki_links() {
+        let base = "https://example.com";
+        let mut pages = HashMap::new();
+        let mut hello = make_page("posts/hello-world.md", base);
+        hello.title = "Hello, World".into();
+        pages.insert("posts/hello-world.md".into(), hello);
+        pages.insert("about.md".into(), make_page("about.md", base));
+        let mut sections = HashMap::new();
+        sections.insert(
+            "posts/_index.md".into(),
+            make_section("posts/_index.md", base),
+        );
+        let index = WikiIndex::new(&pages, &sections);
+
+        let input = "[[hello-world]], [[Hello, World|the post]], [[posts/hello-world#Getting Started]], \
+                     [[About]], [[posts]], [[#Local Heading]] and [[Missing page]].\n\n\
+                     `[[hello-world]]` and ![[diagram.png]]\n\n\
+                     ```toml\n[[taxonomies]]\n```\n";
+        let (output, unresolved) = resolve_wiki_links(input, &index, &MarkdownConfig::default());
+        assert_eq!(
+            output,
+            "[hello-world](https://example.com/posts/hello-world/), \
+             [the post](https://example.com/posts/hello-world/), \
+             [posts/hello-world > Getting Started](https://example.com/posts/hello-world/#getting-started), \
+             [About](https://example.com/about/), [posts](https://example.com/posts/), \
+             [Local Heading](#local-heading) and Missing page.\n\n\
+             `[[hello-world]]` and ![[diagram.png]]\n\n\
+             ```toml\n[[taxonomies]]\n```\n"
+        );
+        assert_eq!(unresolved, ["Missing page"]);
+    }
 }
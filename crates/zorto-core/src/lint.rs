@@ -10,7 +10,7 @@ use std::collections::HashMap;
 use std::path::Path;
 use std::sync::LazyLock;
 
-use crate::content::{Page, Section, section_key_for};
+use crate::content::{Page, Section, page_section_key};
 
 /// A lint warning produced by the linter.
 #[derive(Debug)]
@@ -255,7 +255,7 @@ pub fn lint_frontmatter(
         }
 
         // Date is required for pages in date-sorted sections
-        let section_key = section_key_for(&page.relative_path);
+        let section_key = page_section_key(page, sections);
         let in_date_section = sections
             .get(&section_key)
             .is_some_and(|s| matches!(s.sort_by, Some(crate::config::SortBy::Date)));
@@ -1,34 +1,59 @@
+use pulldown_cmark::utils::TextMergeWithOffset;
 use pulldown_cmark::{BlockQuoteKind, CodeBlockKind, CowStr, Event, Options, Parser, Tag, TagEnd};
 use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::LazyLock;
-use syntect::highlighting::ThemeSet;
-use syntect::html::highlighted_html_for_string;
-use syntect::parsing::{SyntaxDefinition, SyntaxSet};
 
-use crate::config::{AnchorLinks, MarkdownConfig};
-use crate::content::escape_xml;
+use crate::config::{
+    AnchorLinks, BlockOptions, ErrorMode, MarkdownConfig, MathMode, OnError, OutputMode,
+};
+use crate::content::{Heading, escape_xml};
 use crate::execute::{ExecutableBlock, VizOutput};
+use crate::highlight;
+use crate::math::render_math;
 use crate::shortcodes::{
     CALLOUT_ICON_CAUTION, CALLOUT_ICON_IMPORTANT, CALLOUT_ICON_NOTE, CALLOUT_ICON_TIP,
     CALLOUT_ICON_WARNING,
 };
 
-static SYNTAX_SET: LazyLock<SyntaxSet> = LazyLock::new(|| {
-    let mut builder = SyntaxSet::load_defaults_newlines().into_builder();
-    // Add TOML syntax (not in syntect's defaults)
-    if let Ok(toml_syn) = SyntaxDefinition::load_from_str(
-        include_str!("../syntaxes/TOML.sublime-syntax"),
-        true,
-        Some("TOML"),
-    ) {
-        builder.add(toml_syn);
-    }
-    builder.build()
-});
-static THEME_SET: LazyLock<ThemeSet> = LazyLock::new(ThemeSet::load_defaults);
 static FILE_ATTR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"file="([^"]+)""#).unwrap());
-
-const DEFAULT_HIGHLIGHT_THEME: &str = "base16-ocean.dark";
+static DEPS_ATTR_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"deps="([^"]+)""#).unwrap());
+static OPTION_ATTR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"\b(echo|output|error|collapse|on_error|parallel|caption)=(?:"([^"]*)"|([^\s"]+))"#,
+    )
+    .unwrap()
+});
+static EXEC_PLACEHOLDER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!-- EXEC_BLOCK_(\d+) -->").unwrap());
+/// `^superscript^`, or `~subscript~` the parser did not take for
+/// strikethrough (as inside a word).
+static SCRIPT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\^([^\s^]+)\^|~([^\s~]+)~").unwrap());
+/// A trailing `{#custom-id}` on a heading, honored without
+/// `heading_attributes`.
+static HEADING_ID_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s*\{#([^\s{}]+)\}$").unwrap());
+static ABBREVIATION_DEF_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\*\[([^\]]+)\]:[ \t]*(.*)$").unwrap());
+/// One `.class`, `#id` or `key=value` of a `{...}` attribute list. Quotes
+/// may be curly, as smart punctuation leaves them.
+static ATTR_TOKEN_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"^\s*(?:([.#])([\w-]+)|([\w:-]+)=(?:"([^"]*)"|“([^”]*)”|'([^']*)'|‘([^’]*)’|([^\s"'“”‘’]+)))"#,
+    )
+    .unwrap()
+});
+/// A block's attribute marker and the opening tag that follows it.
+static ATTR_MARKER_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"<!-- zorto-attrs (\d+) -->\s*<([a-zA-Z][a-zA-Z0-9]*)([^>]*)>").unwrap()
+});
+static STRAY_ATTR_MARKER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!-- zorto-attrs \d+ -->").unwrap());
+static HTML_ATTR_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"([^\s=/]+)(?:="([^"]*)")?"#).unwrap());
 
 /// Render markdown to HTML with all processing steps.
 pub fn render_markdown(
@@ -37,17 +62,33 @@ pub fn render_markdown(
     executable_blocks: &mut Vec<ExecutableBlock>,
     base_url: &str,
 ) -> String {
-    let mut options = Options::empty();
-    options.insert(Options::ENABLE_TABLES);
-    options.insert(Options::ENABLE_FOOTNOTES);
-    options.insert(Options::ENABLE_STRIKETHROUGH);
-    options.insert(Options::ENABLE_TASKLISTS);
-    options.insert(Options::ENABLE_GFM);
-    if config.smart_punctuation {
-        options.insert(Options::ENABLE_SMART_PUNCTUATION);
-    }
+    render_markdown_with_headings(content, config, executable_blocks, base_url).0
+}
 
-    let parser = Parser::new_ext(content, options);
+/// Render markdown to HTML like [`render_markdown`], also returning its
+/// headings in document order (without `permalink` or `children`; see
+/// [`content::build_toc`](crate::content::build_toc)).
+///
+/// Heading ids are the slugified heading text, or the id set with a
+/// `{#custom-id}` attribute. Repeated ids get a numeric suffix: `intro`,
+/// `intro-1`, `intro-2`.
+pub fn render_markdown_with_headings(
+    content: &str,
+    config: &MarkdownConfig,
+    executable_blocks: &mut Vec<ExecutableBlock>,
+    base_url: &str,
+) -> (String, Vec<Heading>) {
+    // `*[HTML]: ...` definitions come out of the text they apply to
+    let (content, abbreviations) = if config.abbreviations {
+        extract_abbreviations(content, config)
+    } else {
+        (Cow::Borrowed(content), Abbreviations::default())
+    };
+    let content = content.as_ref();
+    let mut parser = TextMergeWithOffset::new(
+        Parser::new_ext(content, parser_options(config)).into_offset_iter(),
+    )
+    .peekable();
     let mut events: Vec<Event> = Vec::new();
 
     let mut in_code_block = false;
@@ -55,8 +96,15 @@ pub fn render_markdown(
     let mut code_content = String::new();
     let mut heading_text = String::new();
     let mut in_heading = false;
-
-    for event in parser {
+    // `{#id}` stripped from the end of the current heading's text
+    let mut heading_id: Option<String> = None;
+    let mut headings = Vec::new();
+    let mut used_ids: HashSet<String> = HashSet::new();
+    let mut image_depth = 0;
+    // Whether each open `~`/`~~` span is subscript
+    let mut subscripts = Vec::new();
+
+    while let Some((event, range)) = parser.next() {
         match event {
             // --- GitHub-style callout / alert blockquotes ---
             Event::Start(Tag::BlockQuote(Some(kind))) => {
@@ -86,13 +134,16 @@ pub fn render_markdown(
                 if code_lang.starts_with('{') && code_lang.ends_with('}') {
                     let lang = &code_lang[1..code_lang.len() - 1];
                     // Parse potential attributes like file="..."
-                    let (actual_lang, file_ref) = parse_code_attrs(lang);
+                    let attrs = parse_code_attrs(lang);
 
                     let block_idx = executable_blocks.len();
                     executable_blocks.push(ExecutableBlock {
-                        language: actual_lang.to_string(),
+                        language: attrs.language.to_string(),
                         source: code_content.clone(),
-                        file_ref,
+                        file_ref: attrs.file_ref,
+                        deps: attrs.deps,
+                        options: attrs.options,
+                        data: Default::default(),
                         output: None,
                         error: None,
                         viz: Vec::new(),
@@ -110,28 +161,51 @@ pub fn render_markdown(
             Event::Text(text) if in_code_block => {
                 code_content.push_str(&text);
             }
-            Event::Start(Tag::Heading { level, .. }) => {
+            Event::Start(Tag::Heading {
+                level,
+                id,
+                classes,
+                attrs,
+            }) => {
                 in_heading = true;
                 heading_text.clear();
-                // Remember where we'll patch in the id later
+                let (classes, attrs) = if config.heading_attributes {
+                    (classes, attrs)
+                } else {
+                    (vec![], vec![])
+                };
+                // Remember where we'll patch in the id later; a custom id
+                // stays until then
                 events.push(Event::Start(Tag::Heading {
                     level,
-                    id: None,
-                    classes: vec![],
-                    attrs: vec![],
+                    id,
+                    classes,
+                    attrs,
                 }));
             }
-            Event::End(TagEnd::Heading(_level)) => {
+            Event::End(TagEnd::Heading(level)) => {
                 in_heading = false;
-                let id = slug::slugify(&heading_text);
 
                 // Patch the heading start event with the computed id
+                let mut id = String::new();
                 for ev in events.iter_mut().rev() {
                     if let Event::Start(Tag::Heading { id: h_id, .. }) = ev {
+                        let base = match (h_id.as_ref(), heading_id.take()) {
+                            (Some(custom), _) => custom.to_string(),
+                            (None, Some(custom)) => custom,
+                            (None, None) => slug::slugify(&heading_text),
+                        };
+                        id = unique_id(base, &mut used_ids);
                         *h_id = Some(CowStr::from(id.clone()));
                         break;
                     }
                 }
+                headings.push(Heading {
+                    level: level as u32,
+                    id: id.clone(),
+                    title: heading_text.trim().to_string(),
+                    ..Default::default()
+                });
 
                 // Insert anchor link if configured
                 if config.insert_anchor_links == AnchorLinks::Right {
@@ -145,10 +219,68 @@ pub fn render_markdown(
 
                 events.push(event);
             }
-            Event::Text(ref text) if in_heading => {
+            Event::Code(ref text) if in_heading => {
                 heading_text.push_str(text);
                 events.push(event);
             }
+            Event::Text(mut text) => {
+                if in_heading {
+                    let ends_heading =
+                        matches!(parser.peek(), Some((Event::End(TagEnd::Heading(_)), _)));
+                    if ends_heading
+                        && !config.heading_attributes
+                        && let Some(caps) = HEADING_ID_RE.captures(&text)
+                    {
+                        heading_id = Some(caps[1].to_string());
+                        let end = caps.get(0).map_or(text.len(), |m| m.start());
+                        text = CowStr::from(text[..end].to_string());
+                    }
+                    heading_text.push_str(&text);
+                }
+                let html = (image_depth == 0)
+                    .then(|| inline_extensions(&text, config, &abbreviations))
+                    .flatten();
+                match html {
+                    Some(html) => events.push(Event::InlineHtml(CowStr::from(html))),
+                    None => events.push(Event::Text(text)),
+                }
+            }
+            Event::Start(Tag::Image { .. }) => {
+                image_depth += 1;
+                events.push(event);
+            }
+            Event::End(TagEnd::Image) => {
+                image_depth -= 1;
+                events.push(event);
+            }
+            Event::Start(Tag::Strikethrough) => {
+                // `~text~` is subscript, `~~text~~` strikethrough
+                let subscript = config.subscript && !content[range].starts_with("~~");
+                subscripts.push(subscript);
+                if subscript {
+                    events.push(Event::InlineHtml(CowStr::from("<sub>")));
+                } else {
+                    events.push(event);
+                }
+            }
+            Event::End(TagEnd::Strikethrough) => {
+                let end = match subscripts.pop() {
+                    Some(true) => Event::InlineHtml(CowStr::from("</sub>")),
+                    _ => event,
+                };
+                events.push(end);
+            }
+            Event::InlineMath(tex) => {
+                if in_heading {
+                    heading_text.push_str(&tex);
+                }
+                let html = render_math(&tex, false);
+                events.push(Event::InlineHtml(CowStr::from(html)));
+            }
+            Event::DisplayMath(tex) => {
+                let html = render_math(&tex, true);
+                events.push(Event::InlineHtml(CowStr::from(html)));
+            }
             Event::Start(Tag::Link {
                 dest_url, title, ..
             }) => {
@@ -187,11 +319,404 @@ pub fn render_markdown(
         }
     }
 
+    let block_attributes = if config.block_attributes {
+        take_block_attributes(&mut events)
+    } else {
+        Vec::new()
+    };
+
     // Render to HTML
     let mut html = String::new();
     pulldown_cmark::html::push_html(&mut html, events.into_iter());
+    if !block_attributes.is_empty() {
+        html = apply_block_attributes(&html, &block_attributes);
+    }
 
-    html
+    (html, headings)
+}
+
+/// `id`, or `id` with the first numeric suffix not in `used`, which it is
+/// then added to.
+fn unique_id(id: String, used: &mut HashSet<String>) -> String {
+    let mut unique = id.clone();
+    let mut n = 0;
+    while !used.insert(unique.clone()) {
+        n += 1;
+        unique = format!("{id}-{n}");
+    }
+    unique
+}
+
+/// pulldown-cmark extensions enabled for `config`.
+fn parser_options(config: &MarkdownConfig) -> Options {
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_FOOTNOTES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    options.insert(Options::ENABLE_GFM);
+    if config.heading_attributes {
+        options.insert(Options::ENABLE_HEADING_ATTRIBUTES);
+    }
+    if config.smart_punctuation {
+        options.insert(Options::ENABLE_SMART_PUNCTUATION);
+    }
+    if config.math != MathMode::None {
+        options.insert(Options::ENABLE_MATH);
+    }
+    if config.definition_lists {
+        options.insert(Options::ENABLE_DEFINITION_LIST);
+    }
+    options
+}
+
+/// Byte ranges of the code in `content`: code blocks, fences included, and
+/// inline code spans.
+pub(crate) fn code_ranges(content: &str, config: &MarkdownConfig) -> Vec<Range<usize>> {
+    Parser::new_ext(content, parser_options(config))
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(_)) | Event::Code(_) => Some(range),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `text` as HTML with superscripts, subscripts and abbreviations, or
+/// `None` if it has none.
+fn inline_extensions(
+    text: &str,
+    config: &MarkdownConfig,
+    abbreviations: &Abbreviations,
+) -> Option<String> {
+    let scripts: Vec<_> = SCRIPT_RE
+        .captures_iter(text)
+        .filter_map(|caps| match (caps.get(1), caps.get(2)) {
+            (Some(inner), _) if config.superscript => Some((caps.get(0)?, "sup", inner)),
+            (_, Some(inner)) if config.subscript => Some((caps.get(0)?, "sub", inner)),
+            _ => None,
+        })
+        .collect();
+    if scripts.is_empty() && !abbreviations.matches(text) {
+        return None;
+    }
+    let mut html = String::new();
+    let mut last = 0;
+    for (all, tag, inner) in scripts {
+        html.push_str(&abbreviations.expand(&text[last..all.start()]));
+        html.push_str(&format!(
+            "<{tag}>{}</{tag}>",
+            abbreviations.expand(inner.as_str())
+        ));
+        last = all.end();
+    }
+    html.push_str(&abbreviations.expand(&text[last..]));
+    Some(html)
+}
+
+/// Abbreviations defined with `*[ABBR]: Full text`.
+#[derive(Default)]
+struct Abbreviations {
+    titles: HashMap<String, String>,
+    /// Matches any abbreviation, longest first.
+    re: Option<Regex>,
+}
+
+impl Abbreviations {
+    fn new(definitions: Vec<(String, String)>) -> Self {
+        let mut names: Vec<&String> = definitions.iter().map(|(name, _)| name).collect();
+        names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+        let pattern = names
+            .iter()
+            .map(|name| regex::escape(name))
+            .collect::<Vec<_>>()
+            .join("|");
+        let re = (!names.is_empty()).then(|| Regex::new(&pattern).unwrap());
+        Self {
+            titles: definitions.into_iter().collect(),
+            re,
+        }
+    }
+
+    /// Whether `text` contains an abbreviation as a whole word.
+    fn matches(&self, text: &str) -> bool {
+        self.words(text).next().is_some()
+    }
+
+    /// `text` as HTML, with its abbreviations in `<abbr>`.
+    fn expand(&self, text: &str) -> String {
+        let mut html = String::new();
+        let mut last = 0;
+        for (start, end) in self.words(text) {
+            let name = &text[start..end];
+            html.push_str(&escape_xml(&text[last..start]));
+            html.push_str(&format!(
+                "<abbr title=\"{}\">{}</abbr>",
+                escape_xml(&self.titles[name]),
+                escape_xml(name)
+            ));
+            last = end;
+        }
+        html.push_str(&escape_xml(&text[last..]));
+        html
+    }
+
+    /// Byte ranges of the abbreviations in `text` that are not part of a
+    /// longer word.
+    fn words<'t>(&'t self, text: &'t str) -> impl Iterator<Item = (usize, usize)> + 't {
+        let is_word = |c: char| c.is_alphanumeric() || c == '_';
+        self.re
+            .iter()
+            .flat_map(move |re| re.find_iter(text))
+            .filter(move |m| {
+                !text[..m.start()].chars().next_back().is_some_and(is_word)
+                    && !text[m.end()..].chars().next().is_some_and(is_word)
+            })
+            .map(|m| (m.start(), m.end()))
+    }
+}
+
+/// Take the abbreviation definition lines out of `content`, skipping code.
+fn extract_abbreviations<'c>(
+    content: &'c str,
+    config: &MarkdownConfig,
+) -> (Cow<'c, str>, Abbreviations) {
+    let code = code_ranges(content, config);
+    let mut definitions = Vec::new();
+    let mut out = String::with_capacity(content.len());
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        let in_code = code.iter().any(|range| range.contains(&offset));
+        match ABBREVIATION_DEF_RE.captures(line.trim_end()) {
+            Some(caps) if !in_code => {
+                definitions.push((caps[1].trim().to_string(), caps[2].trim().to_string()));
+            }
+            _ => out.push_str(line),
+        }
+        offset += line.len();
+    }
+    if definitions.is_empty() {
+        return (Cow::Borrowed(content), Abbreviations::default());
+    }
+    (Cow::Owned(out), Abbreviations::new(definitions))
+}
+
+/// Attributes from a `{.class #id key=value}` list.
+#[derive(Debug, Default, PartialEq)]
+struct BlockAttributes {
+    id: Option<String>,
+    classes: Vec<String>,
+    attrs: Vec<(String, String)>,
+}
+
+/// Parse `text` if it is a `{...}` attribute list (optionally `{: ...}`).
+fn parse_block_attributes(text: &str) -> Option<BlockAttributes> {
+    let inner = text.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let mut rest = inner.strip_prefix(':').unwrap_or(inner);
+    let mut attributes = BlockAttributes::default();
+    while !rest.trim().is_empty() {
+        let caps = ATTR_TOKEN_RE.captures(rest)?;
+        match (caps.get(1).map(|m| m.as_str()), caps.get(2)) {
+            (Some("."), Some(name)) => attributes.classes.push(name.as_str().to_string()),
+            (Some(_), Some(name)) => attributes.id = Some(name.as_str().to_string()),
+            _ => {
+                let value = (4..=8).find_map(|i| caps.get(i)).map_or("", |m| m.as_str());
+                attributes
+                    .attrs
+                    .push((caps[3].to_string(), value.to_string()));
+            }
+        }
+        rest = &rest[caps.get(0).unwrap().end()..];
+    }
+    let empty = attributes == BlockAttributes::default();
+    (!empty).then_some(attributes)
+}
+
+/// Take `{...}` attribute lists out of `events`, leaving a marker before
+/// the block each applies to for [`apply_block_attributes`]. A list on a
+/// line of its own applies to the block before it; a list on the last line
+/// of a paragraph applies to that paragraph. Lists with no block to apply
+/// to stay as text.
+fn take_block_attributes(events: &mut Vec<Event>) -> Vec<BlockAttributes> {
+    let mut found = Vec::new();
+    let mut i = 2;
+    while i < events.len() {
+        let attributes = match (&events[i - 1], &events[i]) {
+            (Event::Text(text), Event::End(TagEnd::Paragraph)) => parse_block_attributes(text),
+            _ => None,
+        };
+        let Some(attributes) = attributes else {
+            i += 1;
+            continue;
+        };
+        let marker = Event::Html(CowStr::from(format!(
+            "<!-- zorto-attrs {} -->",
+            found.len()
+        )));
+        if matches!(events[i - 2], Event::Start(Tag::Paragraph)) {
+            // A paragraph of its own, for the block before it
+            let Some(target) = (i > 2).then(|| block_start(events, i - 3)).flatten() else {
+                i += 1;
+                continue;
+            };
+            events.drain(i - 2..=i);
+            events.insert(target, marker);
+            // The event after the removed paragraph, moved by the marker
+            i -= 1;
+        } else if matches!(events[i - 2], Event::SoftBreak) {
+            let Some(target) = block_start(events, i) else {
+                i += 1;
+                continue;
+            };
+            // The paragraph's end moves to `i - 1`, so `i` is next
+            events.drain(i - 2..i);
+            events.insert(target, marker);
+        } else {
+            i += 1;
+            continue;
+        }
+        found.push(attributes);
+    }
+    found
+}
+
+/// Index of the event that opens the block ending at `events[end]`.
+fn block_start(events: &[Event], end: usize) -> Option<usize> {
+    match &events[end] {
+        Event::End(tag_end) => {
+            let mut depth = 0;
+            for (i, event) in events[..end].iter().enumerate().rev() {
+                match event {
+                    Event::End(e) if e == tag_end => depth += 1,
+                    Event::Start(tag) if tag.to_end() == *tag_end => {
+                        if depth == 0 {
+                            return Some(i);
+                        }
+                        depth -= 1;
+                    }
+                    _ => {}
+                }
+            }
+            None
+        }
+        // A rendered block, such as highlighted code
+        Event::Html(html)
+            if html
+                .strip_prefix('<')
+                .is_some_and(|tag| tag.starts_with(|c: char| c.is_ascii_alphabetic())) =>
+        {
+            Some(end)
+        }
+        _ => None,
+    }
+}
+
+/// Merge the attributes taken by [`take_block_attributes`] into the tags
+/// after their markers in `html`.
+fn apply_block_attributes(html: &str, found: &[BlockAttributes]) -> String {
+    let html = ATTR_MARKER_RE.replace_all(html, |caps: &regex::Captures| {
+        match caps[1].parse::<usize>().ok().and_then(|i| found.get(i)) {
+            Some(attributes) => merge_attributes(&caps[2], &caps[3], attributes),
+            None => caps[0].to_string(),
+        }
+    });
+    STRAY_ATTR_MARKER_RE.replace_all(&html, "").into_owned()
+}
+
+/// An opening `tag` with its `existing` attributes and `attributes`: classes
+/// are added, and the id and other attributes replace any already set.
+fn merge_attributes(tag: &str, existing: &str, attributes: &BlockAttributes) -> String {
+    let mut attrs: Vec<(String, Option<String>)> = HTML_ATTR_RE
+        .captures_iter(existing)
+        .map(|caps| {
+            (
+                caps[1].to_string(),
+                caps.get(2).map(|v| v.as_str().to_string()),
+            )
+        })
+        .collect();
+    let set = |attrs: &mut Vec<(String, Option<String>)>, name: &str, value: String| match attrs
+        .iter_mut()
+        .find(|(n, _)| n == name)
+    {
+        Some((_, v)) => *v = Some(value),
+        None => attrs.push((name.to_string(), Some(value))),
+    };
+    if let Some(id) = &attributes.id {
+        set(&mut attrs, "id", escape_xml(id));
+    }
+    if !attributes.classes.is_empty() {
+        let classes = escape_xml(&attributes.classes.join(" "));
+        match attrs.iter_mut().find(|(n, _)| n == "class") {
+            Some((_, Some(class))) if !class.is_empty() => *class = format!("{class} {classes}"),
+            Some((_, class)) => *class = Some(classes),
+            None => attrs.push(("class".to_string(), Some(classes))),
+        }
+    }
+    for (name, value) in &attributes.attrs {
+        set(&mut attrs, name, escape_xml(value));
+    }
+    let mut out = format!("<{tag}");
+    for (name, value) in attrs {
+        match value {
+            Some(value) => out.push_str(&format!(" {name}=\"{value}\"")),
+            None => out.push_str(&format!(" {name}")),
+        }
+    }
+    out.push('>');
+    out
+}
+
+/// Byte ranges of the executable code blocks in `content`, fences included,
+/// in the order [`render_markdown`] collects them.
+pub(crate) fn executable_block_ranges(content: &str, config: &MarkdownConfig) -> Vec<Range<usize>> {
+    Parser::new_ext(content, parser_options(config))
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(lang)))
+                if lang.starts_with('{') && lang.ends_with('}') =>
+            {
+                Some(range)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// A fenced, non-executable code block, as found by [`fenced_code_blocks`].
+pub(crate) struct FencedBlock {
+    /// Byte range in the content, fences included.
+    pub range: Range<usize>,
+    /// First word of the info string.
+    pub language: String,
+    pub source: String,
+}
+
+/// The fenced code blocks of `content` that are not executable.
+pub(crate) fn fenced_code_blocks(content: &str, config: &MarkdownConfig) -> Vec<FencedBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<FencedBlock> = None;
+    for (event, range) in Parser::new_ext(content, parser_options(config)).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(info)))
+                if !(info.starts_with('{') && info.ends_with('}')) =>
+            {
+                current = Some(FencedBlock {
+                    range,
+                    language: info.split_whitespace().next().unwrap_or("").to_string(),
+                    source: String::new(),
+                });
+            }
+            Event::Text(text) => {
+                if let Some(block) = &mut current {
+                    block.source.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => blocks.extend(current.take()),
+            _ => {}
+        }
+    }
+    blocks
 }
 
 /// Get CSS class, SVG icon, and display title for a GitHub-style callout.
@@ -211,53 +736,92 @@ pub fn extract_summary(content: &str) -> Option<String> {
     content.find(marker).map(|pos| content[..pos].to_string())
 }
 
-/// Replace executable block placeholders with rendered output
+/// Replace executable block placeholders with rendered output, as set by
+/// each block's display options.
+///
+/// `base_url` is used to render `output="asis"` output as markdown.
 pub fn replace_exec_placeholders(
     html: &str,
     blocks: &[ExecutableBlock],
     config: &MarkdownConfig,
+    base_url: &str,
 ) -> String {
-    let mut result = html.to_string();
+    // One pass, so that an executed block whose output happens to contain
+    // another block's placeholder string does not receive that block.
+    let mut replaced = vec![false; blocks.len()];
+    EXEC_PLACEHOLDER_RE
+        .replace_all(html, |caps: &regex::Captures| {
+            let block = caps[1].parse::<usize>().ok().and_then(|i| {
+                let first = !std::mem::replace(replaced.get_mut(i)?, true);
+                first.then(|| &blocks[i])
+            });
+            match block {
+                Some(block) => render_exec_block(block, config, base_url),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+}
 
-    for (i, block) in blocks.iter().enumerate() {
-        let placeholder = format!("<!-- EXEC_BLOCK_{i} -->");
-        if result.contains(&placeholder) {
-            let source_html = highlight_code(&block.source, &block.language, config);
-            let mut block_html = format!(r#"<div class="code-block-executed">{source_html}"#,);
+/// Render an executed block: its source, output, error, visualizations and
+/// caption, as its options allow.
+fn render_exec_block(block: &ExecutableBlock, config: &MarkdownConfig, base_url: &str) -> String {
+    let options = &block.options;
+    let mut block_html = String::new();
+    if options.echo() {
+        let source_html = highlight_code(&block.source, &block.language, config);
+        if options.collapse() {
+            block_html.push_str(&format!(
+                r#"<details class="code-fold"><summary>Code</summary>{source_html}</details>"#
+            ));
+        } else {
+            block_html.push_str(&source_html);
+        }
+    }
 
-            if let Some(ref output) = block.output
-                && !output.is_empty()
-            {
-                block_html.push_str(&format!(
-                    r#"<div class="code-output"><pre><code>{}</code></pre></div>"#,
-                    escape_xml(output)
-                ));
-            }
-            if let Some(ref error) = block.error
-                && !error.is_empty()
-            {
-                block_html.push_str(&format!(
-                    r#"<div class="code-error"><pre><code>{}</code></pre></div>"#,
-                    escape_xml(error)
-                ));
-            }
-            // Render visualization output (after text output, before closing div)
-            for v in &block.viz {
-                block_html.push_str(&render_viz_output(v));
-            }
-            block_html.push_str("</div>");
-            // replacen with limit 1 so that an executed block whose output text
-            // happens to contain another block's placeholder string does not
-            // cause that later substitution to land in the wrong place.
-            result = result.replacen(&placeholder, &block_html, 1);
+    let output = block.output.as_deref().filter(|o| !o.is_empty());
+    if let (OutputMode::Show, Some(output)) = (options.output(), output) {
+        block_html.push_str(&format!(
+            r#"<div class="code-output"><pre><code>{}</code></pre></div>"#,
+            escape_xml(output)
+        ));
+    }
+    if let Some(ref error) = block.error
+        && !error.is_empty()
+        && options.error() != ErrorMode::Hide
+    {
+        block_html.push_str(&format!(
+            r#"<div class="code-error"><pre><code>{}</code></pre></div>"#,
+            escape_xml(error)
+        ));
+    }
+    // Render visualization output (after text output, before closing div)
+    if options.output() != OutputMode::Hide {
+        for v in &block.viz {
+            block_html.push_str(&render_viz_output(v, config, base_url));
         }
     }
+    if let Some(caption) = &options.caption {
+        block_html.push_str(&format!(
+            r#"<div class="code-caption">{}</div>"#,
+            escape_xml(caption)
+        ));
+    }
 
-    result
+    let mut html = if block_html.is_empty() {
+        String::new()
+    } else {
+        format!(r#"<div class="code-block-executed">{block_html}</div>"#)
+    };
+    // As-is output becomes part of the page, outside the block's frame
+    if let (OutputMode::Asis, Some(output)) = (options.output(), output) {
+        html.push_str(&render_markdown(output, config, &mut Vec::new(), base_url));
+    }
+    html
 }
 
 /// Render a single visualization output into HTML.
-fn render_viz_output(viz: &VizOutput) -> String {
+fn render_viz_output(viz: &VizOutput, config: &MarkdownConfig, base_url: &str) -> String {
     match viz.kind.as_str() {
         "img" => {
             format!(
@@ -272,14 +836,16 @@ fn render_viz_output(viz: &VizOutput) -> String {
             // meaningful additional risk — the user already has full code execution.
             format!(r#"<div class="code-viz">{}</div>"#, viz.data)
         }
+        "markdown" => format!(
+            r#"<div class="code-viz">{}</div>"#,
+            render_markdown(&viz.data, config, &mut Vec::new(), base_url)
+        ),
         _ => String::new(),
     }
 }
 
 /// Highlight a code block with syntect
 fn highlight_code(code: &str, lang: &str, config: &MarkdownConfig) -> String {
-    let ss = &*SYNTAX_SET;
-    let ts = &*THEME_SET;
     let fallback = || {
         format!(
             "<pre><code class=\"language-{lang}\">{}</code></pre>",
@@ -291,33 +857,62 @@ fn highlight_code(code: &str, lang: &str, config: &MarkdownConfig) -> String {
         return fallback();
     }
 
-    let theme_name = config
-        .highlight_theme
-        .as_deref()
-        .unwrap_or(DEFAULT_HIGHLIGHT_THEME);
-    let syntax = ss
-        .find_syntax_by_token(lang)
-        .unwrap_or_else(|| ss.find_syntax_plain_text());
-    let theme = ts
-        .themes
-        .get(theme_name)
-        .unwrap_or(&ts.themes[DEFAULT_HIGHLIGHT_THEME]);
-
-    highlighted_html_for_string(code, ss, syntax, theme).unwrap_or_else(|_| fallback())
+    let highlighter = config.highlighter.as_deref().unwrap_or(&highlight::BUNDLED);
+    highlighter
+        .highlight(code, lang, config.highlight_theme.as_deref())
+        .unwrap_or_else(fallback)
 }
 
-/// Parse code block attributes like {python file="script.py"}
-fn parse_code_attrs(lang: &str) -> (&str, Option<String>) {
-    let parts: Vec<&str> = lang.splitn(2, ' ').collect();
-    let actual_lang = parts[0];
+/// Attributes of an executable code fence.
+struct CodeAttrs<'a> {
+    language: &'a str,
+    /// `file="script.py"`: run this file instead of the block's body.
+    file_ref: Option<String>,
+    /// `deps="data/*.csv, config.toml"`: files the block reads.
+    deps: Vec<String>,
+    /// `echo=false`, `output="asis"`, `caption="..."` and the like.
+    options: BlockOptions,
+}
 
-    let file_ref = if parts.len() > 1 {
-        FILE_ATTR_RE.captures(parts[1]).map(|c| c[1].to_string())
-    } else {
-        None
-    };
+/// Parse code block attributes like
+/// {python file="script.py" deps="data/*.csv" echo=false}
+fn parse_code_attrs(lang: &str) -> CodeAttrs<'_> {
+    let (language, attrs) = lang.split_once(' ').unwrap_or((lang, ""));
+
+    let file_ref = FILE_ATTR_RE.captures(attrs).map(|c| c[1].to_string());
+    let deps = DEPS_ATTR_RE
+        .captures(attrs)
+        .map(|c| {
+            c[1].split([',', ' '])
+                .map(str::trim)
+                .filter(|d| !d.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default();
+
+    // Options with values they do not take are ignored
+    let mut options = BlockOptions::default();
+    for caps in OPTION_ATTR_RE.captures_iter(attrs) {
+        let value = caps.get(2).or(caps.get(3)).map_or("", |m| m.as_str());
+        let flag = || value.parse::<bool>().ok();
+        match &caps[1] {
+            "echo" => options.echo = flag(),
+            "output" => options.output = OutputMode::parse(value),
+            "error" => options.error = ErrorMode::parse(value),
+            "collapse" => options.collapse = flag(),
+            "on_error" => options.on_error = OnError::parse(value),
+            "parallel" => options.parallel = flag(),
+            _ => options.caption = Some(value.to_string()),
+        }
+    }
 
-    (actual_lang, file_ref)
+    CodeAttrs {
+        language,
+        file_ref,
+        deps,
+        options,
+    }
 }
 
 fn is_external_url(url: &str, base_url: &str) -> bool {
@@ -399,12 +994,22 @@ mod tests {
     }
 
     #[test]
-    fn test_toml_syntax_available() {
-        let ss = &*SYNTAX_SET;
-        assert!(
-            ss.find_syntax_by_token("toml").is_some(),
-            "TOML syntax should be available for highlighting"
+    fn test_render_code_block_css_classes() {
+        let config = MarkdownConfig {
+            highlight_theme: Some("css".into()),
+            ..default_config()
+        };
+        let html = render_markdown(
+            "```rust
+fn main() {}
+```",
+            &config,
+            &mut Vec::new(),
+            "",
         );
+        assert!(html.starts_with("<pre class=\"z-code\"><code class=\"language-rust\">"));
+        assert!(html.contains("<span class=\"z-storage z-type z-function z-rust\">fn</span>"));
+        assert!(!html.contains("style="));
     }
 
     #[test]
@@ -418,6 +1023,30 @@ mod tests {
         assert!(html.contains("<!-- EXEC_BLOCK_0 -->"));
     }
 
+    #[test]
+    fn test_parse_code_attrs_file_and_deps() {
+        let attrs = parse_code_attrs(r#"python file="run.py" deps="data/*.csv, config.toml""#);
+        assert_eq!(attrs.language, "python");
+        assert_eq!(attrs.file_ref.as_deref(), Some("run.py"));
+        assert_eq!(attrs.deps, ["data/*.csv", "config.toml"]);
+
+        let attrs = parse_code_attrs("bash");
+        assert_eq!(attrs.language, "bash");
+        assert!(attrs.file_ref.is_none());
+        assert!(attrs.deps.is_empty());
+    }
+
+    #[test]
+    fn test_executable_block_ranges_match_render_order() {
+        let input = "Intro\n\n```{python}\nprint(1)\n```\n\n```rust\nfn main() {}\n```\n\n```{bash}\necho hi\n```\n";
+        let ranges = executable_block_ranges(input, &default_config());
+        let mut blocks = Vec::new();
+        render_markdown(input, &default_config(), &mut blocks, "");
+        assert_eq!(ranges.len(), blocks.len());
+        assert_eq!(&input[ranges[0].clone()], "```{python}\nprint(1)\n```");
+        assert!(input[ranges[1].clone()].starts_with("```{bash}"));
+    }
+
     #[test]
     fn test_render_table() {
         let mut blocks = Vec::new();
@@ -473,6 +1102,185 @@ mod tests {
         assert!(html.contains("id=\"hello-world\""));
     }
 
+    #[test]
+    fn test_render_heading_ids_unique_and_custom() {
+        let input = "# Intro\n\n## Intro\n\n### The `Site` type {#site}\n\n## Intro\n\n## Site\n";
+        let (html, headings) = render_markdown_with_headings(
+            input,
+            &default_config(),
+            &mut Vec::new(),
+            "https://example.com",
+        );
+        assert!(html.contains(r#"<h1 id="intro">Intro</h1>"#));
+        assert!(html.contains(r#"<h2 id="intro-1">Intro</h2>"#));
+        assert!(html.contains(r#"<h3 id="site">The <code>Site</code> type</h3>"#));
+        assert!(html.contains(r#"<h2 id="intro-2">Intro</h2>"#));
+        assert!(html.contains(r#"<h2 id="site-1">Site</h2>"#));
+
+        let summary: Vec<(u32, &str, &str)> = headings
+            .iter()
+            .map(|h| (h.level, h.id.as_str(), h.title.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                (1, "intro", "Intro"),
+                (2, "intro-1", "Intro"),
+                (3, "site", "The Site type"),
+                (2, "intro-2", "Intro"),
+                (2, "site-1", "Site"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_math() {
+        let input = "Costs $5 and $10.\n\nEuler: $e^{i\\pi} = -1$\n\n$$\\frac{a}{b}$$\n";
+        let html = render_markdown(input, &default_config(), &mut Vec::new(), "");
+        // Off by default: dollar signs stay text
+        assert!(html.contains("Euler: $e^{i\\pi} = -1$"));
+        assert!(!html.contains("<math"));
+
+        let config = MarkdownConfig {
+            math: MathMode::Mathml,
+            ..default_config()
+        };
+        let html = render_markdown(input, &config, &mut Vec::new(), "");
+        assert!(html.contains("<p>Costs $5 and $10.</p>"));
+        assert!(html.contains("<p>Euler: <math xmlns=\"http://www.w3.org/1998/Math/MathML\">"));
+        assert!(
+            html.contains("<math xmlns=\"http://www.w3.org/1998/Math/MathML\" display=\"block\">")
+        );
+        assert!(html.contains("<mfrac>"));
+        assert!(!html.contains("class=\"math"));
+    }
+
+    #[test]
+    fn test_render_definition_lists() {
+        let input = "Term\n: Its definition\n";
+        let html = render_markdown(input, &default_config(), &mut Vec::new(), "");
+        assert!(!html.contains("<dl>"));
+
+        let config = MarkdownConfig {
+            definition_lists: true,
+            ..default_config()
+        };
+        let html = render_markdown(input, &config, &mut Vec::new(), "");
+        assert!(html.contains("<dl>\n<dt>Term</dt>\n<dd>Its definition</dd>\n</dl>"));
+    }
+
+    #[test]
+    fn test_render_heading_attributes() {
+        let input = "## Title {#top .wide data-level=2}\n";
+        // Off: only a trailing `{#id}` is read; other braces stay text
+        let html = render_markdown(input, &default_config(), &mut Vec::new(), "");
+        assert!(
+            html.contains(">Title {#top .wide data-level=2}</h2>"),
+            "got: {html}"
+        );
+        let render = |input: &str| render_markdown(input, &default_config(), &mut Vec::new(), "");
+        assert_eq!(render("## Title {#top}\n"), "<h2 id=\"top\">Title</h2>\n");
+        assert_eq!(
+            render("## Rust closures {x}\n"),
+            "<h2 id=\"rust-closures-x\">Rust closures {x}</h2>\n"
+        );
+        assert_eq!(
+            render("## Sets {#a} and {#b} {#sets}\n"),
+            "<h2 id=\"sets\">Sets {#a} and {#b}</h2>\n"
+        );
+
+        let config = MarkdownConfig {
+            heading_attributes: true,
+            ..default_config()
+        };
+        let html = render_markdown(input, &config, &mut Vec::new(), "");
+        assert!(html.contains("<h2 id=\"top\" class=\"wide\" data-level=\"2\">Title</h2>"));
+    }
+
+    #[test]
+    fn test_render_block_attributes() {
+        let config = MarkdownConfig {
+            block_attributes: true,
+            ..default_config()
+        };
+        let render = |input: &str| render_markdown(input, &config, &mut Vec::new(), "");
+
+        // On the paragraph's last line
+        let html = render("A note.\n{.aside #n1 data-x=\"a b\"}\n");
+        assert_eq!(
+            html,
+            "<p id=\"n1\" class=\"aside\" data-x=\"a b\">A note.</p>\n"
+        );
+
+        // On a line of its own, after the block
+        let html = render("- one\n- two\n\n{: .checklist}\n\nNext\n");
+        assert!(html.starts_with("<ul class=\"checklist\">\n<li>one</li>"));
+        assert!(html.contains("<p>Next</p>"));
+        assert!(!html.contains('{'));
+
+        let html = render("> quoted\n\n{.pull}\n");
+        assert!(html.starts_with("<blockquote class=\"pull\">"));
+
+        // Highlighted code keeps its own classes and style
+        let html = render("```text\nplain\n```\n\n{.wide #listing}\n");
+        assert!(html.starts_with("<pre style="));
+        assert!(html.contains(" id=\"listing\" class=\"wide\">"));
+
+        // Not attribute lists, or nothing to apply to
+        assert_eq!(render("{.lonely}\n"), "<p>{.lonely}</p>\n");
+        assert_eq!(render("Set {a, b}\n"), "<p>Set {a, b}</p>\n");
+        assert_eq!(render("x\n{not attrs}\n"), "<p>x\n{not attrs}</p>\n");
+
+        let off = render_markdown(
+            "A note.\n{.aside}\n",
+            &default_config(),
+            &mut Vec::new(),
+            "",
+        );
+        assert_eq!(off, "<p>A note.\n{.aside}</p>\n");
+    }
+
+    #[test]
+    fn test_render_superscript_and_subscript() {
+        let input = "x^2^ + H~2~O, ~n~ ~~old~~ and `a^b^`\n";
+        let html = render_markdown(input, &default_config(), &mut Vec::new(), "");
+        assert!(html.contains("x^2^ + H~2~O, <del>n</del> <del>old</del>"));
+
+        let config = MarkdownConfig {
+            superscript: true,
+            subscript: true,
+            ..default_config()
+        };
+        let html = render_markdown(input, &config, &mut Vec::new(), "");
+        assert_eq!(
+            html,
+            "<p>x<sup>2</sup> + H<sub>2</sub>O, <sub>n</sub> <del>old</del> and <code>a^b^</code></p>\n"
+        );
+    }
+
+    #[test]
+    fn test_render_abbreviations() {
+        let input = "The HTML spec and HTML5 differ from XHTML.\n\n\
+                     ```text\n*[CODE]: stays\n```\n\n\
+                     *[HTML]: Hyper Text Markup Language\n\
+                     *[HTML5]: HTML, version 5\n";
+        let config = MarkdownConfig {
+            abbreviations: true,
+            ..default_config()
+        };
+        let html = render_markdown(input, &config, &mut Vec::new(), "");
+        assert!(html.starts_with(
+            "<p>The <abbr title=\"Hyper Text Markup Language\">HTML</abbr> spec and \
+             <abbr title=\"HTML, version 5\">HTML5</abbr> differ from XHTML.</p>"
+        ));
+        assert!(html.contains("*[CODE]: stays"));
+        assert!(!html.contains("*[HTML]"));
+
+        let html = render_markdown(input, &default_config(), &mut Vec::new(), "");
+        assert!(!html.contains("<abbr"));
+        assert!(html.contains("*[HTML]: Hyper Text Markup Language"));
+    }
+
     #[test]
     fn test_render_external_link_target_blank() {
         let mut config = default_config();
@@ -514,11 +1322,14 @@ mod tests {
             language: "python".into(),
             source: "print('hi')".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: Some("hi\n".into()),
             error: None,
             viz: Vec::new(),
         }];
-        let result = replace_exec_placeholders(html, &blocks, &default_config());
+        let result = replace_exec_placeholders(html, &blocks, &default_config(), "");
         assert!(result.contains("code-block-executed"));
         assert!(result.contains("code-output"));
         assert!(result.contains("hi\n"));
@@ -532,15 +1343,105 @@ mod tests {
             language: "python".into(),
             source: "bad".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: None,
             error: Some("NameError".into()),
             viz: Vec::new(),
         }];
-        let result = replace_exec_placeholders(html, &blocks, &default_config());
+        let result = replace_exec_placeholders(html, &blocks, &default_config(), "");
         assert!(result.contains("code-error"));
         assert!(result.contains("NameError"));
     }
 
+    #[test]
+    fn test_parse_code_attrs_options() {
+        let attrs = parse_code_attrs(
+            r#"python echo=false output="asis" error=fail collapse=true caption="Sales, by month""#,
+        );
+        let options = attrs.options;
+        assert_eq!(options.echo, Some(false));
+        assert_eq!(options.output, Some(OutputMode::Asis));
+        assert_eq!(options.error, Some(ErrorMode::Fail));
+        assert_eq!(options.collapse, Some(true));
+        assert_eq!(options.caption.as_deref(), Some("Sales, by month"));
+
+        let options = parse_code_attrs(r#"bash output=false error=maybe on_error="fail""#).options;
+        assert_eq!(options.on_error, Some(OnError::Fail));
+        assert_eq!(options.output, Some(OutputMode::Hide));
+        assert_eq!(options.error, None);
+        assert_eq!(parse_code_attrs("bash").options, BlockOptions::default());
+    }
+
+    fn optioned_block(attrs: &str, output: &str, error: Option<&str>) -> ExecutableBlock {
+        ExecutableBlock {
+            language: "python".into(),
+            source: "print('src')".into(),
+            file_ref: None,
+            deps: Vec::new(),
+            options: parse_code_attrs(&format!("python {attrs}")).options,
+            data: Default::default(),
+            output: Some(output.into()),
+            error: error.map(String::from),
+            viz: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_replace_exec_block_options() {
+        let render = |block: ExecutableBlock| {
+            replace_exec_placeholders("<!-- EXEC_BLOCK_0 -->", &[block], &default_config(), "")
+        };
+
+        let html = render(optioned_block("echo=false", "out", None));
+        assert!(
+            !html.contains("src") && html.contains("code-output"),
+            "{html}"
+        );
+
+        let html = render(optioned_block("output=false", "out", Some("warned")));
+        assert!(html.contains("src") && !html.contains("out<"), "{html}");
+        assert!(html.contains("warned"), "{html}");
+
+        // Nothing left to show renders nothing
+        assert_eq!(
+            render(optioned_block("echo=false output=false", "out", None)),
+            ""
+        );
+
+        let html = render(optioned_block("error=hide", "out", Some("warned")));
+        assert!(!html.contains("warned"), "{html}");
+
+        let html = render(optioned_block(
+            r#"collapse=true caption="Fig <1>""#,
+            "out",
+            None,
+        ));
+        assert!(
+            html.contains(r#"<details class="code-fold"><summary>Code</summary>"#),
+            "{html}"
+        );
+        assert!(
+            html.contains(r#"<div class="code-caption">Fig &lt;1&gt;</div>"#),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn test_replace_exec_asis_output_is_markdown() {
+        let block = optioned_block(
+            r#"echo=false output="asis""#,
+            "| a | b |\n|---|---|\n| 1 | **2** |\n",
+            None,
+        );
+        let html =
+            replace_exec_placeholders("<!-- EXEC_BLOCK_0 -->", &[block], &default_config(), "");
+        assert!(!html.contains("code-block-executed"), "{html}");
+        assert!(html.contains("<table>"), "{html}");
+        assert!(html.contains("<strong>2</strong>"), "{html}");
+    }
+
     #[test]
     fn test_render_external_link_escapes_attributes() {
         let mut config = default_config();
@@ -626,6 +1527,9 @@ mod tests {
             language: "python".into(),
             source: "import matplotlib".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: Some(String::new()),
             error: None,
             viz: vec![VizOutput {
@@ -633,7 +1537,7 @@ mod tests {
                 data: "data:image/png;base64,abc123".into(),
             }],
         }];
-        let result = replace_exec_placeholders(html, &blocks, &default_config());
+        let result = replace_exec_placeholders(html, &blocks, &default_config(), "");
         assert!(result.contains("code-viz"));
         assert!(result.contains(r#"<img src="data:image/png;base64,abc123""#));
         assert!(result.contains(r#"alt="Plot output""#));
@@ -646,6 +1550,9 @@ mod tests {
             language: "python".into(),
             source: "import plotly".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: Some(String::new()),
             error: None,
             viz: vec![VizOutput {
@@ -653,11 +1560,35 @@ mod tests {
                 data: "<div id=\"plotly\">chart</div>".into(),
             }],
         }];
-        let result = replace_exec_placeholders(html, &blocks, &default_config());
+        let result = replace_exec_placeholders(html, &blocks, &default_config(), "");
         assert!(result.contains("code-viz"));
         assert!(result.contains("<div id=\"plotly\">chart</div>"));
     }
 
+    #[test]
+    fn test_replace_exec_with_viz_markdown() {
+        let html = "<!-- EXEC_BLOCK_0 -->";
+        let blocks = vec![ExecutableBlock {
+            language: "python".into(),
+            source: "Report()".into(),
+            file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
+            output: Some(String::new()),
+            error: None,
+            viz: vec![VizOutput {
+                kind: "markdown".into(),
+                data: "**bold**".into(),
+            }],
+        }];
+        let result = replace_exec_placeholders(html, &blocks, &default_config(), "");
+        assert!(
+            result.contains(r#"<div class="code-viz"><p><strong>bold</strong>"#),
+            "got: {result}"
+        );
+    }
+
     #[test]
     fn test_replace_exec_with_output_and_viz() {
         let html = "<!-- EXEC_BLOCK_0 -->";
@@ -665,6 +1596,9 @@ mod tests {
             language: "python".into(),
             source: "print('hello')".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: Some("hello\n".into()),
             error: None,
             viz: vec![VizOutput {
@@ -672,7 +1606,7 @@ mod tests {
                 data: "data:image/png;base64,xyz".into(),
             }],
         }];
-        let result = replace_exec_placeholders(html, &blocks, &default_config());
+        let result = replace_exec_placeholders(html, &blocks, &default_config(), "");
         // Both text output and viz should appear
         assert!(result.contains("code-output"));
         assert!(result.contains("code-viz"));
@@ -689,11 +1623,14 @@ mod tests {
             language: "python".into(),
             source: "print('hi')".into(),
             file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
             output: Some("hi\n".into()),
             error: None,
             viz: Vec::new(),
         }];
-        let result = replace_exec_placeholders(html, &blocks, &default_config());
+        let result = replace_exec_placeholders(html, &blocks, &default_config(), "");
         assert!(!result.contains("code-viz"));
     }
 
@@ -782,6 +1719,9 @@ mod tests {
                 language: "python".into(),
                 source: "print('a')".into(),
                 file_ref: None,
+                deps: Vec::new(),
+                options: Default::default(),
+                data: Default::default(),
                 output: Some("<!-- EXEC_BLOCK_1 -->".into()),
                 error: None,
                 viz: Vec::new(),
@@ -790,12 +1730,15 @@ mod tests {
                 language: "python".into(),
                 source: "print('b')".into(),
                 file_ref: None,
+                deps: Vec::new(),
+                options: Default::default(),
+                data: Default::default(),
                 output: Some("BLOCK_ONE_OUTPUT".into()),
                 error: None,
                 viz: Vec::new(),
             },
         ];
-        let result = replace_exec_placeholders(html, &blocks, &default_config());
+        let result = replace_exec_placeholders(html, &blocks, &default_config(), "");
         assert!(
             result.contains("BLOCK_ONE_OUTPUT"),
             "block 1 must still be replaced at its original position"
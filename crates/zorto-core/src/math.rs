@@ -0,0 +1,1218 @@
+//! TeX math to MathML, for `$inline$` and `$$display$$` math in markdown.
+//!
+//! Covers the TeX commonly written in docs: letters, numbers and operators,
+//! `^` / `_` scripts, `\frac`, `\sqrt`, Greek letters and symbols, `\left` /
+//! `\right`, accents, `\overset` / `\underset`, `\text` and font commands,
+//! and matrix-like environments (`matrix`, `pmatrix`, `cases`, `aligned`,
+//! …). An unknown command renders as `<merror>` so the rest of the equation
+//! still shows.
+//!
+//! Every equation carries its TeX source as an `application/x-tex`
+//! annotation.
+//!
+//! The converter is kept in-tree rather than taken from a crate such as
+//! `latex2mathml` or `pulldown-latex` because a build renders every page's
+//! math unattended: it has to degrade per command (one unknown command is
+//! one `<merror>`, never a failed page), carry the TeX annotation, and stay
+//! free of dependencies. The cost is this file. New TeX is supported by
+//! extending the lookup functions (`operator`, `identifier`, `accent`, …)
+//! or `Parser::command`, with a test per construct.
+
+use crate::content::escape_xml;
+
+/// Render TeX `tex` as a `<math>` element.
+pub fn render_math(tex: &str, display: bool) -> String {
+    let body = mrow(Parser::new(tex).parse_all());
+    let display_attr = if display { " display=\"block\"" } else { "" };
+    format!(
+        "<math xmlns=\"http://www.w3.org/1998/Math/MathML\"{display_attr}>\
+         <semantics>{body}<annotation encoding=\"application/x-tex\">{}</annotation>\
+         </semantics></math>",
+        escape_xml(tex.trim())
+    )
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    /// `\name`, or `\` and one non-letter (`\,`, `\{`, `\\`).
+    Command(&'a str),
+    Open,
+    Close,
+    Sup,
+    Sub,
+    Amp,
+    Prime,
+    Letter(char),
+    Number(&'a str),
+    Symbol(char),
+}
+
+/// Font set by `\mathbf`, `\mathbb` and friends.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Variant {
+    Normal,
+    Italic,
+    Bold,
+    BoldItalic,
+    DoubleStruck,
+    Script,
+    Fraktur,
+    SansSerif,
+    Monospace,
+}
+
+struct Parser<'a> {
+    src: &'a str,
+    pos: usize,
+    variant: Option<Variant>,
+}
+
+impl<'a> Parser<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src,
+            pos: 0,
+            variant: None,
+        }
+    }
+
+    /// The next token, its start and its end, without consuming it.
+    fn lex(&self) -> Option<(Token<'a>, usize, usize)> {
+        let rest = &self.src[self.pos..];
+        let trimmed = rest.trim_start();
+        let start = self.pos + rest.len() - trimmed.len();
+        let c = trimmed.chars().next()?;
+        let (token, len) = match c {
+            '\\' => {
+                let after = &trimmed[1..];
+                let letters = after
+                    .find(|c: char| !c.is_ascii_alphabetic())
+                    .unwrap_or(after.len());
+                if letters > 0 {
+                    (Token::Command(&after[..letters]), 1 + letters)
+                } else if let Some(next) = after.chars().next() {
+                    let len = next.len_utf8();
+                    (Token::Command(&after[..len]), 1 + len)
+                } else {
+                    (Token::Symbol('\\'), 1)
+                }
+            }
+            '{' => (Token::Open, 1),
+            '}' => (Token::Close, 1),
+            '^' => (Token::Sup, 1),
+            '_' => (Token::Sub, 1),
+            '&' => (Token::Amp, 1),
+            '\'' => (Token::Prime, 1),
+            c if c.is_ascii_digit() => {
+                let digits = number_len(trimmed);
+                (Token::Number(&trimmed[..digits]), digits)
+            }
+            c if c.is_alphabetic() => (Token::Letter(c), c.len_utf8()),
+            c => (Token::Symbol(c), c.len_utf8()),
+        };
+        Some((token, start, start + len))
+    }
+
+    fn peek(&self) -> Option<Token<'a>> {
+        self.lex().map(|(token, _, _)| token)
+    }
+
+    fn advance(&mut self) {
+        if let Some((_, _, end)) = self.lex() {
+            self.pos = end;
+        }
+    }
+
+    /// Whole input; stray closing tokens are skipped.
+    fn parse_all(&mut self) -> Vec<String> {
+        let mut nodes = Vec::new();
+        loop {
+            nodes.extend(self.parse_row());
+            match self.peek() {
+                None => break,
+                Some(Token::Close) => {
+                    self.advance();
+                    nodes.push(merror("}"));
+                }
+                Some(Token::Command("end")) => {
+                    self.advance();
+                    self.raw_group();
+                }
+                // `&`, `\\` and `\right` outside of where they belong
+                Some(_) => self.advance(),
+            }
+        }
+        nodes
+    }
+
+    /// Nodes up to the end of the input or a `}`, `&`, `\\`, `\right` or
+    /// `\end`, which is not consumed.
+    fn parse_row(&mut self) -> Vec<String> {
+        let mut nodes = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Close | Token::Amp) => break,
+                Some(Token::Command("right" | "end" | "\\")) => break,
+                _ => {}
+            }
+            let node = self.parse_scripted();
+            if !node.is_empty() {
+                nodes.push(node);
+            }
+        }
+        nodes
+    }
+
+    /// An atom with its subscript, superscript and primes.
+    fn parse_scripted(&mut self) -> String {
+        let (base, limits) = self.parse_atom();
+        let mut sub = None;
+        let mut sup = None;
+        let mut primes = String::new();
+        loop {
+            match self.peek() {
+                Some(Token::Sub) if sub.is_none() => {
+                    self.advance();
+                    sub = Some(self.parse_argument());
+                }
+                Some(Token::Sup) if sup.is_none() => {
+                    self.advance();
+                    sup = Some(self.parse_argument());
+                }
+                Some(Token::Prime) if sup.is_none() => {
+                    self.advance();
+                    primes.push('′');
+                }
+                _ => break,
+            }
+        }
+        if !primes.is_empty() {
+            let prime = format!("<mo>{primes}</mo>");
+            sup = Some(match sup {
+                Some(sup) => format!("<mrow>{prime}{sup}</mrow>"),
+                None => prime,
+            });
+        }
+        let (sub_tag, sup_tag, both_tag) = if limits {
+            ("munder", "mover", "munderover")
+        } else {
+            ("msub", "msup", "msubsup")
+        };
+        let base = if base.is_empty() && (sub.is_some() || sup.is_some()) {
+            "<mrow></mrow>".to_string()
+        } else {
+            base
+        };
+        match (sub, sup) {
+            (None, None) => base,
+            (Some(sub), None) => format!("<{sub_tag}>{base}{sub}</{sub_tag}>"),
+            (None, Some(sup)) => format!("<{sup_tag}>{base}{sup}</{sup_tag}>"),
+            (Some(sub), Some(sup)) => format!("<{both_tag}>{base}{sub}{sup}</{both_tag}>"),
+        }
+    }
+
+    /// A command argument or script: a `{group}`, or a single atom (a
+    /// single digit of a number, as in `x^23`).
+    fn parse_argument(&mut self) -> String {
+        match self.lex() {
+            Some((Token::Open, _, _)) => self.parse_group(),
+            Some((Token::Number(n), start, _)) if n.len() > 1 => {
+                self.pos = start + 1;
+                self.number(&n[..1])
+            }
+            _ => {
+                let (node, _) = self.parse_atom();
+                if node.is_empty() {
+                    "<mrow></mrow>".to_string()
+                } else {
+                    node
+                }
+            }
+        }
+    }
+
+    /// `{…}`, the `{` not yet consumed.
+    fn parse_group(&mut self) -> String {
+        self.advance();
+        let nodes = self.parse_row();
+        if self.peek() == Some(Token::Close) {
+            self.advance();
+        }
+        format!("<mrow>{}</mrow>", nodes.concat())
+    }
+
+    /// One atom and whether its scripts go under and over it (`\sum`,
+    /// `\lim`). Tokens that end a row are left alone and give `""`.
+    fn parse_atom(&mut self) -> (String, bool) {
+        let Some(token) = self.peek() else {
+            return (String::new(), false);
+        };
+        match token {
+            Token::Close | Token::Amp | Token::Sub | Token::Sup => (String::new(), false),
+            Token::Command("right" | "end" | "\\") => (String::new(), false),
+            Token::Open => (self.parse_group(), false),
+            Token::Letter(c) => {
+                self.advance();
+                (self.identifier(c), false)
+            }
+            Token::Number(n) => {
+                self.advance();
+                (self.number(n), false)
+            }
+            Token::Prime => {
+                self.advance();
+                (mo("′"), false)
+            }
+            Token::Symbol(c) => {
+                self.advance();
+                (symbol(c), false)
+            }
+            Token::Command(name) => {
+                self.advance();
+                self.command(name)
+            }
+        }
+    }
+
+    fn command(&mut self, name: &str) -> (String, bool) {
+        if let Some((c, upright)) = greek(name) {
+            return (self.greek_identifier(c, upright), false);
+        }
+        if let Some(op) = operator(name) {
+            return (fence_or_mo(op), false);
+        }
+        if let Some(c) = identifier(name) {
+            return (format!("<mi>{c}</mi>"), false);
+        }
+        if let Some(op) = large_operator(name) {
+            return (mo(op), true);
+        }
+        if let Some(op) = integral(name) {
+            return (mo(op), false);
+        }
+        if let Some((text, limits)) = function_name(name) {
+            return (format!("<mi>{text}</mi>"), limits);
+        }
+        if let Some(width) = space(name) {
+            return (format!("<mspace width=\"{width}\"/>"), false);
+        }
+        if let Some(variant) = font(name) {
+            let outer = self.variant.replace(variant);
+            let arg = self.parse_argument();
+            self.variant = outer;
+            return (arg, false);
+        }
+        if let Some((accent, stretchy)) = accent(name) {
+            let arg = self.parse_argument();
+            return (
+                format!(
+                    "<mover accent=\"true\">{arg}<mo stretchy=\"{stretchy}\">{accent}</mo></mover>"
+                ),
+                false,
+            );
+        }
+        let node = match name {
+            "frac" | "dfrac" | "tfrac" | "cfrac" => {
+                let num = self.parse_argument();
+                let den = self.parse_argument();
+                format!("<mfrac>{num}{den}</mfrac>")
+            }
+            "binom" | "dbinom" | "tbinom" => {
+                let top = self.parse_argument();
+                let bottom = self.parse_argument();
+                format!(
+                    "<mrow><mo>(</mo><mfrac linethickness=\"0\">{top}{bottom}</mfrac><mo>)</mo></mrow>"
+                )
+            }
+            "sqrt" => match self.raw_optional() {
+                Some(index) => {
+                    let index = mrow(Parser::new(&index).parse_all());
+                    let radicand = self.parse_argument();
+                    format!("<mroot>{radicand}{index}</mroot>")
+                }
+                None => format!("<msqrt>{}</msqrt>", self.parse_argument()),
+            },
+            "text" | "textrm" | "textnormal" | "mbox" | "hbox" => self.text(None),
+            "textbf" => self.text(Some("bold")),
+            "textit" => self.text(Some("italic")),
+            "texttt" => self.text(Some("monospace")),
+            "operatorname" => format!("<mi>{}</mi>", escape_xml(self.raw_group().trim())),
+            "underline" => format!(
+                "<munder accentunder=\"true\">{}<mo stretchy=\"true\">_</mo></munder>",
+                self.parse_argument()
+            ),
+            "overset" | "stackrel" => {
+                let over = self.parse_argument();
+                let base = self.parse_argument();
+                format!("<mover>{base}{over}</mover>")
+            }
+            "underset" => {
+                let under = self.parse_argument();
+                let base = self.parse_argument();
+                format!("<munder>{base}{under}</munder>")
+            }
+            "overbrace" => {
+                let arg = self.parse_argument();
+                return (
+                    format!("<mover>{arg}<mo stretchy=\"true\">⏞</mo></mover>"),
+                    true,
+                );
+            }
+            "underbrace" => {
+                let arg = self.parse_argument();
+                return (
+                    format!("<munder>{arg}<mo stretchy=\"true\">⏟</mo></munder>"),
+                    true,
+                );
+            }
+            "left" => {
+                let open = self.delimiter();
+                let inner = self.parse_row();
+                let close = if self.peek() == Some(Token::Command("right")) {
+                    self.advance();
+                    self.delimiter()
+                } else {
+                    String::new()
+                };
+                format!(
+                    "<mrow>{}{}{}</mrow>",
+                    stretchy_fence(&open),
+                    inner.concat(),
+                    stretchy_fence(&close)
+                )
+            }
+            "big" | "bigl" | "bigr" | "bigm" => sized_fence(&self.delimiter(), "1.2em"),
+            "Big" | "Bigl" | "Bigr" | "Bigm" => sized_fence(&self.delimiter(), "1.8em"),
+            "bigg" | "biggl" | "biggr" | "biggm" => sized_fence(&self.delimiter(), "2.4em"),
+            "Bigg" | "Biggl" | "Biggr" | "Biggm" => sized_fence(&self.delimiter(), "3em"),
+            "not" => {
+                let (next, _) = self.parse_atom();
+                match next.as_str() {
+                    "<mo>=</mo>" => mo("≠"),
+                    "<mo>∈</mo>" => mo("∉"),
+                    _ => match next.strip_suffix("</mo>") {
+                        Some(op) => format!("{op}\u{338}</mo>"),
+                        None => next,
+                    },
+                }
+            }
+            "pmod" => format!(
+                "<mrow><mspace width=\"1em\"/><mo>(</mo><mi>mod</mi><mspace width=\"0.333em\"/>{}<mo>)</mo></mrow>",
+                self.parse_argument()
+            ),
+            "bmod" | "mod" => mo("mod"),
+            "begin" => self.environment(),
+            // Layout hints with no MathML counterpart here
+            "displaystyle" | "textstyle" | "limits" | "nolimits" | "nonumber" | "notag" => {
+                String::new()
+            }
+            _ => merror(&format!("\\{name}")),
+        };
+        (node, false)
+    }
+
+    /// `\begin{name}…\end{name}` as an `<mtable>`, `\begin` consumed.
+    fn environment(&mut self) -> String {
+        let name = self.raw_group();
+        let name = name.trim().trim_end_matches('*');
+        let spec = if name == "array" || name == "alignat" {
+            self.raw_group()
+        } else {
+            String::new()
+        };
+
+        let mut rows: Vec<Vec<String>> = vec![Vec::new()];
+        loop {
+            let cell = self.parse_row();
+            rows.last_mut().unwrap().push(cell.concat());
+            match self.peek() {
+                Some(Token::Amp) => self.advance(),
+                Some(Token::Command("\\")) => {
+                    self.advance();
+                    rows.push(Vec::new());
+                }
+                Some(Token::Command("end")) => {
+                    self.advance();
+                    self.raw_group();
+                    break;
+                }
+                Some(Token::Close) => self.advance(),
+                _ => break,
+            }
+        }
+        // A trailing `\\` leaves an empty last row
+        if rows.len() > 1 && rows.last().is_some_and(|row| row.concat().is_empty()) {
+            rows.pop();
+        }
+
+        let align = match name {
+            "cases" | "dcases" => Some("left left".to_string()),
+            "aligned" | "align" | "alignat" | "split" | "eqnarray" => {
+                Some("right left".to_string())
+            }
+            "array" => Some(
+                spec.chars()
+                    .filter_map(|c| match c {
+                        'l' => Some("left"),
+                        'c' => Some("center"),
+                        'r' => Some("right"),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            ),
+            _ => None,
+        };
+        let mut table = String::from("<mtable");
+        if let Some(align) = align.filter(|a| !a.is_empty()) {
+            table.push_str(&format!(" columnalign=\"{align}\""));
+        }
+        if matches!(name, "aligned" | "align" | "split" | "gathered" | "gather") {
+            table.push_str(" displaystyle=\"true\"");
+        }
+        table.push('>');
+        for row in rows {
+            table.push_str("<mtr>");
+            for cell in row {
+                table.push_str(&format!("<mtd>{cell}</mtd>"));
+            }
+            table.push_str("</mtr>");
+        }
+        table.push_str("</mtable>");
+
+        let (open, close) = match name {
+            "pmatrix" => ("(", ")"),
+            "bmatrix" => ("[", "]"),
+            "Bmatrix" => ("{", "}"),
+            "vmatrix" => ("|", "|"),
+            "Vmatrix" => ("‖", "‖"),
+            "cases" | "dcases" => ("{", ""),
+            _ => return table,
+        };
+        format!(
+            "<mrow>{}{table}{}</mrow>",
+            stretchy_fence(open),
+            stretchy_fence(close)
+        )
+    }
+
+    /// The delimiter after `\left`, `\right` or `\big`; `.` is none.
+    fn delimiter(&mut self) -> String {
+        let delim = match self.peek() {
+            Some(Token::Symbol('.')) => String::new(),
+            Some(Token::Symbol(c)) => c.to_string(),
+            Some(Token::Command(name)) => match operator(name) {
+                Some(op) => op.to_string(),
+                None => return String::new(),
+            },
+            _ => return String::new(),
+        };
+        self.advance();
+        delim
+    }
+
+    /// `\text{…}` and friends: the raw group as `<mtext>`.
+    fn text(&mut self, variant: Option<&str>) -> String {
+        // Leading and trailing spaces in `<mtext>` would be collapsed
+        let text = escape_xml(&self.raw_group()).replace(' ', "\u{a0}");
+        match variant {
+            Some(variant) => format!("<mtext mathvariant=\"{variant}\">{text}</mtext>"),
+            None => format!("<mtext>{text}</mtext>"),
+        }
+    }
+
+    /// The source inside the next `{…}`, or the next token's source if it
+    /// is not a group.
+    fn raw_group(&mut self) -> String {
+        let rest = &self.src[self.pos..];
+        let trimmed = rest.trim_start();
+        let start = self.pos + rest.len() - trimmed.len();
+        if !trimmed.starts_with('{') {
+            return match self.lex() {
+                Some((_, start, end)) => {
+                    self.pos = end;
+                    self.src[start..end].to_string()
+                }
+                None => String::new(),
+            };
+        }
+        let mut depth = 0;
+        let mut escaped = false;
+        for (i, c) in trimmed.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        self.pos = start + i + 1;
+                        return trimmed[1..i].to_string();
+                    }
+                }
+                _ => {}
+            }
+        }
+        // Unclosed: the rest of the input
+        self.pos = self.src.len();
+        trimmed[1..].to_string()
+    }
+
+    /// The source inside an optional `[…]` argument, if there is one.
+    ///
+    /// Brackets inside braces or escaped (`\]`) do not end it, and nested
+    /// optional arguments (`\sqrt[\sqrt[2]{3}]{x}`) keep their own brackets.
+    fn raw_optional(&mut self) -> Option<String> {
+        let rest = &self.src[self.pos..];
+        let trimmed = rest.trim_start();
+        let start = self.pos + rest.len() - trimmed.len();
+        if !trimmed.starts_with('[') {
+            return None;
+        }
+        let (mut brackets, mut braces) = (0, 0);
+        let mut chars = trimmed.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => {
+                    chars.next();
+                }
+                '{' => braces += 1,
+                '}' => braces -= 1,
+                '[' if braces == 0 => brackets += 1,
+                ']' if braces == 0 => {
+                    brackets -= 1;
+                    if brackets == 0 {
+                        self.pos = start + i + 1;
+                        return Some(trimmed[1..i].to_string());
+                    }
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn identifier(&self, c: char) -> String {
+        match self.variant {
+            None | Some(Variant::Italic) => format!("<mi>{}</mi>", escape_xml(&c.to_string())),
+            Some(Variant::Normal) => {
+                format!(
+                    "<mi mathvariant=\"normal\">{}</mi>",
+                    escape_xml(&c.to_string())
+                )
+            }
+            Some(variant) => match styled_char(c, variant) {
+                Some(styled) => format!("<mi>{styled}</mi>"),
+                None => format!("<mi>{}</mi>", escape_xml(&c.to_string())),
+            },
+        }
+    }
+
+    fn greek_identifier(&self, c: char, upright: bool) -> String {
+        match self.variant {
+            Some(Variant::Normal) => format!("<mi mathvariant=\"normal\">{c}</mi>"),
+            Some(Variant::Italic) => format!("<mi>{c}</mi>"),
+            _ if upright => format!("<mi mathvariant=\"normal\">{c}</mi>"),
+            _ => format!("<mi>{c}</mi>"),
+        }
+    }
+
+    fn number(&self, n: &str) -> String {
+        let styled: String = match self.variant {
+            Some(variant) => n
+                .chars()
+                .map(|c| styled_char(c, variant).unwrap_or(c))
+                .collect(),
+            None => n.to_string(),
+        };
+        format!("<mn>{styled}</mn>")
+    }
+}
+
+/// Length of the number at the start of `s`: digits, and a decimal point
+/// followed by digits.
+fn number_len(s: &str) -> usize {
+    let bytes = s.as_bytes();
+    let mut len = bytes.iter().take_while(|b| b.is_ascii_digit()).count();
+    if bytes.get(len) == Some(&b'.') && bytes.get(len + 1).is_some_and(|b| b.is_ascii_digit()) {
+        len += 1;
+        len += bytes[len..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+    }
+    len
+}
+
+fn mrow(nodes: Vec<String>) -> String {
+    if nodes.len() == 1 {
+        nodes.into_iter().next().unwrap()
+    } else {
+        format!("<mrow>{}</mrow>", nodes.concat())
+    }
+}
+
+fn mo(op: &str) -> String {
+    format!("<mo>{}</mo>", escape_xml(op))
+}
+
+fn merror(source: &str) -> String {
+    format!("<merror><mtext>{}</mtext></merror>", escape_xml(source))
+}
+
+fn is_fence(op: &str) -> bool {
+    matches!(
+        op,
+        "(" | ")" | "[" | "]" | "{" | "}" | "|" | "‖" | "⟨" | "⟩" | "⌊" | "⌋" | "⌈" | "⌉"
+    )
+}
+
+/// An operator; bare fences keep their size, as in TeX.
+fn fence_or_mo(op: &str) -> String {
+    if is_fence(op) {
+        format!("<mo stretchy=\"false\">{}</mo>", escape_xml(op))
+    } else {
+        mo(op)
+    }
+}
+
+fn stretchy_fence(op: &str) -> String {
+    if op.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<mo fence=\"true\" stretchy=\"true\">{}</mo>",
+            escape_xml(op)
+        )
+    }
+}
+
+fn sized_fence(op: &str, size: &str) -> String {
+    format!(
+        "<mo minsize=\"{size}\" maxsize=\"{size}\">{}</mo>",
+        escape_xml(op)
+    )
+}
+
+fn symbol(c: char) -> String {
+    match c {
+        '-' => mo("−"),
+        '*' => mo("∗"),
+        '~' => "<mspace width=\"0.333em\"/>".to_string(),
+        c => fence_or_mo(&c.to_string()),
+    }
+}
+
+/// Greek letter for `\name`, and whether it is upright (capitals).
+fn greek(name: &str) -> Option<(char, bool)> {
+    let lower = match name {
+        "alpha" => 'α',
+        "beta" => 'β',
+        "gamma" => 'γ',
+        "delta" => 'δ',
+        "epsilon" => 'ϵ',
+        "varepsilon" => 'ε',
+        "zeta" => 'ζ',
+        "eta" => 'η',
+        "theta" => 'θ',
+        "vartheta" => 'ϑ',
+        "iota" => 'ι',
+        "kappa" => 'κ',
+        "lambda" => 'λ',
+        "mu" => 'μ',
+        "nu" => 'ν',
+        "xi" => 'ξ',
+        "omicron" => 'ο',
+        "pi" => 'π',
+        "varpi" => 'ϖ',
+        "rho" => 'ρ',
+        "varrho" => 'ϱ',
+        "sigma" => 'σ',
+        "varsigma" => 'ς',
+        "tau" => 'τ',
+        "upsilon" => 'υ',
+        "phi" => 'ϕ',
+        "varphi" => 'φ',
+        "chi" => 'χ',
+        "psi" => 'ψ',
+        "omega" => 'ω',
+        _ => {
+            let upper = match name {
+                "Gamma" => 'Γ',
+                "Delta" => 'Δ',
+                "Theta" => 'Θ',
+                "Lambda" => 'Λ',
+                "Xi" => 'Ξ',
+                "Pi" => 'Π',
+                "Sigma" => 'Σ',
+                "Upsilon" => 'Υ',
+                "Phi" => 'Φ',
+                "Psi" => 'Ψ',
+                "Omega" => 'Ω',
+                _ => return None,
+            };
+            return Some((upper, true));
+        }
+    };
+    Some((lower, false))
+}
+
+fn operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "times" => "×",
+        "cdot" => "⋅",
+        "pm" => "±",
+        "mp" => "∓",
+        "div" => "÷",
+        "ast" => "∗",
+        "star" => "⋆",
+        "circ" => "∘",
+        "bullet" => "∙",
+        "leq" | "le" => "≤",
+        "geq" | "ge" => "≥",
+        "neq" | "ne" => "≠",
+        "lt" => "<",
+        "gt" => ">",
+        "approx" => "≈",
+        "equiv" => "≡",
+        "sim" => "∼",
+        "simeq" => "≃",
+        "cong" => "≅",
+        "propto" => "∝",
+        "ll" => "≪",
+        "gg" => "≫",
+        "prec" => "≺",
+        "succ" => "≻",
+        "preceq" => "⪯",
+        "succeq" => "⪰",
+        "triangleq" => "≜",
+        "coloneqq" => "≔",
+        "in" => "∈",
+        "notin" => "∉",
+        "ni" => "∋",
+        "subset" => "⊂",
+        "subseteq" => "⊆",
+        "supset" => "⊃",
+        "supseteq" => "⊇",
+        "cup" => "∪",
+        "cap" => "∩",
+        "setminus" => "∖",
+        "sqcup" => "⊔",
+        "uplus" => "⊎",
+        "wedge" | "land" => "∧",
+        "vee" | "lor" => "∨",
+        "neg" | "lnot" => "¬",
+        "oplus" => "⊕",
+        "otimes" => "⊗",
+        "odot" => "⊙",
+        "to" | "rightarrow" => "→",
+        "leftarrow" | "gets" => "←",
+        "leftrightarrow" => "↔",
+        "Rightarrow" => "⇒",
+        "Leftarrow" => "⇐",
+        "Leftrightarrow" => "⇔",
+        "longrightarrow" => "⟶",
+        "longleftarrow" => "⟵",
+        "implies" | "Longrightarrow" => "⟹",
+        "impliedby" | "Longleftarrow" => "⟸",
+        "iff" | "Longleftrightarrow" => "⟺",
+        "mapsto" => "↦",
+        "uparrow" => "↑",
+        "downarrow" => "↓",
+        "forall" => "∀",
+        "exists" => "∃",
+        "nexists" => "∄",
+        "mid" => "∣",
+        "parallel" => "∥",
+        "perp" => "⊥",
+        "vdash" => "⊢",
+        "models" => "⊨",
+        "therefore" => "∴",
+        "because" => "∵",
+        "cdots" => "⋯",
+        "ldots" | "dots" => "…",
+        "vdots" => "⋮",
+        "ddots" => "⋱",
+        "colon" => ":",
+        "langle" => "⟨",
+        "rangle" => "⟩",
+        "lfloor" => "⌊",
+        "rfloor" => "⌋",
+        "lceil" => "⌈",
+        "rceil" => "⌉",
+        "vert" | "lvert" | "rvert" => "|",
+        "Vert" | "lVert" | "rVert" | "|" => "‖",
+        "{" | "lbrace" => "{",
+        "}" | "rbrace" => "}",
+        "&" => "&",
+        "%" => "%",
+        "#" => "#",
+        _ => return None,
+    })
+}
+
+fn identifier(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "infty" => "∞",
+        "partial" => "∂",
+        "nabla" => "∇",
+        "emptyset" | "varnothing" => "∅",
+        "hbar" => "ℏ",
+        "ell" => "ℓ",
+        "Re" => "ℜ",
+        "Im" => "ℑ",
+        "aleph" => "ℵ",
+        "wp" => "℘",
+        "angle" => "∠",
+        "triangle" => "△",
+        "top" => "⊤",
+        "bot" => "⊥",
+        "prime" => "′",
+        "imath" => "ı",
+        "jmath" => "ȷ",
+        "dagger" => "†",
+        "ddagger" => "‡",
+        "$" => "$",
+        "_" => "_",
+        _ => return None,
+    })
+}
+
+/// Operators whose scripts go under and over them.
+fn large_operator(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "sum" => "∑",
+        "prod" => "∏",
+        "coprod" => "∐",
+        "bigcup" => "⋃",
+        "bigcap" => "⋂",
+        "bigvee" => "⋁",
+        "bigwedge" => "⋀",
+        "bigoplus" => "⨁",
+        "bigotimes" => "⨂",
+        "bigsqcup" => "⨆",
+        _ => return None,
+    })
+}
+
+fn integral(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "int" => "∫",
+        "iint" => "∬",
+        "iiint" => "∭",
+        "oint" => "∮",
+        _ => return None,
+    })
+}
+
+/// Upright function name for `\name`, and whether its scripts go under and
+/// over it.
+fn function_name(name: &str) -> Option<(&'static str, bool)> {
+    Some(match name {
+        "sin" => ("sin", false),
+        "cos" => ("cos", false),
+        "tan" => ("tan", false),
+        "cot" => ("cot", false),
+        "sec" => ("sec", false),
+        "csc" => ("csc", false),
+        "arcsin" => ("arcsin", false),
+        "arccos" => ("arccos", false),
+        "arctan" => ("arctan", false),
+        "sinh" => ("sinh", false),
+        "cosh" => ("cosh", false),
+        "tanh" => ("tanh", false),
+        "coth" => ("coth", false),
+        "log" => ("log", false),
+        "ln" => ("ln", false),
+        "lg" => ("lg", false),
+        "exp" => ("exp", false),
+        "arg" => ("arg", false),
+        "deg" => ("deg", false),
+        "dim" => ("dim", false),
+        "ker" => ("ker", false),
+        "hom" => ("hom", false),
+        "lim" => ("lim", true),
+        "liminf" => ("lim inf", true),
+        "limsup" => ("lim sup", true),
+        "max" => ("max", true),
+        "min" => ("min", true),
+        "sup" => ("sup", true),
+        "inf" => ("inf", true),
+        "det" => ("det", true),
+        "gcd" => ("gcd", true),
+        "Pr" => ("Pr", true),
+        _ => return None,
+    })
+}
+
+fn space(name: &str) -> Option<&'static str> {
+    Some(match name {
+        "," | "thinspace" => "0.1667em",
+        ":" | ">" | "medspace" => "0.2222em",
+        ";" | "thickspace" => "0.2778em",
+        "!" => "-0.1667em",
+        " " => "0.333em",
+        "quad" => "1em",
+        "qquad" => "2em",
+        _ => return None,
+    })
+}
+
+fn font(name: &str) -> Option<Variant> {
+    Some(match name {
+        "mathrm" | "mathup" => Variant::Normal,
+        "mathit" => Variant::Italic,
+        "mathbf" => Variant::Bold,
+        "boldsymbol" | "bm" => Variant::BoldItalic,
+        "mathbb" => Variant::DoubleStruck,
+        "mathcal" | "mathscr" => Variant::Script,
+        "mathfrak" => Variant::Fraktur,
+        "mathsf" => Variant::SansSerif,
+        "mathtt" => Variant::Monospace,
+        _ => return None,
+    })
+}
+
+/// Accent character for `\name`, and whether it stretches over its
+/// argument.
+fn accent(name: &str) -> Option<(&'static str, bool)> {
+    Some(match name {
+        "hat" => ("^", false),
+        "widehat" => ("^", true),
+        "tilde" => ("~", false),
+        "widetilde" => ("~", true),
+        "bar" => ("‾", false),
+        "overline" => ("‾", true),
+        "vec" => ("→", false),
+        "overrightarrow" => ("→", true),
+        "dot" => ("˙", false),
+        "ddot" => ("¨", false),
+        "check" => ("ˇ", false),
+        "breve" => ("˘", false),
+        "acute" => ("´", false),
+        "grave" => ("`", false),
+        _ => return None,
+    })
+}
+
+/// `c` in the Unicode mathematical alphanumeric style for `variant`, if
+/// it has one.
+fn styled_char(c: char, variant: Variant) -> Option<char> {
+    // (capital A, small a, digit 0, letters that live elsewhere in Unicode)
+    let (upper, lower, digit, holes): (u32, u32, Option<u32>, &[(char, char)]) = match variant {
+        Variant::Bold => (0x1D400, 0x1D41A, Some(0x1D7CE), &[]),
+        Variant::BoldItalic => (0x1D468, 0x1D482, None, &[]),
+        Variant::DoubleStruck => (
+            0x1D538,
+            0x1D552,
+            Some(0x1D7D8),
+            &[
+                ('C', 'ℂ'),
+                ('H', 'ℍ'),
+                ('N', 'ℕ'),
+                ('P', 'ℙ'),
+                ('Q', 'ℚ'),
+                ('R', 'ℝ'),
+                ('Z', 'ℤ'),
+            ],
+        ),
+        Variant::Script => (
+            0x1D49C,
+            0x1D4B6,
+            None,
+            &[
+                ('B', 'ℬ'),
+                ('E', 'ℰ'),
+                ('F', 'ℱ'),
+                ('H', 'ℋ'),
+                ('I', 'ℐ'),
+                ('L', 'ℒ'),
+                ('M', 'ℳ'),
+                ('R', 'ℛ'),
+                ('e', 'ℯ'),
+                ('g', 'ℊ'),
+                ('o', 'ℴ'),
+            ],
+        ),
+        Variant::Fraktur => (
+            0x1D504,
+            0x1D51E,
+            None,
+            &[('C', 'ℭ'), ('H', 'ℌ'), ('I', 'ℑ'), ('R', 'ℜ'), ('Z', 'ℨ')],
+        ),
+        Variant::SansSerif => (0x1D5A0, 0x1D5BA, Some(0x1D7E2), &[]),
+        Variant::Monospace => (0x1D670, 0x1D68A, Some(0x1D7F6), &[]),
+        Variant::Normal | Variant::Italic => return None,
+    };
+    if let Some(&(_, styled)) = holes.iter().find(|(plain, _)| *plain == c) {
+        return Some(styled);
+    }
+    let code = if c.is_ascii_uppercase() {
+        upper + (c as u32 - 'A' as u32)
+    } else if c.is_ascii_lowercase() {
+        lower + (c as u32 - 'a' as u32)
+    } else if c.is_ascii_digit() {
+        digit? + (c as u32 - '0' as u32)
+    } else {
+        return None;
+    };
+    char::from_u32(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The MathML body of `tex`, without the `<math>` wrapper.
+    fn body(tex: &str) -> String {
+        mrow(Parser::new(tex).parse_all())
+    }
+
+    #[test]
+    fn test_render_math_wrappers() {
+        let inline = render_math("x < 1", false);
+        assert!(inline.starts_with("<math xmlns=\"http://www.w3.org/1998/Math/MathML\">"));
+        assert!(inline.contains("<mo>&lt;</mo>"));
+        assert!(
+            inline.contains("<annotation encoding=\"application/x-tex\">x &lt; 1</annotation>")
+        );
+
+        let display = render_math("x", true);
+        assert!(display.contains("display=\"block\""));
+    }
+
+    #[test]
+    fn test_tokens() {
+        assert_eq!(
+            body("2x + 3.14"),
+            "<mrow><mn>2</mn><mi>x</mi><mo>+</mo><mn>3.14</mn></mrow>"
+        );
+        assert_eq!(body("a - b"), "<mrow><mi>a</mi><mo>−</mo><mi>b</mi></mrow>");
+        assert_eq!(
+            body(r"\alpha \Gamma"),
+            "<mrow><mi>α</mi><mi mathvariant=\"normal\">Γ</mi></mrow>"
+        );
+    }
+
+    #[test]
+    fn test_scripts() {
+        assert_eq!(body("x^2"), "<msup><mi>x</mi><mn>2</mn></msup>");
+        assert_eq!(
+            body("x_i^{n+1}"),
+            "<msubsup><mi>x</mi><mi>i</mi><mrow><mi>n</mi><mo>+</mo><mn>1</mn></mrow></msubsup>"
+        );
+        // One digit per bare script, as in TeX
+        assert_eq!(
+            body("x^23"),
+            "<mrow><msup><mi>x</mi><mn>2</mn></msup><mn>3</mn></mrow>"
+        );
+        assert_eq!(body("f'"), "<msup><mi>f</mi><mo>′</mo></msup>");
+        assert_eq!(
+            body(r"\sum_{i=1}^n i"),
+            "<mrow><munderover><mo>∑</mo><mrow><mi>i</mi><mo>=</mo><mn>1</mn></mrow><mi>n</mi></munderover><mi>i</mi></mrow>"
+        );
+        assert_eq!(
+            body(r"\int_0^1"),
+            "<msubsup><mo>∫</mo><mn>0</mn><mn>1</mn></msubsup>"
+        );
+        assert_eq!(
+            body(r"\lim_{x \to 0}"),
+            "<munder><mi>lim</mi><mrow><mi>x</mi><mo>→</mo><mn>0</mn></mrow></munder>"
+        );
+    }
+
+    #[test]
+    fn test_fractions_roots_and_fences() {
+        assert_eq!(
+            body(r"\frac{a}{b}"),
+            "<mfrac><mrow><mi>a</mi></mrow><mrow><mi>b</mi></mrow></mfrac>"
+        );
+        assert_eq!(body(r"\sqrt 2"), "<msqrt><mn>2</mn></msqrt>");
+        assert_eq!(
+            body(r"\sqrt[3]{x}"),
+            "<mroot><mrow><mi>x</mi></mrow><mn>3</mn></mroot>"
+        );
+        assert_eq!(
+            body(r"\sqrt [n+1] x"),
+            "<mroot><mi>x</mi><mrow><mi>n</mi><mo>+</mo><mn>1</mn></mrow></mroot>"
+        );
+        assert_eq!(
+            body(r"\sqrt[\sqrt[2]{3}]{x}"),
+            "<mroot><mrow><mi>x</mi></mrow><mroot><mrow><mn>3</mn></mrow><mn>2</mn></mroot></mroot>"
+        );
+        // A bracket in braces belongs to the index
+        assert_eq!(
+            body(r"\sqrt[{]}]{x}"),
+            "<mroot><mrow><mi>x</mi></mrow><mrow><mo stretchy=\"false\">]</mo></mrow></mroot>"
+        );
+        // Without a closing bracket, `[` is the radicand
+        assert_eq!(
+            body(r"\sqrt[3"),
+            "<mrow><msqrt><mo stretchy=\"false\">[</mo></msqrt><mn>3</mn></mrow>"
+        );
+        assert_eq!(
+            body(r"\left( x \right."),
+            "<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mi>x</mi></mrow>"
+        );
+        assert_eq!(
+            body("(x)"),
+            "<mrow><mo stretchy=\"false\">(</mo><mi>x</mi><mo stretchy=\"false\">)</mo></mrow>"
+        );
+    }
+
+    #[test]
+    fn test_overset_and_underset() {
+        assert_eq!(
+            body(r"\overset{def}{=}"),
+            "<mover><mrow><mo>=</mo></mrow><mrow><mi>d</mi><mi>e</mi><mi>f</mi></mrow></mover>"
+        );
+        assert_eq!(
+            body(r"\underset{n \to \infty}{\longrightarrow}"),
+            "<munder><mrow><mo>⟶</mo></mrow><mrow><mi>n</mi><mo>→</mo><mi>∞</mi></mrow></munder>"
+        );
+        assert_eq!(body(r"\stackrel{?}{=}"), body(r"\overset{?}{=}"));
+    }
+
+    #[test]
+    fn test_text_and_fonts() {
+        assert_eq!(
+            body(r"\text{if } x"),
+            "<mrow><mtext>if\u{a0}</mtext><mi>x</mi></mrow>"
+        );
+        assert_eq!(body(r"\mathbb{R}"), "<mrow><mi>ℝ</mi></mrow>");
+        assert_eq!(body(r"\mathbf{v}"), "<mrow><mi>𝐯</mi></mrow>");
+        assert_eq!(
+            body(r"\mathrm{d}x"),
+            "<mrow><mrow><mi mathvariant=\"normal\">d</mi></mrow><mi>x</mi></mrow>"
+        );
+        assert_eq!(body(r"\operatorname{rank}"), "<mi>rank</mi>");
+    }
+
+    #[test]
+    fn test_environments() {
+        assert_eq!(
+            body(r"\begin{pmatrix} a & b \\ c & d \end{pmatrix}"),
+            "<mrow><mo fence=\"true\" stretchy=\"true\">(</mo><mtable>\
+             <mtr><mtd><mi>a</mi></mtd><mtd><mi>b</mi></mtd></mtr>\
+             <mtr><mtd><mi>c</mi></mtd><mtd><mi>d</mi></mtd></mtr>\
+             </mtable><mo fence=\"true\" stretchy=\"true\">)</mo></mrow>"
+        );
+        let cases = body(r"\begin{cases} 1 & x > 0 \\ 0 & \text{otherwise} \\ \end{cases}");
+        assert!(cases.contains("<mtable columnalign=\"left left\">"));
+        assert_eq!(cases.matches("<mtr>").count(), 2);
+    }
+
+    #[test]
+    fn test_unknown_and_unbalanced() {
+        assert_eq!(
+            body(r"\foo x"),
+            "<mrow><merror><mtext>\\foo</mtext></merror><mi>x</mi></mrow>"
+        );
+        // Unbalanced input still renders
+        assert_eq!(
+            body(r"\frac{a"),
+            "<mfrac><mrow><mi>a</mi></mrow><mrow></mrow></mfrac>"
+        );
+        assert!(body("a}").contains("<merror>"));
+    }
+}
@@ -0,0 +1,454 @@
+//! Jupyter notebooks (`.ipynb`) as content pages.
+//!
+//! A notebook is converted to markdown when it is loaded: markdown cells are
+//! copied as-is, raw cells are passed through, and code cells become
+//! executable fences (```` ```{python} ````) in the kernel's language. The
+//! outputs saved in the notebook are kept alongside the page and fill those
+//! blocks at render time, so a notebook renders exactly as it was last run
+//! without executing anything. With `[execute] notebooks = true` (or
+//! `execute = true` under the notebook's `[extra]`), the code cells run
+//! through the regular executor instead.
+//!
+//! Frontmatter comes from a leading raw cell holding a `+++` TOML block, or
+//! from a `zorto` object in the notebook metadata. Without a title, the first
+//! `# Heading` of the notebook is used.
+
+use regex::Regex;
+use serde::Deserialize;
+use std::sync::LazyLock;
+
+use crate::config::ExecuteConfig;
+use crate::content::{Frontmatter, parse_frontmatter};
+use crate::execute::{ExecutableBlock, VizOutput};
+
+static ANSI_ESCAPE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\x1b\[[0-9;]*[A-Za-z]").unwrap());
+
+/// A notebook converted to a markdown page body.
+#[derive(Debug)]
+pub struct Notebook {
+    /// Frontmatter from the notebook's raw cell or metadata.
+    pub frontmatter: Frontmatter,
+    /// Markdown body, with code cells as executable fences.
+    pub body: String,
+    /// Code cells and their stored outputs, in notebook order.
+    pub cells: Vec<CodeCell>,
+}
+
+/// A code cell and the outputs saved with it.
+#[derive(Debug, Clone)]
+pub struct CodeCell {
+    /// Kernel language of the cell (e.g. `"python"`).
+    pub language: String,
+    /// Cell source.
+    pub source: String,
+    /// Text written to stdout and plain-text results.
+    pub output: Option<String>,
+    /// Text written to stderr and any exception traceback.
+    pub error: Option<String>,
+    /// Rich outputs: images and HTML.
+    pub viz: Vec<VizOutput>,
+}
+
+#[derive(Deserialize)]
+struct RawNotebook {
+    nbformat: u32,
+    #[serde(default)]
+    metadata: serde_json::Value,
+    cells: Vec<RawCell>,
+}
+
+#[derive(Deserialize)]
+struct RawCell {
+    cell_type: String,
+    #[serde(deserialize_with = "multiline")]
+    source: String,
+    #[serde(default)]
+    outputs: Vec<RawOutput>,
+}
+
+#[derive(Deserialize)]
+struct RawOutput {
+    output_type: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default, deserialize_with = "multiline")]
+    text: String,
+    #[serde(default)]
+    data: serde_json::Map<String, serde_json::Value>,
+    #[serde(default)]
+    ename: String,
+    #[serde(default)]
+    evalue: String,
+    #[serde(default)]
+    traceback: Vec<String>,
+}
+
+/// nbformat stores multiline strings either whole or as a list of lines.
+fn multiline<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Multiline {
+        One(String),
+        Lines(Vec<String>),
+    }
+    Ok(match Multiline::deserialize(deserializer)? {
+        Multiline::One(s) => s,
+        Multiline::Lines(lines) => lines.concat(),
+    })
+}
+
+fn multiline_value(value: &serde_json::Value) -> String {
+    match value {
+        serde_json::Value::String(s) => s.clone(),
+        serde_json::Value::Array(lines) => lines.iter().filter_map(|l| l.as_str()).collect(),
+        _ => String::new(),
+    }
+}
+
+/// Parse a notebook file's JSON into a page body and stored outputs.
+///
+/// # Errors
+///
+/// Returns an error if the JSON is not an nbformat 4 notebook or its
+/// frontmatter is invalid.
+pub fn parse_notebook(json: &str) -> anyhow::Result<Notebook> {
+    let raw: RawNotebook =
+        serde_json::from_str(json).map_err(|e| anyhow::anyhow!("invalid notebook: {e}"))?;
+    if raw.nbformat != 4 {
+        anyhow::bail!(
+            "unsupported notebook format version {} (expected 4)",
+            raw.nbformat
+        );
+    }
+    let language = kernel_language(&raw.metadata);
+
+    let mut cells = raw.cells.into_iter().peekable();
+    let mut frontmatter = None;
+    if let Some(first) = cells.peek()
+        && first.cell_type == "raw"
+        && first.source.trim_start().starts_with("+++")
+    {
+        let (fm, _) = parse_frontmatter(first.source.trim_start())?;
+        frontmatter = Some(fm);
+        cells.next();
+    }
+    let frontmatter = match (frontmatter, raw.metadata.get("zorto")) {
+        (Some(fm), _) => fm,
+        (None, Some(meta)) => serde_json::from_value(meta.clone())
+            .map_err(|e| anyhow::anyhow!("invalid `zorto` notebook metadata: {e}"))?,
+        (None, None) => Frontmatter::default(),
+    };
+
+    let mut body = String::new();
+    let mut code_cells = Vec::new();
+    for cell in cells {
+        match cell.cell_type.as_str() {
+            "markdown" | "raw" => push_chunk(&mut body, &cell.source),
+            "code" => {
+                if cell.source.trim().is_empty() {
+                    continue;
+                }
+                let fence = fence_for(&cell.source);
+                push_chunk(
+                    &mut body,
+                    &format!(
+                        "{fence}{{{language}}}\n{}\n{fence}",
+                        cell.source.trim_end_matches('\n')
+                    ),
+                );
+                code_cells.push(code_cell(&language, cell));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Notebook {
+        frontmatter,
+        body,
+        cells: code_cells,
+    })
+}
+
+/// Whether a notebook's code cells should be executed rather than rendered
+/// from their stored outputs. `[extra] execute` overrides `[execute] notebooks`.
+pub fn should_execute(extra: &serde_json::Value, config: &ExecuteConfig) -> bool {
+    extra
+        .get("execute")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(config.notebooks)
+}
+
+/// Fill executable blocks with the stored outputs of the code cells they
+/// were generated from. Blocks that did not come from a code cell (e.g. an
+/// executable fence inside a markdown cell) are left empty.
+pub fn apply_stored_outputs(cells: &[CodeCell], blocks: &mut [ExecutableBlock]) {
+    let mut cells = cells.iter().peekable();
+    for block in blocks {
+        if let Some(cell) = cells.next_if(|cell| {
+            cell.language == block.language && cell.source.trim_end() == block.source.trim_end()
+        }) {
+            block.output = cell.output.clone();
+            block.error = cell.error.clone();
+            block.viz = cell.viz.clone();
+        }
+    }
+}
+
+/// The notebook's kernel language, defaulting to Python.
+fn kernel_language(metadata: &serde_json::Value) -> String {
+    metadata
+        .pointer("/kernelspec/language")
+        .or_else(|| metadata.pointer("/language_info/name"))
+        .and_then(|v| v.as_str())
+        .map(|lang| lang.to_lowercase())
+        .unwrap_or_else(|| "python".to_string())
+}
+
+/// Append a block of markdown, separated from the previous one by a blank line.
+fn push_chunk(body: &mut String, chunk: &str) {
+    let chunk = chunk.trim_matches('\n');
+    if chunk.is_empty() {
+        return;
+    }
+    if !body.is_empty() {
+        body.push_str("\n\n");
+    }
+    body.push_str(chunk);
+}
+
+/// A backtick fence longer than any backtick run in `source`.
+fn fence_for(source: &str) -> String {
+    let longest = source.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    "`".repeat(longest.max(2) + 1)
+}
+
+fn code_cell(language: &str, cell: RawCell) -> CodeCell {
+    let mut output = String::new();
+    let mut error = String::new();
+    let mut viz = Vec::new();
+    for out in cell.outputs {
+        match out.output_type.as_str() {
+            "stream" if out.name.as_deref() == Some("stderr") => error.push_str(&out.text),
+            "stream" => output.push_str(&out.text),
+            "execute_result" | "display_data" => {
+                if let Some(v) = rich_output(&out.data) {
+                    viz.push(v);
+                } else if let Some(text) = out.data.get("text/plain") {
+                    output.push_str(&multiline_value(text));
+                    if !output.ends_with('\n') {
+                        output.push('\n');
+                    }
+                }
+            }
+            "error" => {
+                let traceback = if out.traceback.is_empty() {
+                    format!("{}: {}", out.ename, out.evalue)
+                } else {
+                    ANSI_ESCAPE_RE
+                        .replace_all(&out.traceback.join("\n"), "")
+                        .into_owned()
+                };
+                if !error.is_empty() && !error.ends_with('\n') {
+                    error.push('\n');
+                }
+                error.push_str(&traceback);
+            }
+            _ => {}
+        }
+    }
+    CodeCell {
+        language: language.to_string(),
+        source: cell.source,
+        output: (!output.is_empty()).then_some(output),
+        error: (!error.is_empty()).then_some(error),
+        viz,
+    }
+}
+
+/// The richest displayable representation in a MIME bundle.
+fn rich_output(data: &serde_json::Map<String, serde_json::Value>) -> Option<VizOutput> {
+    if let Some(html) = data.get("text/html") {
+        return Some(VizOutput {
+            kind: "html".into(),
+            data: multiline_value(html),
+        });
+    }
+    if let Some(svg) = data.get("image/svg+xml") {
+        return Some(VizOutput {
+            kind: "html".into(),
+            data: multiline_value(svg),
+        });
+    }
+    for mime in ["image/png", "image/jpeg", "image/gif"] {
+        if let Some(image) = data.get(mime) {
+            let base64: String = multiline_value(image)
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .collect();
+            return Some(VizOutput {
+                kind: "img".into(),
+                data: format!("data:{mime};base64,{base64}"),
+            });
+        }
+    }
+    data.get("text/markdown").map(|markdown| VizOutput {
+        kind: "markdown".into(),
+        data: multiline_value(markdown),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn notebook(cells: serde_json::Value) -> String {
+        serde_json::json!({
+            "nbformat": 4,
+            "nbformat_minor": 5,
+            "metadata": {"kernelspec": {"language": "python", "name": "python3"}},
+            "cells": cells,
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_notebook_body_and_outputs() {
+        let nb = parse_notebook(&notebook(serde_json::json!([
+            {"cell_type": "markdown", "metadata": {}, "source": ["# Analysis\n", "\n", "Intro."]},
+            {"cell_type": "code", "metadata": {}, "execution_count": 1, "source": "x = 1\nprint(x)",
+             "outputs": [
+                {"output_type": "stream", "name": "stdout", "text": ["1\n"]},
+                {"output_type": "display_data", "metadata": {},
+                 "data": {"image/png": "iVBORw0\nKGgo=\n", "text/plain": ["<Figure>"]}},
+             ]},
+            {"cell_type": "code", "metadata": {}, "execution_count": 2, "source": "x",
+             "outputs": [{"output_type": "execute_result", "execution_count": 2, "metadata": {},
+                          "data": {"text/plain": ["1"]}}]},
+            {"cell_type": "code", "metadata": {}, "execution_count": null, "source": "", "outputs": []},
+        ])))
+        .unwrap();
+
+        assert_eq!(
+            nb.body,
+            "# Analysis\n\nIntro.\n\n```{python}\nx = 1\nprint(x)\n```\n\n```{python}\nx\n```"
+        );
+        assert_eq!(nb.cells.len(), 2);
+        assert_eq!(nb.cells[0].output.as_deref(), Some("1\n"));
+        assert_eq!(nb.cells[0].viz[0].kind, "img");
+        assert_eq!(
+            nb.cells[0].viz[0].data,
+            "data:image/png;base64,iVBORw0KGgo="
+        );
+        assert_eq!(nb.cells[1].output.as_deref(), Some("1\n"));
+    }
+
+    #[test]
+    fn test_parse_notebook_errors_and_html() {
+        let nb = parse_notebook(&notebook(serde_json::json!([
+            {"cell_type": "code", "metadata": {}, "source": "df",
+             "outputs": [{"output_type": "execute_result", "metadata": {},
+                          "data": {"text/html": ["<table>", "</table>"], "text/plain": "df"}}]},
+            {"cell_type": "code", "metadata": {}, "source": "1/0",
+             "outputs": [{"output_type": "error", "ename": "ZeroDivisionError",
+                          "evalue": "division by zero",
+                          "traceback": ["\u{1b}[0;31mZeroDivisionError\u{1b}[0m: division by zero"]}]},
+        ])))
+        .unwrap();
+        assert_eq!(nb.cells[0].viz[0].kind, "html");
+        assert_eq!(nb.cells[0].viz[0].data, "<table></table>");
+        assert!(nb.cells[0].output.is_none());
+        assert_eq!(
+            nb.cells[1].error.as_deref(),
+            Some("ZeroDivisionError: division by zero")
+        );
+    }
+
+    #[test]
+    fn test_parse_notebook_markdown_output() {
+        let nb = parse_notebook(&notebook(serde_json::json!([
+            {"cell_type": "code", "metadata": {}, "source": "Markdown('# Hi')",
+             "outputs": [{"output_type": "execute_result", "metadata": {},
+                          "data": {"text/markdown": ["# Hi"], "text/plain": "<Markdown>"}}]},
+        ])))
+        .unwrap();
+        assert_eq!(nb.cells[0].viz[0].kind, "markdown");
+        assert_eq!(nb.cells[0].viz[0].data, "# Hi");
+    }
+
+    #[test]
+    fn test_parse_notebook_frontmatter_sources() {
+        let nb = parse_notebook(&notebook(serde_json::json!([
+            {"cell_type": "raw", "metadata": {}, "source": "+++\ntitle = \"From raw\"\ndate = 2026-01-02\n+++\n"},
+            {"cell_type": "markdown", "metadata": {}, "source": "Body"},
+        ])))
+        .unwrap();
+        assert_eq!(nb.frontmatter.title.as_deref(), Some("From raw"));
+        assert_eq!(nb.body, "Body");
+
+        let json = serde_json::json!({
+            "nbformat": 4,
+            "metadata": {"zorto": {"title": "From metadata", "tags": ["data"]}},
+            "cells": [],
+        })
+        .to_string();
+        let nb = parse_notebook(&json).unwrap();
+        assert_eq!(nb.frontmatter.title.as_deref(), Some("From metadata"));
+        assert!(nb.frontmatter.rest.contains_key("tags"));
+
+        let err = parse_notebook(r#"{"nbformat": 3, "cells": []}"#).unwrap_err();
+        assert!(err.to_string().contains("version 3"));
+    }
+
+    #[test]
+    fn test_parse_notebook_kernel_language_and_fences() {
+        let json = serde_json::json!({
+            "nbformat": 4,
+            "metadata": {"language_info": {"name": "R"}},
+            "cells": [{"cell_type": "code", "metadata": {}, "source": "cat('```')", "outputs": []}],
+        })
+        .to_string();
+        let nb = parse_notebook(&json).unwrap();
+        assert_eq!(nb.body, "````{r}\ncat('```')\n````");
+        assert_eq!(nb.cells[0].language, "r");
+    }
+
+    #[test]
+    fn test_apply_stored_outputs_skips_unmatched_blocks() {
+        let nb = parse_notebook(&notebook(serde_json::json!([
+            {"cell_type": "code", "metadata": {}, "source": "print('a')",
+             "outputs": [{"output_type": "stream", "name": "stdout", "text": "a\n"}]},
+        ])))
+        .unwrap();
+        let block = |source: &str| ExecutableBlock {
+            language: "python".into(),
+            source: source.into(),
+            file_ref: None,
+            deps: Vec::new(),
+            options: Default::default(),
+            data: Default::default(),
+            output: None,
+            error: None,
+            viz: Vec::new(),
+        };
+        let mut blocks = vec![block("print('from markdown')\n"), block("print('a')\n")];
+        apply_stored_outputs(&nb.cells, &mut blocks);
+        assert!(blocks[0].output.is_none());
+        assert_eq!(blocks[1].output.as_deref(), Some("a\n"));
+    }
+
+    #[test]
+    fn test_should_execute_override() {
+        let mut config = ExecuteConfig::default();
+        assert!(!should_execute(&serde_json::json!({}), &config));
+        assert!(should_execute(
+            &serde_json::json!({"execute": true}),
+            &config
+        ));
+        config.notebooks = true;
+        assert!(!should_execute(
+            &serde_json::json!({"execute": false}),
+            &config
+        ));
+    }
+}
@@ -0,0 +1,180 @@
+//! Order-preserving parallel map for the build pipeline.
+//!
+//! Pages and sections are rendered independently of each other, so the build
+//! spreads them across scoped worker threads. Results always come back in
+//! input order, which keeps output and the first reported error deterministic
+//! regardless of scheduling.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Number of worker threads to use for `len` items: one per core, or
+/// `min_workers` if that is more, but never more than there are items.
+fn worker_count(len: usize, min_workers: usize) -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .max(min_workers)
+        .min(len)
+}
+
+/// Apply `f` to every item on a pool of scoped threads, returning results in
+/// the same order as `items`.
+///
+/// Falls back to a plain sequential map for zero or one item, or on a single
+/// core. A panic in `f` is propagated to the caller.
+pub(crate) fn par_map<T, R, F>(items: &[T], f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    par_map_with(items, 0, f)
+}
+
+/// Like [`par_map`], but with at least `min_workers` threads even on fewer
+/// cores, for work that mostly waits on child processes.
+pub(crate) fn par_map_with<T, R, F>(items: &[T], min_workers: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = worker_count(items.len(), min_workers);
+    if workers <= 1 {
+        return items.iter().map(f).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let chunks: Vec<Vec<(usize, R)>> = std::thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut out = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else { break };
+                        out.push((i, f(item)));
+                    }
+                    out
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+
+    let mut results: Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+    for (i, r) in chunks.into_iter().flatten() {
+        results[i] = Some(r);
+    }
+    results
+        .into_iter()
+        .map(|r| r.expect("every item is processed exactly once"))
+        .collect()
+}
+
+/// Like [`par_map`], but items for which `sequential` returns `true` are
+/// processed first, one at a time in input order, before the rest run in
+/// parallel. Results are still returned in input order.
+///
+/// Used for work that shares state between items, where the order the items
+/// touch that state must not depend on scheduling. The parallel part runs on
+/// at least `min_workers` threads, as in [`par_map_with`].
+pub(crate) fn par_map_partitioned<T, R, S, F>(
+    items: &[T],
+    min_workers: usize,
+    sequential: S,
+    f: F,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    S: Fn(&T) -> bool,
+    F: Fn(&T) -> R + Sync,
+{
+    let (ordered, concurrent): (Vec<usize>, Vec<usize>) =
+        (0..items.len()).partition(|&i| sequential(&items[i]));
+    let mut results: Vec<Option<R>> = (0..items.len()).map(|_| None).collect();
+    for i in ordered {
+        results[i] = Some(f(&items[i]));
+    }
+    let concurrent_results = par_map_with(&concurrent, min_workers, |&i| f(&items[i]));
+    for (i, r) in concurrent.into_iter().zip(concurrent_results) {
+        results[i] = Some(r);
+    }
+    results
+        .into_iter()
+        .map(|r| r.expect("every item is processed exactly once"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_par_map_preserves_order() {
+        let items: Vec<usize> = (0..500).collect();
+        let out = par_map(&items, |i| i * 2);
+        assert_eq!(out, items.iter().map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_par_map_empty_and_single() {
+        let empty: Vec<u8> = Vec::new();
+        assert!(par_map(&empty, |b| *b).is_empty());
+        assert_eq!(par_map(&[7], |n| n + 1), vec![8]);
+    }
+
+    #[test]
+    fn test_par_map_first_error_is_deterministic() {
+        let items: Vec<usize> = (0..200).collect();
+        for _ in 0..10 {
+            let results = par_map(&items, |&i| {
+                if i % 50 == 49 {
+                    Err(format!("item {i}"))
+                } else {
+                    Ok(i)
+                }
+            });
+            let first = results.into_iter().collect::<Result<Vec<_>, _>>();
+            assert_eq!(first.unwrap_err(), "item 49");
+        }
+    }
+
+    #[test]
+    fn test_par_map_partitioned_runs_sequential_items_in_order() {
+        let items: Vec<usize> = (0..100).collect();
+        let order = std::sync::Mutex::new(Vec::new());
+        let out = par_map_partitioned(
+            &items,
+            0,
+            |i| i % 10 == 0,
+            |&i| {
+                if i % 10 == 0 {
+                    order.lock().unwrap().push(i);
+                }
+                i * 3
+            },
+        );
+        assert_eq!(out, items.iter().map(|i| i * 3).collect::<Vec<_>>());
+        assert_eq!(
+            order.into_inner().unwrap(),
+            (0..100).step_by(10).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn test_par_map_with_min_workers_runs_items_together() {
+        // Every item waits for all the others, so this only finishes if
+        // each one has its own thread, whatever the core count.
+        let barrier = std::sync::Barrier::new(4);
+        let out = par_map_with(&[1, 2, 3, 4], 4, |&i| {
+            barrier.wait();
+            i
+        });
+        assert_eq!(out, vec![1, 2, 3, 4]);
+    }
+}
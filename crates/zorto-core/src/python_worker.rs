@@ -0,0 +1,301 @@
+//! Out-of-process Python execution (`[execute] python_mode = "subprocess"`).
+//!
+//! Each page gets its own worker: an external interpreter running
+//! [`WORKER_CODE`], which executes blocks in a persistent namespace so later
+//! blocks see earlier definitions, like a notebook kernel. Requests and
+//! responses are JSON lines over the worker's stdin/stdout. Unlike the
+//! embedded interpreter, a worker can be killed when a block exceeds
+//! `[execute] timeout_seconds` without affecting the rest of the build. On
+//! Unix the worker leads its own process group, so killing it also kills
+//! any processes its blocks started.
+//!
+//! With `[execute.sandbox] enable = true` the worker runs in a sandbox (see
+//! [`crate::sandbox`]) for its whole life, and its blocks run in the
+//! sandbox's private directory.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, Command, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use crate::config::ExecuteConfig;
+use crate::execute::{BLOCK_PRELUDE_CODE, PythonOutput, VIZ_DETECTION_CODE, VizOutput, find_venv};
+use crate::sandbox::{self, Sandbox};
+
+/// The worker's main loop. The viz detection code arrives as `sys.argv[1]`
+/// and the block prelude as `sys.argv[2]`.
+///
+/// The protocol uses private duplicates of fds 0 and 1. The real fds are
+/// pointed at `/dev/null` and stderr, so neither user code (`input()`) nor
+/// child processes it spawns can read requests or corrupt responses.
+const WORKER_CODE: &str = r#"
+import io, json, os, sys, traceback
+_viz_code, _prelude_code = sys.argv[1], sys.argv[2]
+sys.argv = ['']
+_requests = os.fdopen(os.dup(0), 'r', encoding='utf-8')
+_responses = os.fdopen(os.dup(1), 'w', encoding='utf-8')
+os.dup2(os.open(os.devnull, os.O_RDONLY), 0)
+os.dup2(2, 1)
+sys.stdin = open(os.devnull)
+_ns = {'__name__': '__main__', '__builtins__': __builtins__}
+for _line in _requests:
+    _req = json.loads(_line)
+    _out, _err = io.StringIO(), io.StringIO()
+    _error, _viz, _data = None, [], '{}'
+    try:
+        os.chdir(_req['cwd'])
+        sys.stdout, sys.stderr = _out, _err
+        exec(_prelude_code, _ns)
+        try:
+            _ns['__zorto_internal_run__'](_req['code'])
+        except BaseException as _e:
+            _error = ''.join(traceback.format_exception_only(type(_e), _e)).strip()
+        try:
+            _data = _ns['__zorto_internal_module__']._take()
+        except Exception as _e:
+            _error = _error or ''.join(traceback.format_exception_only(type(_e), _e)).strip()
+        if _error is None:
+            try:
+                exec(_viz_code, _ns)
+                _viz = [list(_v) for _v in _ns.get('__zorto_internal_viz_output__', [])]
+            except Exception as _e:
+                print(f'zorto: viz detection error: {_e}', file=sys.__stderr__)
+    finally:
+        sys.stdout, sys.stderr = sys.__stdout__, sys.__stderr__
+    _responses.write(json.dumps({
+        'stdout': _out.getvalue(), 'stderr': _err.getvalue(), 'error': _error, 'viz': _viz,
+        'data': _data,
+    }) + '\n')
+    _responses.flush()
+"#;
+
+/// One block's result as reported by the worker.
+#[derive(Deserialize)]
+struct Response {
+    stdout: String,
+    stderr: String,
+    error: Option<String>,
+    viz: Vec<(String, String)>,
+    /// Values passed to `zorto.set()`, as a JSON object.
+    data: String,
+}
+
+/// A running Python worker. Killed, with its process group, when dropped.
+pub(crate) struct PythonWorker {
+    child: Child,
+    stdin: ChildStdin,
+    responses: Receiver<std::io::Result<String>>,
+    /// The worker's sandbox, which must outlive it.
+    sandbox: Option<Sandbox>,
+    /// Set once the exited worker has been reaped, after which its pid may
+    /// belong to another process.
+    reaped: bool,
+}
+
+impl PythonWorker {
+    /// Start a worker with the configured interpreter, sandboxed when
+    /// `[execute.sandbox]` is enabled.
+    pub(crate) fn spawn(config: &ExecuteConfig, site_root: &Path) -> anyhow::Result<Self> {
+        let python = interpreter(config, site_root);
+        let sandbox = if config.sandbox.enable {
+            Some(Sandbox::new(&config.sandbox)?)
+        } else {
+            None
+        };
+        let mut cmd = Command::new(&python);
+        cmd.arg("-u")
+            .arg("-c")
+            .arg(WORKER_CODE)
+            .arg(VIZ_DETECTION_CODE)
+            .arg(BLOCK_PRELUDE_CODE)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::inherit())
+            // Not the build's own directory, which the embedded interpreter
+            // may have moved into a directory that no longer exists
+            .current_dir(site_root);
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            cmd.process_group(0);
+        }
+        if let Some(sandbox) = &sandbox {
+            sandbox.apply(&mut cmd);
+        }
+        // No display in a worker; matplotlib must not try to open windows.
+        let backend_set = match &sandbox {
+            Some(_) => cmd.get_envs().any(|(name, _)| name == "MPLBACKEND"),
+            None => std::env::var_os("MPLBACKEND").is_some(),
+        };
+        if !backend_set {
+            cmd.env("MPLBACKEND", "Agg");
+        }
+        let mut child = cmd.spawn().map_err(|e| match &sandbox {
+            _ if e.kind() == std::io::ErrorKind::NotFound => anyhow::anyhow!(
+                "Python interpreter `{python}` not found. Install Python 3 or set `[execute] python` in config.toml."
+            ),
+            Some(sandbox) => sandbox.spawn_error(e),
+            None => anyhow::anyhow!("failed to start Python worker `{python}`: {e}"),
+        })?;
+
+        let stdin = child
+            .stdin
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("failed to open Python worker stdin"))?;
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow::anyhow!("failed to capture Python worker stdout"))?;
+        // Responses are read on a thread so the caller can wait with a timeout.
+        let (tx, responses) = mpsc::channel();
+        std::thread::spawn(move || {
+            for line in BufReader::new(stdout).lines() {
+                if tx.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Self {
+            child,
+            stdin,
+            responses,
+            sandbox,
+            reaped: false,
+        })
+    }
+
+    /// Execute `code` with `cwd` as the working directory, returning stdout,
+    /// stderr (including any exception), captured visualizations and the
+    /// values passed to `zorto.set()`.
+    ///
+    /// On a timeout the worker is killed and an error is returned; the
+    /// worker must not be used afterwards. A sandboxed worker runs `code` in
+    /// its sandbox's directory instead of `cwd`.
+    pub(crate) fn run(
+        &mut self,
+        code: &str,
+        cwd: &Path,
+        timeout_seconds: u64,
+    ) -> anyhow::Result<PythonOutput> {
+        let cwd = self.sandbox.as_ref().map_or(cwd, Sandbox::dir);
+        let request = serde_json::json!({ "code": code, "cwd": cwd });
+        if writeln!(self.stdin, "{request}")
+            .and_then(|()| self.stdin.flush())
+            .is_err()
+        {
+            return Err(self.exited());
+        }
+
+        let response = if timeout_seconds == 0 {
+            self.responses
+                .recv()
+                .map_err(|_| RecvTimeoutError::Disconnected)
+        } else {
+            self.responses
+                .recv_timeout(Duration::from_secs(timeout_seconds))
+        };
+        let line = match response {
+            Ok(line) => {
+                line.map_err(|e| anyhow::anyhow!("failed to read from Python worker: {e}"))?
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                self.kill();
+                anyhow::bail!(
+                    "execution exceeded the {timeout_seconds}-second timeout (configurable via `[execute] timeout_seconds` in config.toml; set to 0 to disable)"
+                );
+            }
+            Err(RecvTimeoutError::Disconnected) => return Err(self.exited()),
+        };
+
+        let response: Response = serde_json::from_str(&line)
+            .map_err(|e| anyhow::anyhow!("invalid response from Python worker: {e}"))?;
+        if let Some(max) = self.sandbox.as_ref().and_then(Sandbox::output_limit)
+            && (response.stdout.len() + response.stderr.len()) as u64 > max
+        {
+            anyhow::bail!(
+                "output exceeded {max} bytes (configurable via `[execute.sandbox] max_output_bytes` in config.toml)"
+            );
+        }
+        let viz = response
+            .viz
+            .into_iter()
+            .map(|(kind, data)| VizOutput { kind, data })
+            .collect();
+        let stderr = match &response.error {
+            Some(error) => format!("{}\n{error}", response.stderr).trim().to_string(),
+            None => response.stderr,
+        };
+        let data = serde_json::from_str(&response.data)
+            .map_err(|e| anyhow::anyhow!("invalid response from Python worker: {e}"))?;
+        Ok(PythonOutput {
+            stdout: response.stdout,
+            stderr,
+            viz,
+            exception: response.error,
+            data,
+        })
+    }
+
+    /// Kill the worker and everything in its process group, and reap it.
+    fn kill(&mut self) {
+        #[cfg(unix)]
+        // SAFETY: kill(2) has no memory-safety preconditions. The worker
+        // has not been reaped, so its pid is still its process group's id.
+        unsafe {
+            libc::kill(-(self.child.id() as libc::pid_t), libc::SIGKILL);
+        }
+        #[cfg(not(unix))]
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        self.reaped = true;
+    }
+
+    /// Error for a worker that stopped responding because it exited.
+    fn exited(&mut self) -> anyhow::Error {
+        match sandbox::wait(&mut self.child, true) {
+            Ok(Some(exit)) => {
+                self.reaped = true;
+                match self
+                    .sandbox
+                    .as_ref()
+                    .and_then(|sandbox| sandbox.limit_error(exit, ""))
+                {
+                    Some(msg) => anyhow::anyhow!("{msg}"),
+                    None => anyhow::anyhow!("Python worker exited unexpectedly ({})", exit.status),
+                }
+            }
+            Ok(None) => anyhow::anyhow!("Python worker exited unexpectedly"),
+            Err(e) => anyhow::anyhow!("Python worker exited unexpectedly: {e}"),
+        }
+    }
+}
+
+impl Drop for PythonWorker {
+    fn drop(&mut self) {
+        if !self.reaped {
+            self.kill();
+        }
+    }
+}
+
+/// The interpreter to run: `[execute] python`, else the site's venv, else
+/// `python3`.
+fn interpreter(config: &ExecuteConfig, site_root: &Path) -> String {
+    if let Some(python) = &config.python {
+        return python.clone();
+    }
+    let venv_python = if cfg!(windows) {
+        Path::new("Scripts").join("python.exe")
+    } else {
+        Path::new("bin").join("python")
+    };
+    find_venv(site_root)
+        .map(|venv| venv.join(&venv_python))
+        .filter(|python| python.exists())
+        .map(|python| python.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "python3".to_string())
+}
@@ -0,0 +1,359 @@
+//! Isolation for subprocess code blocks (`{bash}`, `{sh}`, `{node}`, other
+//! configured languages, and the Python worker).
+//!
+//! With `[execute.sandbox] enable = true`, each block runs in a private
+//! temporary directory with a scrubbed environment and resource limits set
+//! via `setrlimit` in the child before `exec`. Network access can be removed
+//! by moving the child into fresh Linux user and network namespaces.
+
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus};
+use std::time::Duration;
+
+use crate::config::SandboxConfig;
+
+/// A prepared sandbox for one block. The temporary directory is removed when
+/// this is dropped, so it must outlive the child process.
+pub(crate) struct Sandbox {
+    dir: tempfile::TempDir,
+    config: SandboxConfig,
+}
+
+impl Sandbox {
+    /// Create a sandbox, failing early if the platform cannot honour `config`.
+    pub(crate) fn new(config: &SandboxConfig) -> anyhow::Result<Self> {
+        if !cfg!(unix) {
+            anyhow::bail!(
+                "sandboxed execution requires a Unix platform (set `[execute.sandbox] enable = false` in config.toml)"
+            );
+        }
+        if !config.allow_network && !cfg!(target_os = "linux") {
+            anyhow::bail!(
+                "network isolation requires Linux namespaces (set `[execute.sandbox] allow_network = true` in config.toml)"
+            );
+        }
+        let dir = tempfile::Builder::new()
+            .prefix("zorto-sandbox-")
+            .tempdir()
+            .map_err(|e| anyhow::anyhow!("failed to create sandbox directory: {e}"))?;
+        Ok(Self {
+            dir,
+            config: config.clone(),
+        })
+    }
+
+    /// The private working directory for the block.
+    pub(crate) fn dir(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Maximum bytes of combined stdout/stderr, if limited.
+    pub(crate) fn output_limit(&self) -> Option<u64> {
+        (self.config.max_output_bytes > 0).then_some(self.config.max_output_bytes)
+    }
+
+    /// Configure `cmd` to run inside the sandbox.
+    pub(crate) fn apply(&self, cmd: &mut Command) {
+        cmd.current_dir(self.dir());
+        cmd.env_clear();
+        for name in &self.config.env_allow {
+            if let Some(value) = std::env::var_os(name) {
+                cmd.env(name, value);
+            }
+        }
+        cmd.env("HOME", self.dir()).env("TMPDIR", self.dir());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::CommandExt;
+            let limits = Limits::from_config(&self.config);
+            // SAFETY: the closure runs between fork and exec and only makes
+            // async-signal-safe syscalls (getrlimit/setrlimit/unshare) on
+            // values captured by copy; it does not allocate or take locks.
+            unsafe {
+                cmd.pre_exec(move || limits.apply());
+            }
+        }
+    }
+
+    /// Turn a spawn failure into an actionable message.
+    pub(crate) fn spawn_error(&self, e: std::io::Error) -> anyhow::Error {
+        #[cfg(target_os = "linux")]
+        if !self.config.allow_network
+            && matches!(
+                e.raw_os_error(),
+                Some(libc::EPERM | libc::EINVAL | libc::ENOSPC | libc::EUSERS)
+            )
+        {
+            return anyhow::anyhow!(
+                "could not isolate the network ({e}): unprivileged user namespaces may be disabled on this system (set `[execute.sandbox] allow_network = true` in config.toml to run without network isolation)"
+            );
+        }
+        e.into()
+    }
+
+    /// Explain an exit caused by a sandbox limit, if any.
+    pub(crate) fn limit_error(&self, exit: Exit, stderr: &str) -> Option<String> {
+        let status = exit.status;
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            let cpu = self.config.cpu_seconds;
+            // RLIMIT_CPU sends SIGXCPU at the soft limit and SIGKILL at the
+            // hard limit. Any other SIGKILL (the OOM killer, a user) comes
+            // from a child that has not used up its CPU time.
+            let cpu_exceeded = cpu > 0
+                && match status.signal() {
+                    Some(libc::SIGXCPU) => true,
+                    Some(libc::SIGKILL) => exit.cpu_time >= Duration::from_secs(cpu),
+                    _ => false,
+                };
+            if cpu_exceeded {
+                return Some(format!(
+                    "execution exceeded the CPU limit of {cpu} seconds (configurable via `[execute.sandbox] cpu_seconds` in config.toml)"
+                ));
+            }
+            if status.signal() == Some(libc::SIGXFSZ) {
+                return Some(format!(
+                    "block wrote a file larger than {} bytes (configurable via `[execute.sandbox] max_file_bytes` in config.toml)",
+                    self.config.max_file_bytes
+                ));
+            }
+        }
+        // Hitting RLIMIT_DATA makes allocations fail rather than sending a
+        // signal, and the child decides what happens next, so this is a
+        // guess from its stderr: a failure that does not print one of the
+        // messages `is_allocation_failure` knows keeps its own error.
+        let memory = self.config.memory_mb;
+        if memory > 0 && !status.success() && is_allocation_failure(stderr) {
+            return Some(format!(
+                "execution exceeded the memory limit of {memory} MiB (configurable via `[execute.sandbox] memory_mb` in config.toml)"
+            ));
+        }
+        None
+    }
+}
+
+/// How a child exited.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Exit {
+    pub(crate) status: ExitStatus,
+    /// User plus system CPU time the child used (zero where unknown).
+    pub(crate) cpu_time: Duration,
+}
+
+/// Reap `child` once it has exited, like [`Child::try_wait`] (or, with
+/// `block`, [`Child::wait`]), also collecting the CPU time it used.
+///
+/// Once this returns an exit, `child` must not be waited on or killed again:
+/// `Child` does not know it was reaped, and its pid may have been reused.
+pub(crate) fn wait(child: &mut Child, block: bool) -> std::io::Result<Option<Exit>> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        let pid = child.id() as libc::pid_t;
+        let options = if block { 0 } else { libc::WNOHANG };
+        let mut status = 0;
+        // SAFETY: an all-zero rusage is a valid value.
+        let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+        loop {
+            // SAFETY: `status` and `usage` are valid, writable out-parameters.
+            let ret = unsafe { libc::wait4(pid, &mut status, options, &mut usage) };
+            if ret == pid {
+                let time = |tv: libc::timeval| {
+                    Duration::from_secs(tv.tv_sec as u64) + Duration::from_micros(tv.tv_usec as u64)
+                };
+                return Ok(Some(Exit {
+                    status: ExitStatus::from_raw(status),
+                    cpu_time: time(usage.ru_utime) + time(usage.ru_stime),
+                }));
+            }
+            if ret == 0 {
+                return Ok(None);
+            }
+            let e = std::io::Error::last_os_error();
+            if e.kind() != std::io::ErrorKind::Interrupted {
+                return Err(e);
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let status = if block {
+            Some(child.wait()?)
+        } else {
+            child.try_wait()?
+        };
+        Ok(status.map(|status| Exit {
+            status,
+            cpu_time: Duration::ZERO,
+        }))
+    }
+}
+
+/// Whether stderr looks like bash, node or libc running out of memory.
+fn is_allocation_failure(stderr: &str) -> bool {
+    let stderr = stderr.to_lowercase();
+    [
+        "cannot allocate",
+        "out of memory",
+        "bad_alloc",
+        "allocation failed",
+    ]
+    .iter()
+    .any(|needle| stderr.contains(needle))
+}
+
+/// Resource limits applied in the child between fork and exec.
+#[cfg(unix)]
+#[derive(Clone, Copy)]
+struct Limits {
+    memory_bytes: u64,
+    cpu_seconds: u64,
+    file_bytes: u64,
+    isolate_network: bool,
+}
+
+#[cfg(unix)]
+impl Limits {
+    fn from_config(config: &SandboxConfig) -> Self {
+        Self {
+            memory_bytes: config.memory_mb.saturating_mul(1024 * 1024),
+            cpu_seconds: config.cpu_seconds,
+            file_bytes: config.max_file_bytes,
+            isolate_network: !config.allow_network,
+        }
+    }
+
+    fn apply(&self) -> std::io::Result<()> {
+        // RLIMIT_DATA rather than RLIMIT_AS: V8 reserves far more address
+        // space than it uses, so an address-space cap stops node starting.
+        if self.memory_bytes > 0 {
+            set_limit(libc::RLIMIT_DATA, self.memory_bytes, self.memory_bytes)?;
+        }
+        if self.cpu_seconds > 0 {
+            // One second of grace between SIGXCPU and SIGKILL.
+            set_limit(libc::RLIMIT_CPU, self.cpu_seconds, self.cpu_seconds + 1)?;
+        }
+        if self.file_bytes > 0 {
+            set_limit(libc::RLIMIT_FSIZE, self.file_bytes, self.file_bytes)?;
+        }
+        set_limit(libc::RLIMIT_CORE, 0, 0)?;
+        #[cfg(target_os = "linux")]
+        if self.isolate_network {
+            // A new network namespace has only a downed loopback interface.
+            // The user namespace makes this possible without privileges.
+            // SAFETY: unshare has no memory-safety preconditions.
+            if unsafe { libc::unshare(libc::CLONE_NEWUSER | libc::CLONE_NEWNET) } != 0 {
+                return Err(std::io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Lower a resource limit, never raising it above the current hard limit.
+#[cfg(unix)]
+fn set_limit(resource: RlimitResource, soft: u64, hard: u64) -> std::io::Result<()> {
+    let mut current = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: `current` is a valid, writable rlimit struct.
+    if unsafe { libc::getrlimit(resource, &mut current) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    let max = current.rlim_max;
+    let clamp = |v: u64| -> libc::rlim_t {
+        let v = v as libc::rlim_t;
+        if max == libc::RLIM_INFINITY {
+            v
+        } else {
+            v.min(max)
+        }
+    };
+    let limit = libc::rlimit {
+        rlim_cur: clamp(soft),
+        rlim_max: clamp(hard),
+    };
+    // SAFETY: `limit` is a valid rlimit struct.
+    if unsafe { libc::setrlimit(resource, &limit) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(all(unix, target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(not(all(unix, target_os = "linux", target_env = "gnu")))]
+#[cfg(unix)]
+type RlimitResource = libc::c_int;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_allocation_failure() {
+        assert!(is_allocation_failure(
+            "bash: xrealloc: cannot allocate 66719744 bytes"
+        ));
+        assert!(is_allocation_failure(
+            "terminate called after throwing an instance of 'St9bad_alloc'"
+        ));
+        assert!(!is_allocation_failure("command not found"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_sandbox_scrubs_environment_and_working_dir() {
+        let sandbox = Sandbox::new(&SandboxConfig {
+            enable: true,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut cmd = Command::new("bash");
+        cmd.arg("-c")
+            .arg("pwd; echo \"${CARGO_MANIFEST_DIR:-unset}\"; echo \"$HOME\"");
+        sandbox.apply(&mut cmd);
+        let out = cmd.output().unwrap();
+        let stdout = String::from_utf8(out.stdout).unwrap();
+        let lines: Vec<&str> = stdout.lines().collect();
+        let dir = sandbox.dir().canonicalize().unwrap();
+        assert_eq!(Path::new(lines[0]).canonicalize().unwrap(), dir);
+        assert_eq!(lines[1], "unset");
+        assert_eq!(Path::new(lines[2]), sandbox.dir());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_limit_error_ignores_external_sigkill() {
+        let sandbox = Sandbox::new(&SandboxConfig {
+            enable: true,
+            cpu_seconds: 5,
+            ..Default::default()
+        })
+        .unwrap();
+        let mut cmd = Command::new("sleep");
+        cmd.arg("30");
+        sandbox.apply(&mut cmd);
+        let mut child = cmd.spawn().unwrap();
+        assert!(wait(&mut child, false).unwrap().is_none());
+        child.kill().unwrap();
+        let exit = wait(&mut child, true).unwrap().unwrap();
+        assert!(exit.cpu_time < Duration::from_secs(5));
+        assert_eq!(sandbox.limit_error(exit, ""), None);
+
+        // The same signal after the CPU time ran out is the hard limit
+        let exhausted = Exit {
+            cpu_time: Duration::from_secs(6),
+            ..exit
+        };
+        assert!(
+            sandbox
+                .limit_error(exhausted, "")
+                .unwrap()
+                .contains("CPU limit of 5 seconds")
+        );
+    }
+}
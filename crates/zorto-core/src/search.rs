@@ -0,0 +1,411 @@
+//! Full-text search index generation.
+//!
+//! When `[search] enable = true`, the build writes `search_index.json` next
+//! to the site plus the `zorto-search.js` client that queries it in the
+//! browser. The index is a compact inverted index over rendered content:
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "sections": ["Blog", "Docs"],
+//!   "docs": [
+//!     { "t": "Title", "u": "/posts/hello/", "s": 0, "l": "en",
+//!       "h": ["Heading"], "d": "Snippet text..." }
+//!   ],
+//!   "terms": { "hello": [0, 13, 4, 1] }
+//! }
+//! ```
+//!
+//! - `docs[i].s` indexes into `sections` (the facet a result belongs to), or
+//!   is `null` for content outside any section.
+//! - `terms` maps each lowercase token to flat `[doc, weight, doc, weight, ...]`
+//!   postings. Weights favour titles over headings over descriptions over body.
+
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::LazyLock;
+
+use crate::config::SearchConfig;
+use crate::content::{self, Page, Section};
+
+/// File name of the browser search client in the output directory.
+pub const CLIENT_JS_NAME: &str = "zorto-search.js";
+
+/// Source of the browser search client.
+pub const CLIENT_JS: &str = include_str!("../themes/shared/search.js");
+
+/// Index format version, bumped on incompatible changes to the JSON layout.
+const INDEX_VERSION: u32 = 1;
+
+/// Weight of a token occurrence in the title.
+const TITLE_WEIGHT: u32 = 10;
+/// Weight of a token occurrence in a heading.
+const HEADING_WEIGHT: u32 = 5;
+/// Weight of a token occurrence in the description.
+const DESCRIPTION_WEIGHT: u32 = 3;
+/// Weight of a token occurrence in the body.
+const BODY_WEIGHT: u32 = 1;
+/// Cap on the body contribution, so long pages that repeat a word do not
+/// outrank a page that has it in the title.
+const MAX_BODY_WEIGHT: u32 = 5;
+
+/// Shortest token that is indexed.
+const MIN_TOKEN_CHARS: usize = 2;
+
+static HEADING_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?is)<h[1-6][^>]*>(.*?)</h[1-6]>").unwrap());
+static TAG_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?s)<[^>]*>").unwrap());
+/// Elements whose text is not prose: scripts, styles and inline SVG.
+static NON_TEXT_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)<script\b.*?</script>|<style\b.*?</style>|<svg\b.*?</svg>").unwrap()
+});
+
+/// One searchable page or section.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchDocument {
+    /// Title.
+    #[serde(rename = "t")]
+    pub title: String,
+    /// URL path relative to the site root (e.g. `"/posts/hello/"`).
+    #[serde(rename = "u")]
+    pub url: String,
+    /// Index into [`SearchIndex::sections`], if the content belongs to a section.
+    #[serde(rename = "s")]
+    pub section: Option<usize>,
+    /// Language code.
+    #[serde(rename = "l")]
+    pub lang: String,
+    /// Headings in document order.
+    #[serde(rename = "h")]
+    pub headings: Vec<String>,
+    /// Result snippet: the description, or the start of the body text.
+    #[serde(rename = "d")]
+    pub snippet: String,
+}
+
+/// A complete search index, serialized to `search_index.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchIndex {
+    pub version: u32,
+    /// Section titles used as result facets.
+    pub sections: Vec<String>,
+    pub docs: Vec<SearchDocument>,
+    /// Token → flat `[doc, weight, ...]` postings, sorted by doc.
+    pub terms: BTreeMap<String, Vec<u32>>,
+}
+
+impl SearchIndex {
+    /// Build an index over rendered pages and sections.
+    ///
+    /// Content opts out with `search = false` in its frontmatter `[extra]`.
+    /// Sections with an empty body are indexed by title only.
+    pub fn build(
+        pages: &HashMap<String, Page>,
+        sections: &HashMap<String, Section>,
+        config: &SearchConfig,
+    ) -> Self {
+        let mut index = SearchIndex {
+            version: INDEX_VERSION,
+            sections: Vec::new(),
+            docs: Vec::new(),
+            terms: BTreeMap::new(),
+        };
+        let mut facet_ids: HashMap<String, usize> = HashMap::new();
+        let mut facet = |key: &str, index: &mut SearchIndex| -> Option<usize> {
+            let section = sections.get(key)?;
+            if section.title.is_empty() {
+                return None;
+            }
+            Some(*facet_ids.entry(key.to_string()).or_insert_with(|| {
+                index.sections.push(section.title.clone());
+                index.sections.len() - 1
+            }))
+        };
+
+        // Sorted keys keep doc ids, and therefore the output, deterministic.
+        let mut section_keys: Vec<&String> = sections.keys().collect();
+        section_keys.sort();
+        for key in section_keys {
+            let section = &sections[key];
+            if opted_out(&section.extra) {
+                continue;
+            }
+            let facet_id = facet(key, &mut index);
+            index.add(
+                Entry {
+                    title: &section.title,
+                    path: &section.path,
+                    lang: &section.lang,
+                    description: section.description.as_deref(),
+                    html: &section.content,
+                    section: facet_id,
+                },
+                config,
+            );
+        }
+
+        let mut page_keys: Vec<&String> = pages.keys().collect();
+        page_keys.sort();
+        for key in page_keys {
+            let page = &pages[key];
+            if opted_out(&page.extra) {
+                continue;
+            }
+            let facet_id = facet(&content::page_section_key(page, sections), &mut index);
+            index.add(
+                Entry {
+                    title: &page.title,
+                    path: &page.path,
+                    lang: &page.lang,
+                    description: page.description.as_deref(),
+                    html: &page.content,
+                    section: facet_id,
+                },
+                config,
+            );
+        }
+
+        index
+    }
+
+    /// Serialize the index as compact JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("search index serialization should never fail")
+    }
+
+    fn add(&mut self, entry: Entry<'_>, config: &SearchConfig) {
+        let doc_id = self.docs.len() as u32;
+        let html = NON_TEXT_RE.replace_all(entry.html, " ");
+        let headings: Vec<String> = HEADING_RE
+            .captures_iter(&html)
+            .map(|caps| {
+                html_to_text(&caps[1])
+                    .trim_end_matches('#')
+                    .trim()
+                    .to_string()
+            })
+            .filter(|h| !h.is_empty())
+            .collect();
+        let body = html_to_text(&html);
+
+        let mut weights: HashMap<String, u32> = HashMap::new();
+        let mut add_field = |text: &str, weight: u32, cap: Option<u32>| {
+            let mut field: HashMap<String, u32> = HashMap::new();
+            for token in tokenize(text) {
+                *field.entry(token).or_default() += weight;
+            }
+            for (token, w) in field {
+                *weights.entry(token).or_default() += cap.map_or(w, |c| w.min(c));
+            }
+        };
+        add_field(entry.title, TITLE_WEIGHT, None);
+        for heading in &headings {
+            add_field(heading, HEADING_WEIGHT, None);
+        }
+        if let Some(description) = entry.description {
+            add_field(description, DESCRIPTION_WEIGHT, None);
+        }
+        if config.include_content {
+            add_field(&body, BODY_WEIGHT, Some(MAX_BODY_WEIGHT));
+        }
+        for (token, weight) in weights {
+            self.terms
+                .entry(token)
+                .or_default()
+                .extend([doc_id, weight]);
+        }
+
+        let snippet_source = entry
+            .description
+            .filter(|d| !d.trim().is_empty())
+            .unwrap_or(&body);
+        self.docs.push(SearchDocument {
+            title: entry.title.to_string(),
+            url: entry.path.to_string(),
+            section: entry.section,
+            lang: entry.lang.to_string(),
+            headings,
+            snippet: truncate_chars(snippet_source.trim(), config.snippet_length),
+        });
+    }
+}
+
+/// Fields of a page or section fed into the index.
+struct Entry<'a> {
+    title: &'a str,
+    path: &'a str,
+    lang: &'a str,
+    description: Option<&'a str>,
+    html: &'a str,
+    section: Option<usize>,
+}
+
+/// Whether frontmatter `[extra]` sets `search = false`.
+fn opted_out(extra: &serde_json::Value) -> bool {
+    extra.get("search").and_then(|v| v.as_bool()) == Some(false)
+}
+
+/// Split text into lowercase search tokens.
+///
+/// Tokens are runs of alphanumeric characters (any script), at least
+/// two characters long. The JS client applies the same rule to queries.
+pub fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= MIN_TOKEN_CHARS)
+        .map(str::to_lowercase)
+}
+
+/// Strip tags from an HTML fragment and decode common entities, collapsing
+/// whitespace.
+pub(crate) fn html_to_text(html: &str) -> String {
+    let text = TAG_RE.replace_all(html, " ");
+    let text = text
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&#x27;", "'")
+        .replace("&nbsp;", " ")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Truncate to at most `max` characters on a word boundary, adding `...`.
+fn truncate_chars(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+    let cut: String = text.chars().take(max).collect();
+    let cut = match cut.rfind(' ') {
+        Some(pos) if pos > 0 => &cut[..pos],
+        _ => cut.as_str(),
+    };
+    format!("{}...", cut.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::content::{Frontmatter, build_page, build_section};
+
+    fn page(rel: &str, title: &str, html: &str) -> Page {
+        let mut page = build_page(
+            Frontmatter {
+                title: Some(title.into()),
+                ..Default::default()
+            },
+            String::new(),
+            rel,
+            "https://example.com",
+        );
+        page.content = html.into();
+        page.lang = "en".into();
+        page
+    }
+
+    fn section(rel: &str, title: &str) -> Section {
+        let mut section = build_section(
+            Frontmatter {
+                title: Some(title.into()),
+                ..Default::default()
+            },
+            String::new(),
+            rel,
+            "https://example.com",
+        );
+        section.lang = "en".into();
+        section
+    }
+
+    fn postings<'a>(index: &'a SearchIndex, token: &str) -> &'a [u32] {
+        index.terms.get(token).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    #[test]
+    fn test_tokenize() {
+        let tokens: Vec<String> = tokenize("Hello, World! a x2 Ünïcode—text").collect();
+        assert_eq!(tokens, ["hello", "world", "x2", "ünïcode", "text"]);
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        assert_eq!(
+            html_to_text("<p>Fish &amp; <em>chips</em></p>\n<p>&lt;tag&gt;</p>"),
+            "Fish & chips <tag>"
+        );
+    }
+
+    #[test]
+    fn test_truncate_chars() {
+        assert_eq!(truncate_chars("short", 10), "short");
+        assert_eq!(truncate_chars("one two three four", 10), "one two...");
+    }
+
+    #[test]
+    fn test_build_index_weights_and_facets() {
+        let mut pages = HashMap::new();
+        pages.insert(
+            "posts/rust.md".to_string(),
+            page(
+                "posts/rust.md",
+                "Learning Rust",
+                "<h2 id=\"x\">Ownership</h2><p>Borrowing rules.</p><script>var rust = 1;</script>",
+            ),
+        );
+        pages.insert(
+            "posts/other.md".to_string(),
+            page("posts/other.md", "Other", "<p>Mentions rust once.</p>"),
+        );
+        let mut sections = HashMap::new();
+        sections.insert(
+            "posts/_index.md".to_string(),
+            section("posts/_index.md", "Blog"),
+        );
+
+        let index = SearchIndex::build(&pages, &sections, &SearchConfig::default());
+        assert_eq!(index.version, INDEX_VERSION);
+        assert_eq!(index.sections, ["Blog"]);
+        // Section first, then pages in key order
+        assert_eq!(index.docs[0].title, "Blog");
+        assert_eq!(index.docs[1].title, "Other");
+        assert_eq!(index.docs[2].title, "Learning Rust");
+        assert_eq!(index.docs[2].url, "/posts/rust/");
+        assert_eq!(index.docs[2].section, Some(0));
+        assert_eq!(index.docs[2].headings, ["Ownership"]);
+        assert_eq!(index.docs[2].snippet, "Ownership Borrowing rules.");
+
+        // Title match outweighs body match; script text is not indexed
+        let rust = postings(&index, "rust");
+        assert_eq!(rust.len(), 4);
+        let weight_of = |doc: u32| rust.chunks(2).find(|p| p[0] == doc).unwrap()[1];
+        assert_eq!(weight_of(2), TITLE_WEIGHT);
+        assert_eq!(weight_of(1), BODY_WEIGHT);
+        assert_eq!(
+            postings(&index, "ownership"),
+            [2, HEADING_WEIGHT + BODY_WEIGHT]
+        );
+    }
+
+    #[test]
+    fn test_build_index_without_content_and_opt_out() {
+        let mut pages = HashMap::new();
+        pages.insert("a.md".to_string(), page("a.md", "Alpha", "<p>bodyonly</p>"));
+        let mut hidden = page("b.md", "Hidden", "<p>secret</p>");
+        hidden.extra = serde_json::json!({"search": false});
+        pages.insert("b.md".to_string(), hidden);
+
+        let config = SearchConfig {
+            include_content: false,
+            ..Default::default()
+        };
+        let index = SearchIndex::build(&pages, &HashMap::new(), &config);
+        assert_eq!(index.docs.len(), 1);
+        assert_eq!(index.docs[0].section, None);
+        assert!(postings(&index, "bodyonly").is_empty());
+        assert!(postings(&index, "secret").is_empty());
+        assert_eq!(postings(&index, "alpha"), [0, TITLE_WEIGHT]);
+        let json = index.to_json();
+        assert!(json.starts_with(r#"{"version":1,"#), "got: {json}");
+    }
+}
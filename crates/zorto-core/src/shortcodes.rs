@@ -1,5 +1,5 @@
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 
@@ -13,6 +13,8 @@ static BODY_SHORTCODE_RE: LazyLock<Regex> = LazyLock::new(|| {
 static INLINE_SHORTCODE_RE: LazyLock<Regex> = LazyLock::new(|| {
     Regex::new(r#"\{\{\s*(\w+)\s*\(((?:[^)"']|"[^"]*"|'[^']*')*)\)\s*\}\}"#).unwrap()
 });
+static SHORTCODE_NAME_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{[{%]\s*(\w+)\s*\(").unwrap());
 static ARGS_DOUBLE_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"(\w+)\s*=\s*"([^"]*)""#).unwrap());
 static ARGS_SINGLE_RE: LazyLock<Regex> =
@@ -58,6 +60,46 @@ pub fn process_shortcodes(
     process_inline_shortcodes(&result, shortcode_dir, site_root, sandbox_root)
 }
 
+/// Collect the shortcodes invoked by `content` and the local files it pulls
+/// in via `include`, for incremental rebuild dependency tracking.
+///
+/// Included files are scanned one level deep so that shortcodes used inside
+/// an included snippet are attributed to the including page. Remote includes
+/// are not tracked (they are re-fetched on every full build).
+pub(crate) fn shortcode_dependencies(
+    content: &str,
+    site_root: &Path,
+) -> (BTreeSet<String>, BTreeSet<PathBuf>) {
+    let mut names: BTreeSet<String> = SHORTCODE_NAME_RE
+        .captures_iter(content)
+        .map(|caps| caps[1].to_string())
+        .collect();
+    let mut includes = BTreeSet::new();
+
+    for caps in INLINE_SHORTCODE_RE.captures_iter(content) {
+        if &caps[1] != "include" {
+            continue;
+        }
+        let Some(path) = parse_args(&caps[2]).remove("path") else {
+            continue;
+        };
+        if path.starts_with("http://") || path.starts_with("https://") {
+            continue;
+        }
+        let file = normalize_path(&site_root.join(&path));
+        if let Ok(included) = std::fs::read_to_string(&file) {
+            names.extend(
+                SHORTCODE_NAME_RE
+                    .captures_iter(&included)
+                    .map(|caps| caps[1].to_string()),
+            );
+        }
+        includes.insert(file);
+    }
+
+    (names, includes)
+}
+
 /// Process body shortcodes: {% name(args) %}...{% end %}
 fn process_body_shortcodes(
     content: &str,
@@ -1814,6 +1856,10 @@ fn config_display_name(name: &str) -> Option<(&'static str, &'static str)> {
         "MarkdownConfig" => Some(("[markdown]", "markdown")),
         "TaxonomyConfig" => Some(("[[taxonomies]]", "taxonomies")),
         "ContentDirConfig" => Some(("[[content_dirs]]", "content-dirs")),
+        "GeneratorConfig" => Some(("[[generators]]", "generators")),
+        "SearchConfig" => Some(("[search]", "search")),
+        "ImagesConfig" => Some(("[images]", "images")),
+        "DiagramsConfig" => Some(("[diagrams]", "diagrams")),
         _ => None,
     }
 }
@@ -1824,7 +1870,13 @@ fn clean_type(ty: &str) -> Option<String> {
     match ty {
         "String" => Some("string".to_string()),
         "bool" => Some("bool".to_string()),
-        "MarkdownConfig" | "Vec<TaxonomyConfig>" | "Vec<ContentDirConfig>" => None,
+        "MarkdownConfig"
+        | "Vec<TaxonomyConfig>"
+        | "Vec<ContentDirConfig>"
+        | "Vec<GeneratorConfig>"
+        | "SearchConfig"
+        | "ImagesConfig"
+        | "DiagramsConfig" => None,
         _ if ty.starts_with("Option<") && ty.ends_with('>') => {
             let inner = &ty[7..ty.len() - 1];
             clean_type(inner)
@@ -1834,8 +1886,11 @@ fn clean_type(ty: &str) -> Option<String> {
             clean_type(inner).map(|t| format!("{t}[]"))
         }
         "toml::Value" => Some("table".to_string()),
+        _ if ty.starts_with("BTreeMap<") => Some("table".to_string()),
         "AnchorLinks" => Some("string".to_string()),
         "SortBy" => Some("string".to_string()),
+        "ImageFormat" => Some("string".to_string()),
+        "MathMode" => Some("string".to_string()),
         _ => Some(ty.to_lowercase()),
     }
 }
@@ -1858,6 +1913,18 @@ fn derive_default(serde_attrs: &[String], ty: &str, is_option: bool) -> Option<S
         if attr.contains("default = \"default_section_html\"") {
             return Some("\"section.html\"".to_string());
         }
+        if attr.contains("default = \"default_toc_depth\"") {
+            return Some("6".to_string());
+        }
+        if attr.contains("default = \"default_image_widths\"") {
+            return Some("[480, 960, 1440]".to_string());
+        }
+        if attr.contains("default = \"default_image_quality\"") {
+            return Some("80".to_string());
+        }
+        if attr.contains("default = \"default_image_sizes\"") {
+            return Some("\"100vw\"".to_string());
+        }
         // Generic #[serde(default)] — derive from type
         if attr.contains("default") {
             let clean = ty.trim();
@@ -1879,6 +1946,9 @@ fn derive_default(serde_attrs: &[String], ty: &str, is_option: bool) -> Option<S
             if clean == "SortBy" {
                 return Some("\"date\"".to_string());
             }
+            if clean == "MathMode" {
+                return Some("\"none\"".to_string());
+            }
             return Some("\"\"".to_string());
         }
     }
@@ -1,7 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 
 use crate::config::Config;
 use crate::content::{self, Page, Section};
+use crate::data::DataLoader;
+use crate::deps;
+use crate::images::{ImageProcessor, ResizeOp};
 
 /// A taxonomy term for template rendering
 #[derive(Debug, Clone, serde::Serialize)]
@@ -29,10 +32,17 @@ pub struct Paginator {
 /// When a theme is configured, theme templates are loaded first as a base
 /// layer. Local templates from `templates_dir` then overlay and override
 /// any theme template with the same name.
+///
+/// `exec_data` holds each page's and section's `exec_data` by content path,
+/// for `get_exec_data()`; `loader` reads files for `load_data()`, and
+/// `images` processes them for `resize_image()` and `responsive_image()`.
 pub fn setup_tera(
     templates_dir: &std::path::Path,
     config: &Config,
     sections: &HashMap<String, Section>,
+    exec_data: &HashMap<String, tera::Value>,
+    loader: &DataLoader,
+    images: &ImageProcessor,
 ) -> anyhow::Result<tera::Tera> {
     let mut tera = tera::Tera::default();
 
@@ -89,7 +99,7 @@ pub fn setup_tera(
     }
 
     // Register custom functions
-    register_functions(&mut tera, config, sections);
+    register_functions(&mut tera, config, sections, exec_data, loader, images);
 
     // Register custom filters
     register_filters(&mut tera);
@@ -100,9 +110,63 @@ pub fn setup_tera(
     Ok(tera)
 }
 
-fn register_functions(tera: &mut tera::Tera, config: &Config, sections: &HashMap<String, Section>) {
+/// Build the template reference graph (template → templates it extends,
+/// includes or imports) for incremental rebuilds.
+///
+/// Mirrors the layering in [`setup_tera`]: theme templates first, then local
+/// templates from `templates_dir` replacing any theme template of the same name.
+pub(crate) fn template_graph(
+    templates_dir: &std::path::Path,
+    config: &Config,
+) -> anyhow::Result<HashMap<String, BTreeSet<String>>> {
+    let mut graph = HashMap::new();
+
+    if let Some(theme) = config
+        .theme
+        .as_deref()
+        .and_then(crate::themes::Theme::from_name)
+    {
+        for (name, source) in theme.templates() {
+            graph.insert(name.to_string(), deps::template_references(source));
+        }
+    }
+
+    if templates_dir.exists() {
+        for entry in walkdir::WalkDir::new(templates_dir) {
+            let entry = entry?;
+            let path = entry.path();
+            if !path.is_file() || path.extension().is_none_or(|ext| ext != "html") {
+                continue;
+            }
+            let name = path
+                .strip_prefix(templates_dir)?
+                .to_string_lossy()
+                .replace('\\', "/");
+            let source = std::fs::read_to_string(path)?;
+            graph.insert(name, deps::template_references(&source));
+        }
+    }
+
+    Ok(graph)
+}
+
+fn register_functions(
+    tera: &mut tera::Tera,
+    config: &Config,
+    sections: &HashMap<String, Section>,
+    exec_data: &HashMap<String, tera::Value>,
+    loader: &DataLoader,
+    images: &ImageProcessor,
+) {
     // get_url function
     let base_url = config.base_url.clone();
+    let default_language = config.default_language.clone();
+    let languages: Vec<String> = config
+        .language_codes()
+        .into_iter()
+        .filter(|code| *code != config.default_language)
+        .map(String::from)
+        .collect();
     tera.register_function(
         "get_url",
         move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
@@ -112,19 +176,41 @@ fn register_functions(tera: &mut tera::Tera, config: &Config, sections: &HashMap
                 .ok_or_else(|| tera::Error::msg("get_url requires a 'path' argument"))?;
 
             if let Some(content_path) = path.strip_prefix("@/") {
+                // `@/about.fr.md` and `@/about.md` with `lang="fr"` both
+                // resolve to the French variant.
+                let mut lang = args
+                    .get("lang")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(&default_language)
+                    .to_string();
+                let mut content_path = content_path.to_string();
+                for code in &languages {
+                    let stripped = content::strip_language_suffix(&content_path, code);
+                    if stripped != content_path {
+                        content_path = stripped;
+                        lang = code.clone();
+                        break;
+                    }
+                }
+                let prefix = if lang == default_language {
+                    String::new()
+                } else {
+                    format!("/{lang}")
+                };
                 let url = if content_path.ends_with("_index.md") {
                     let section_path =
-                        content::section_url_path(&content::parent_dir(content_path));
-                    format!("{base_url}{section_path}")
+                        content::section_url_path(&content::parent_dir(&content_path));
+                    format!("{base_url}{prefix}{section_path}")
                 } else {
-                    let stem = std::path::Path::new(content_path)
+                    let stem = std::path::Path::new(&content_path)
                         .file_stem()
                         .unwrap_or_default()
-                        .to_string_lossy();
-                    let slug = slug::slugify(stem.as_ref());
+                        .to_string_lossy()
+                        .to_string();
+                    let slug = slug::slugify(&stem);
                     let page_path =
-                        content::page_url_path(&content::parent_dir(content_path), &slug);
-                    format!("{base_url}{page_path}")
+                        content::page_url_path(&content::parent_dir(&content_path), &slug);
+                    format!("{base_url}{prefix}{page_path}")
                 };
                 Ok(tera::Value::String(url))
             } else {
@@ -159,8 +245,147 @@ fn register_functions(tera: &mut tera::Tera, config: &Config, sections: &HashMap
         },
     );
 
+    // get_exec_data function: values code blocks passed to templates, for
+    // one page or section (`path`), one of its values (`key`, else
+    // `default`), or all of them
+    let exec_data = exec_data.clone();
+    tera.register_function(
+        "get_exec_data",
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let path = args.get("path").and_then(|v| v.as_str());
+            let key = args.get("key").and_then(|v| v.as_str());
+            let Some(path) = path else {
+                if key.is_some() {
+                    return Err(tera::Error::msg(
+                        "get_exec_data requires a 'path' argument with 'key'",
+                    ));
+                }
+                return Ok(tera::Value::Object(
+                    exec_data
+                        .iter()
+                        .map(|(path, data)| (path.clone(), data.clone()))
+                        .collect(),
+                ));
+            };
+            let path = path.strip_prefix("@/").unwrap_or(path);
+            let data = exec_data
+                .get(path)
+                .ok_or_else(|| tera::Error::msg(format!("Content not found: {path}")))?;
+            match key {
+                None => Ok(data.clone()),
+                Some(key) => data
+                    .get(key)
+                    .or(args.get("default"))
+                    .cloned()
+                    .ok_or_else(|| {
+                        tera::Error::msg(format!(
+                            "get_exec_data: no code block in {path} set '{key}'"
+                        ))
+                    }),
+            }
+        },
+    );
+
+    // load_data function: parse a data file (Zola-compatible); a missing
+    // file is an error unless `required=false`, which returns null
+    let loader = loader.clone();
+    tera.register_function(
+        "load_data",
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let path = args
+                .get("path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| tera::Error::msg("load_data requires a 'path' argument"))?;
+            let format = args.get("format").and_then(|v| v.as_str());
+            let required = args
+                .get("required")
+                .and_then(|v| v.as_bool())
+                .unwrap_or(true);
+            match loader.load(path, format) {
+                Ok(Some(value)) => Ok(value),
+                Ok(None) if !required => Ok(tera::Value::Null),
+                Ok(None) => Err(tera::Error::msg(format!(
+                    "load_data: file not found: {path}"
+                ))),
+                Err(e) => Err(tera::Error::msg(format!("load_data: {e}"))),
+            }
+        },
+    );
+
+    // resize_image function: a resized or converted copy of an image
+    // (Zola-compatible)
+    let processor = images.clone();
+    tera.register_function(
+        "resize_image",
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let path = args
+                .get("path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| tera::Error::msg("resize_image requires a 'path' argument"))?;
+            let dimension = |name: &str| -> tera::Result<Option<u32>> {
+                args.get(name)
+                    .map(|v| {
+                        v.as_u64()
+                            .and_then(|n| u32::try_from(n).ok())
+                            .ok_or_else(|| {
+                                tera::Error::msg(format!(
+                                    "resize_image: '{name}' must be a positive integer"
+                                ))
+                            })
+                    })
+                    .transpose()
+            };
+            let (width, height) = (dimension("width")?, dimension("height")?);
+            let op = args.get("op").and_then(|v| v.as_str()).unwrap_or("fill");
+            let format = args.get("format").and_then(|v| v.as_str());
+            let quality = args
+                .get("quality")
+                .and_then(|v| v.as_u64())
+                .map(|q| q.min(u8::MAX as u64) as u8);
+            let (resized, (orig_width, orig_height)) = ResizeOp::from_name(op)
+                .and_then(|op| processor.resize_image(path, op, width, height, format, quality))
+                .map_err(|e| tera::Error::msg(format!("resize_image: {e}")))?;
+            Ok(serde_json::json!({
+                "url": resized.url,
+                "static_path": resized.image.path,
+                "width": resized.width,
+                "height": resized.height,
+                "orig_width": orig_width,
+                "orig_height": orig_height,
+            }))
+        },
+    );
+
+    // responsive_image function: an <img> tag offering resized variants of
+    // an image through `srcset`, as `[images] responsive` does for content
+    let processor = images.clone();
+    tera.register_function(
+        "responsive_image",
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let path = args
+                .get("path")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| tera::Error::msg("responsive_image requires a 'path' argument"))?;
+            let mut attrs = String::new();
+            for name in ["alt", "class", "sizes", "loading"] {
+                let value = match (name, args.get(name).and_then(|v| v.as_str())) {
+                    (_, Some(value)) => value,
+                    ("alt", None) => "",
+                    ("loading", None) => "lazy",
+                    _ => continue,
+                };
+                attrs.push_str(&format!(" {name}=\"{}\"", content::escape_html(value)));
+            }
+            processor
+                .responsive_image(path, &attrs)
+                .map(tera::Value::String)
+                .map_err(|e| tera::Error::msg(format!("responsive_image: {e}")))
+        },
+    );
+
     // get_taxonomy_url function
     let base_url2 = config.base_url.clone();
+    let default_language2 = config.default_language.clone();
     tera.register_function(
         "get_taxonomy_url",
         move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
@@ -173,12 +398,50 @@ fn register_functions(tera: &mut tera::Tera, config: &Config, sections: &HashMap
                 .and_then(|v| v.as_str())
                 .ok_or_else(|| tera::Error::msg("get_taxonomy_url requires 'name'"))?;
 
+            let prefix = match args.get("lang").and_then(|v| v.as_str()) {
+                Some(lang) if lang != default_language2 => format!("/{lang}"),
+                _ => String::new(),
+            };
+
             let slug = slug::slugify(name);
-            let url = format!("{}/{kind}/{slug}/", base_url2);
+            let url = format!("{}{prefix}/{kind}/{slug}/", base_url2);
             Ok(tera::Value::String(url))
         },
     );
 
+    // trans() function: look up a string in `[languages.<lang>.translations]`,
+    // falling back to the default language's table.
+    let default_language3 = config.default_language.clone();
+    let string_tables: HashMap<String, HashMap<String, String>> = config
+        .languages
+        .iter()
+        .map(|(code, lang)| (code.clone(), lang.translations.clone()))
+        .collect();
+    tera.register_function(
+        "trans",
+        move |args: &HashMap<String, tera::Value>| -> tera::Result<tera::Value> {
+            let key = args
+                .get("key")
+                .and_then(|v| v.as_str())
+                .ok_or_else(|| tera::Error::msg("trans requires a 'key' argument"))?;
+            let lang = args
+                .get("lang")
+                .and_then(|v| v.as_str())
+                .unwrap_or(&default_language3);
+
+            [lang, default_language3.as_str()]
+                .iter()
+                .find_map(|code| string_tables.get(*code)?.get(key))
+                .map(|text| tera::Value::String(text.clone()))
+                .ok_or_else(|| {
+                    tera::Error::msg(format!(
+                        "trans: no translation for key '{key}' in language '{lang}' \
+                         (add it under [languages.{lang}.translations] in config.toml)"
+                    ))
+                })
+        },
+    );
+
     // now() function
     tera.register_function(
         "now",
@@ -271,9 +534,11 @@ fn register_tests(tera: &mut tera::Tera) {
 
 /// Build Tera context for a page template
 pub fn page_context(page: &Page, config: &Config) -> tera::Context {
+    let lang = context_language(&page.lang, config);
     let mut ctx = tera::Context::new();
     ctx.insert("page", page);
-    ctx.insert("config", &config_to_value(config));
+    ctx.insert("config", &localized_config_value(config, lang));
+    ctx.insert("lang", lang);
     ctx.insert("section", &tera::Value::Null);
     ctx
 }
@@ -284,9 +549,11 @@ pub fn section_context(
     config: &Config,
     paginator: Option<&Paginator>,
 ) -> tera::Context {
+    let lang = context_language(&section.lang, config);
     let mut ctx = tera::Context::new();
     ctx.insert("section", section);
-    ctx.insert("config", &config_to_value(config));
+    ctx.insert("config", &localized_config_value(config, lang));
+    ctx.insert("lang", lang);
     ctx.insert("page", &tera::Value::Null);
     if let Some(pag) = paginator {
         ctx.insert("paginator", pag);
@@ -295,20 +562,24 @@ pub fn section_context(
 }
 
 /// Build Tera context for taxonomy list template
-pub fn taxonomy_list_context(terms: &[TaxonomyTerm], config: &Config) -> tera::Context {
+pub fn taxonomy_list_context(terms: &[TaxonomyTerm], config: &Config, lang: &str) -> tera::Context {
+    let lang = context_language(lang, config);
     let mut ctx = tera::Context::new();
     ctx.insert("terms", terms);
-    ctx.insert("config", &config_to_value(config));
+    ctx.insert("config", &localized_config_value(config, lang));
+    ctx.insert("lang", lang);
     ctx.insert("page", &tera::Value::Null);
     ctx.insert("section", &tera::Value::Null);
     ctx
 }
 
 /// Build Tera context for taxonomy single template
-pub fn taxonomy_single_context(term: &TaxonomyTerm, config: &Config) -> tera::Context {
+pub fn taxonomy_single_context(term: &TaxonomyTerm, config: &Config, lang: &str) -> tera::Context {
+    let lang = context_language(lang, config);
     let mut ctx = tera::Context::new();
     ctx.insert("term", term);
-    ctx.insert("config", &config_to_value(config));
+    ctx.insert("config", &localized_config_value(config, lang));
+    ctx.insert("lang", lang);
     ctx.insert("page", &tera::Value::Null);
     ctx.insert("section", &tera::Value::Null);
     ctx
@@ -319,6 +590,25 @@ pub fn config_to_value(config: &Config) -> serde_json::Value {
     serde_json::to_value(config).expect("Config serialization should never fail")
 }
 
+/// `lang` for the template context: the content's language, or the site's
+/// default language when unset.
+fn context_language<'a>(lang: &'a str, config: &'a Config) -> &'a str {
+    if lang.is_empty() {
+        &config.default_language
+    } else {
+        lang
+    }
+}
+
+/// [`config_to_value`] with `title` and `description` replaced by their
+/// `[languages.<lang>]` values, so templates can keep using `config.title`.
+fn localized_config_value(config: &Config, lang: &str) -> serde_json::Value {
+    let mut value = config_to_value(config);
+    value["title"] = config.title_for(lang).into();
+    value["description"] = config.description_for(lang).into();
+    value
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -366,6 +656,15 @@ author = "Tester"
         )
     }
 
+    fn loader() -> DataLoader {
+        DataLoader::new(std::path::Path::new("."), std::path::Path::new("."))
+    }
+
+    fn images() -> ImageProcessor {
+        let path = std::path::Path::new(".");
+        ImageProcessor::new(path, path, &minimal_config())
+    }
+
     #[test]
     fn test_config_to_value_fields() {
         let config = minimal_config();
@@ -426,7 +725,15 @@ author = "Tester"
         std::fs::write(tmpl_dir.join("test.html"), "{{ count | pluralize }}").unwrap();
         let config = minimal_config();
         let sections = HashMap::new();
-        let tera = setup_tera(&tmpl_dir, &config, &sections).unwrap();
+        let tera = setup_tera(
+            &tmpl_dir,
+            &config,
+            &sections,
+            &HashMap::new(),
+            &loader(),
+            &images(),
+        )
+        .unwrap();
         let mut ctx = tera::Context::new();
         ctx.insert("count", &1);
         let result = tera.render("test.html", &ctx).unwrap();
@@ -436,6 +743,115 @@ author = "Tester"
         assert_eq!(result, "s");
     }
 
+    #[test]
+    fn test_get_exec_data_function() {
+        let tmp = TempDir::new().unwrap();
+        let tmpl_dir = tmp.path().join("templates");
+        std::fs::create_dir_all(&tmpl_dir).unwrap();
+        let templates = [
+            (
+                "value.html",
+                r#"{{ get_exec_data(path="@/stats.md", key="users") }}"#,
+            ),
+            (
+                "page.html",
+                r#"{{ get_exec_data(path="stats.md") | json_encode() | safe }}"#,
+            ),
+            (
+                "all.html",
+                r#"{% for path, data in get_exec_data() %}{{ path }};{% endfor %}"#,
+            ),
+            (
+                "missing_key.html",
+                r#"{{ get_exec_data(path="stats.md", key="x") }}"#,
+            ),
+            (
+                "missing_page.html",
+                r#"{{ get_exec_data(path="nope.md") }}"#,
+            ),
+            ("key_only.html", r#"{{ get_exec_data(key="users") }}"#),
+            (
+                "default.html",
+                r#"{{ get_exec_data(path="_index.md", key="users", default="-") }}"#,
+            ),
+        ];
+        for (name, body) in templates {
+            std::fs::write(tmpl_dir.join(name), body).unwrap();
+        }
+        let exec_data = HashMap::from([
+            ("stats.md".to_string(), serde_json::json!({"users": 1234})),
+            ("_index.md".to_string(), serde_json::json!({})),
+        ]);
+        let tera = setup_tera(
+            &tmpl_dir,
+            &minimal_config(),
+            &HashMap::new(),
+            &exec_data,
+            &loader(),
+            &images(),
+        )
+        .unwrap();
+        let ctx = tera::Context::new();
+        let render = |name: &str| tera.render(name, &ctx);
+
+        assert_eq!(render("value.html").unwrap(), "1234");
+        assert_eq!(render("page.html").unwrap(), r#"{"users":1234}"#);
+        assert_eq!(render("all.html").unwrap(), "_index.md;stats.md;");
+        assert_eq!(render("default.html").unwrap(), "-");
+        for name in ["missing_key.html", "missing_page.html", "key_only.html"] {
+            assert!(render(name).is_err(), "{name} should fail");
+        }
+    }
+
+    #[test]
+    fn test_load_data_function() {
+        let tmp = TempDir::new().unwrap();
+        let root = tmp.path().join("site");
+        let tmpl_dir = root.join("templates");
+        std::fs::create_dir_all(&tmpl_dir).unwrap();
+        std::fs::write(root.join("team.csv"), "name,role\nAda,lead\n").unwrap();
+        std::fs::write(tmp.path().join("outside.toml"), "x = 1\n").unwrap();
+        let templates = [
+            (
+                "csv.html",
+                r#"{% set team = load_data(path="team.csv") %}{{ team.headers | join(sep=",") }}:{{ team.records[0][0] }}"#,
+            ),
+            (
+                "plain.html",
+                r#"{{ load_data(path="team.csv", format="plain") | length }}"#,
+            ),
+            (
+                "optional.html",
+                r#"{% set missing = load_data(path="missing.toml", required=false) %}{% if not missing %}none{% endif %}"#,
+            ),
+            ("missing.html", r#"{{ load_data(path="missing.toml") }}"#),
+            ("escape.html", r#"{{ load_data(path="../outside.toml") }}"#),
+        ];
+        for (name, body) in templates {
+            std::fs::write(tmpl_dir.join(name), body).unwrap();
+        }
+        let loader = DataLoader::new(&root, &root);
+        let tera = setup_tera(
+            &tmpl_dir,
+            &minimal_config(),
+            &HashMap::new(),
+            &HashMap::new(),
+            &loader,
+            &images(),
+        )
+        .unwrap();
+        let ctx = tera::Context::new();
+        let render = |name: &str| tera.render(name, &ctx);
+
+        assert_eq!(render("csv.html").unwrap(), "name,role:Ada");
+        assert_eq!(render("plain.html").unwrap(), "19");
+        assert_eq!(render("optional.html").unwrap(), "none");
+        for name in ["missing.html", "escape.html"] {
+            assert!(render(name).is_err(), "{name} should fail");
+        }
+        assert_eq!(loader.loaded_files().len(), 1);
+    }
+
     #[test]
     fn test_slice_filter() {
         let tmp = TempDir::new().unwrap();
@@ -448,7 +864,15 @@ author = "Tester"
         .unwrap();
         let config = minimal_config();
         let sections = HashMap::new();
-        let tera = setup_tera(&tmpl_dir, &config, &sections).unwrap();
+        let tera = setup_tera(
+            &tmpl_dir,
+            &config,
+            &sections,
+            &HashMap::new(),
+            &loader(),
+            &images(),
+        )
+        .unwrap();
         let mut ctx = tera::Context::new();
         ctx.insert("items", &vec!["a", "b", "c", "d"]);
         let result = tera.render("test.html", &ctx).unwrap();
@@ -467,7 +891,15 @@ author = "Tester"
         .unwrap();
         let config = minimal_config();
         let sections = HashMap::new();
-        let tera = setup_tera(&tmpl_dir, &config, &sections).unwrap();
+        let tera = setup_tera(
+            &tmpl_dir,
+            &config,
+            &sections,
+            &HashMap::new(),
+            &loader(),
+            &images(),
+        )
+        .unwrap();
         let mut ctx = tera::Context::new();
         ctx.insert("d", "2025-06-15");
         let result = tera.render("test.html", &ctx).unwrap();
@@ -486,7 +918,15 @@ author = "Tester"
         .unwrap();
         let config = minimal_config();
         let sections = HashMap::new();
-        let tera = setup_tera(&tmpl_dir, &config, &sections).unwrap();
+        let tera = setup_tera(
+            &tmpl_dir,
+            &config,
+            &sections,
+            &HashMap::new(),
+            &loader(),
+            &images(),
+        )
+        .unwrap();
         let mut ctx = tera::Context::new();
         ctx.insert("path", "/blog/post");
         assert_eq!(tera.render("test.html", &ctx).unwrap(), "yes");
@@ -506,7 +946,15 @@ author = "Tester"
         .unwrap();
         let config = minimal_config();
         let sections = HashMap::new();
-        let tera = setup_tera(&tmpl_dir, &config, &sections).unwrap();
+        let tera = setup_tera(
+            &tmpl_dir,
+            &config,
+            &sections,
+            &HashMap::new(),
+            &loader(),
+            &images(),
+        )
+        .unwrap();
         let ctx = tera::Context::new();
         let result = tera.render("test.html", &ctx).unwrap();
         assert_eq!(result, "https://example.com/posts/hello/");
@@ -524,9 +972,106 @@ author = "Tester"
         .unwrap();
         let config = minimal_config();
         let sections = HashMap::new();
-        let tera = setup_tera(&tmpl_dir, &config, &sections).unwrap();
+        let tera = setup_tera(
+            &tmpl_dir,
+            &config,
+            &sections,
+            &HashMap::new(),
+            &loader(),
+            &images(),
+        )
+        .unwrap();
         let ctx = tera::Context::new();
         let result = tera.render("test.html", &ctx).unwrap();
         assert_eq!(result, "https://example.com/img/photo.png");
     }
+
+    #[test]
+    fn test_trans_and_localized_urls() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(
+            tmp.path().join("config.toml"),
+            r#"
+base_url = "https://example.com"
+
+[languages.en.translations]
+read_more = "Read more"
+only_en = "English only"
+
+[languages.fr.translations]
+read_more = "Lire la suite"
+"#,
+        )
+        .unwrap();
+        let config = Config::load(tmp.path()).unwrap();
+        let tmpl_dir = tmp.path().join("templates");
+        std::fs::create_dir_all(&tmpl_dir).unwrap();
+        std::fs::write(
+            tmpl_dir.join("test.html"),
+            r#"{{ trans(key="read_more", lang=lang) }}|{{ trans(key="only_en", lang=lang) }}|{{ get_url(path="@/about.md", lang=lang) | safe }}|{{ get_url(path="@/about.fr.md") | safe }}|{{ get_taxonomy_url(kind="tags", name="Rust", lang=lang) | safe }}"#,
+        )
+        .unwrap();
+        let tera = setup_tera(
+            &tmpl_dir,
+            &config,
+            &HashMap::new(),
+            &HashMap::new(),
+            &loader(),
+            &images(),
+        )
+        .unwrap();
+
+        let mut ctx = tera::Context::new();
+        ctx.insert("lang", "fr");
+        assert_eq!(
+            tera.render("test.html", &ctx).unwrap(),
+            "Lire la suite|English only|https://example.com/fr/about/|https://example.com/fr/about/|https://example.com/fr/tags/rust/"
+        );
+
+        ctx.insert("lang", "en");
+        assert_eq!(
+            tera.render("test.html", &ctx).unwrap(),
+            "Read more|English only|https://example.com/about/|https://example.com/fr/about/|https://example.com/tags/rust/"
+        );
+
+        std::fs::write(tmpl_dir.join("missing.html"), r#"{{ trans(key="nope") }}"#).unwrap();
+        let tera = setup_tera(
+            &tmpl_dir,
+            &config,
+            &HashMap::new(),
+            &HashMap::new(),
+            &loader(),
+            &images(),
+        )
+        .unwrap();
+        assert!(tera.render("missing.html", &tera::Context::new()).is_err());
+    }
+
+    #[test]
+    fn test_page_context_localizes_config() {
+        let tmp = TempDir::new().unwrap();
+        std::fs::write(
+            tmp.path().join("config.toml"),
+            r#"
+base_url = "https://example.com"
+title = "My Site"
+
+[languages.fr]
+title = "Mon site"
+"#,
+        )
+        .unwrap();
+        let config = Config::load(tmp.path()).unwrap();
+        let mut page = minimal_page();
+        page.lang = "fr".into();
+        let ctx = page_context(&page, &config);
+        let json = ctx.into_json();
+        assert_eq!(json["lang"], "fr");
+        assert_eq!(json["config"]["title"], "Mon site");
+
+        page.lang = String::new();
+        let json = page_context(&page, &config).into_json();
+        assert_eq!(json["lang"], "en");
+        assert_eq!(json["config"]["title"], "My Site");
+    }
 }
@@ -182,6 +182,10 @@ impl Theme {
         "tags/single.html",
         include_str!("../themes/zorto/templates/tags/single.html"),
     );
+    const SEARCH_PARTIAL_HTML: (&'static str, &'static str) = (
+        "partials/search.html",
+        include_str!("../themes/zorto/templates/partials/search.html"),
+    );
 
     /// Get all template files for this theme as `(name, content)` pairs.
     ///
@@ -199,6 +203,7 @@ impl Theme {
             Self::POST_MACRO_HTML,
             Self::TAGS_LIST_HTML,
             Self::TAGS_SINGLE_HTML,
+            Self::SEARCH_PARTIAL_HTML,
         ]
     }
 
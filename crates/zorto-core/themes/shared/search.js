@@ -0,0 +1,241 @@
+// Zorto built-in search client.
+//
+// Queries the `search_index.json` written by `zorto build` when
+// `[search] enable = true`. Exposes `window.ZortoSearch` for custom UIs and
+// wires up the `partials/search.html` modal when it is on the page.
+(function () {
+  'use strict';
+
+  var MIN_TOKEN_CHARS = 2;
+  var MAX_PREFIX_TERMS = 50;
+
+  // Same rule as the Rust indexer: runs of letters/digits, lowercased.
+  function tokenize(text) {
+    return String(text || '')
+      .toLowerCase()
+      .split(/[^\p{L}\p{N}]+/u)
+      .filter(function (t) { return Array.from(t).length >= MIN_TOKEN_CHARS; });
+  }
+
+  var cache = {};
+  function load(url) {
+    if (!cache[url]) {
+      cache[url] = fetch(url).then(function (r) {
+        if (!r.ok) throw new Error('failed to load search index: ' + r.status);
+        return r.json();
+      }).then(function (index) {
+        index.keys = Object.keys(index.terms).sort();
+        return index;
+      });
+    }
+    return cache[url];
+  }
+
+  // Postings for a query token. The last token of a query also matches as a
+  // prefix so results update while typing.
+  function postingsFor(index, token, prefix) {
+    var scores = {};
+    function add(term, factor) {
+      var postings = index.terms[term];
+      if (!postings) return;
+      for (var i = 0; i < postings.length; i += 2) {
+        scores[postings[i]] = (scores[postings[i]] || 0) + postings[i + 1] * factor;
+      }
+    }
+    add(token, 1);
+    if (prefix) {
+      var keys = index.keys;
+      var lo = 0, hi = keys.length;
+      while (lo < hi) {
+        var mid = (lo + hi) >> 1;
+        if (keys[mid] < token) lo = mid + 1; else hi = mid;
+      }
+      for (var n = 0; lo < keys.length && n < MAX_PREFIX_TERMS; lo++, n++) {
+        if (keys[lo].lastIndexOf(token, 0) !== 0) break;
+        if (keys[lo] !== token) add(keys[lo], 0.5);
+      }
+    }
+    return scores;
+  }
+
+  // Rank documents matching every query token.
+  // Options: `lang` and `section` restrict results, `limit` caps them.
+  function query(index, text, options) {
+    options = options || {};
+    var tokens = tokenize(text);
+    if (tokens.length === 0) return [];
+    var totals = null;
+    tokens.forEach(function (token, i) {
+      var scores = postingsFor(index, token, i === tokens.length - 1);
+      if (totals === null) {
+        totals = scores;
+        return;
+      }
+      var next = {};
+      Object.keys(totals).forEach(function (doc) {
+        if (scores[doc]) next[doc] = totals[doc] + scores[doc];
+      });
+      totals = next;
+    });
+    var results = [];
+    Object.keys(totals).forEach(function (id) {
+      var doc = index.docs[id];
+      if (options.lang && doc.l !== options.lang) return;
+      var section = doc.s === null ? null : index.sections[doc.s];
+      if (options.section && section !== options.section) return;
+      results.push({
+        title: doc.t,
+        url: doc.u,
+        section: section,
+        lang: doc.l,
+        headings: doc.h,
+        snippet: doc.d,
+        score: totals[id]
+      });
+    });
+    results.sort(function (a, b) { return b.score - a.score || a.title.localeCompare(b.title); });
+    return results.slice(0, options.limit || 10);
+  }
+
+  window.ZortoSearch = { tokenize: tokenize, load: load, query: query };
+
+  // --- Modal UI (partials/search.html) ---
+
+  var modal = document.getElementById('search-modal');
+  if (!modal || !modal.dataset.index) return;
+  var input = document.getElementById('search-input');
+  var resultsEl = document.getElementById('search-results');
+  var statusEl = document.getElementById('search-status');
+  var backdrop = modal.querySelector('.search-modal__backdrop');
+  var baseUrl = (modal.dataset.base || '/').replace(/\/$/, '');
+  var lang = modal.dataset.lang || '';
+  var debounceTimer = null;
+  var selectedIdx = -1;
+
+  function escapeHtml(s) {
+    var d = document.createElement('div');
+    d.appendChild(document.createTextNode(s));
+    return d.innerHTML;
+  }
+
+  function highlight(text, tokens) {
+    var html = escapeHtml(text);
+    if (tokens.length === 0) return html;
+    var re = new RegExp('(' + tokens.map(function (t) {
+      return t.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
+    }).join('|') + ')', 'giu');
+    return html.replace(re, '<mark>$1</mark>');
+  }
+
+  function openModal() {
+    modal.removeAttribute('hidden');
+    input.value = '';
+    resultsEl.innerHTML = '';
+    statusEl.innerHTML = '';
+    selectedIdx = -1;
+    // Focus before animation — must be synchronous for mobile Safari
+    input.focus({ preventScroll: true });
+    requestAnimationFrame(function () { modal.classList.add('search-modal--visible'); });
+    load(modal.dataset.index).catch(function (err) {
+      console.error(err);
+      resultsEl.innerHTML = '<div class="search-modal__empty">Failed to load search index.</div>';
+    });
+  }
+
+  function closeModal() {
+    modal.classList.remove('search-modal--visible');
+    setTimeout(function () {
+      modal.setAttribute('hidden', '');
+      input.value = '';
+      resultsEl.innerHTML = '';
+      statusEl.innerHTML = '';
+      selectedIdx = -1;
+    }, 200);
+  }
+
+  function getResultLinks() {
+    return resultsEl.querySelectorAll('.search-modal__result');
+  }
+
+  function updateSelection() {
+    var links = getResultLinks();
+    links.forEach(function (el, i) {
+      el.classList.toggle('search-modal__result--selected', i === selectedIdx);
+    });
+    if (selectedIdx >= 0 && links[selectedIdx]) {
+      links[selectedIdx].scrollIntoView({ block: 'nearest' });
+    }
+  }
+
+  function doSearch(text) {
+    selectedIdx = -1;
+    if (!text.trim()) {
+      resultsEl.innerHTML = '';
+      statusEl.textContent = '';
+      return;
+    }
+    load(modal.dataset.index).then(function (index) {
+      var tokens = tokenize(text);
+      var results = query(index, text, { lang: lang });
+      if (results.length === 0) {
+        statusEl.textContent = '';
+        resultsEl.innerHTML = '<div class="search-modal__empty">No results found.</div>';
+        return;
+      }
+      statusEl.textContent = results.length + ' result' + (results.length === 1 ? '' : 's');
+      resultsEl.innerHTML = results.map(function (r) {
+        var where = r.section ? escapeHtml(r.section) + ' &middot; ' : '';
+        return '<a class="search-modal__result" href="' + escapeHtml(baseUrl + r.url) + '">' +
+          '<div class="search-modal__result-title">' + highlight(r.title, tokens) + '</div>' +
+          '<div class="search-modal__result-url">' + where + escapeHtml(r.url) + '</div>' +
+          '<div class="search-modal__result-snippet">' + highlight(r.snippet, tokens) + '</div>' +
+          '</a>';
+      }).join('');
+    });
+  }
+
+  var toggleBtn = document.querySelector('.search-toggle');
+  if (toggleBtn) toggleBtn.addEventListener('click', openModal);
+
+  document.addEventListener('keydown', function (e) {
+    if ((e.ctrlKey || e.metaKey) && e.key === 'k') {
+      e.preventDefault();
+      if (modal.hasAttribute('hidden')) openModal(); else closeModal();
+      return;
+    }
+    if (modal.hasAttribute('hidden')) return;
+    if (e.key === 'Escape') {
+      closeModal();
+      return;
+    }
+    var links = getResultLinks();
+    if (e.key === 'ArrowDown') {
+      e.preventDefault();
+      if (links.length > 0) {
+        selectedIdx = (selectedIdx + 1) % links.length;
+        updateSelection();
+      }
+    } else if (e.key === 'ArrowUp') {
+      e.preventDefault();
+      if (links.length > 0) {
+        selectedIdx = selectedIdx <= 0 ? links.length - 1 : selectedIdx - 1;
+        updateSelection();
+      }
+    } else if (e.key === 'Enter') {
+      if (selectedIdx >= 0 && links[selectedIdx]) {
+        e.preventDefault();
+        links[selectedIdx].click();
+      }
+    }
+  });
+
+  backdrop.addEventListener('click', closeModal);
+  var closeBtn = document.getElementById('search-close');
+  if (closeBtn) closeBtn.addEventListener('click', closeModal);
+
+  input.addEventListener('input', function () {
+    var text = input.value;
+    clearTimeout(debounceTimer);
+    debounceTimer = setTimeout(function () { doSearch(text); }, 100);
+  });
+})();
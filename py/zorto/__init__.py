@@ -23,6 +23,7 @@ __all__ = [
     "Page",
     "run_cli",
     "Section",
+    "set",
     "Site",
     "version",
 ]
@@ -38,6 +39,18 @@ def run_cli(argv: list[str] | None = None) -> None:
         sys.exit(130)
 
 
+def set(key: str, value: object) -> None:
+    """Make `value` available to templates as `page.exec_data[key]`.
+
+    Only works inside an executable code block.
+    """
+    try:
+        from zorto import _exec
+    except ImportError:
+        raise RuntimeError("zorto.set() only works inside an executable code block") from None
+    _exec.set(key, value)
+
+
 def main() -> None:
     """CLI entry point."""
     run_cli()
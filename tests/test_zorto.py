@@ -18,6 +18,17 @@ def test_version_is_semver():
     assert all(p.isdigit() for p in parts)
 
 
+# -- set ----------------------------------------------------------------------
+
+
+def test_set_outside_code_block_fails():
+    try:
+        zorto.set("key", 1)
+        assert False, "Expected RuntimeError"
+    except RuntimeError:
+        pass
+
+
 # -- load (using the bundled website/) ----------------------------------------
 
 